@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Longest request excerpt quoted in a replay miss.
+const MISS_PREVIEW_CHARS: usize = 300;
+
+/// One line of a fixture file.
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureEntry<Req, Resp> {
+    key: String,
+    request: Req,
+    response: Resp,
+}
+
+/// The hash a request is recorded and replayed under.
+pub fn fixture_key<Req: Serialize>(request: &Req) -> Result<String> {
+    Ok(format!("{:x}", md5::compute(serde_json::to_string(request)?.as_bytes())))
+}
+
+/// Appends request/response pairs to a JSONL fixture file, one per line,
+/// flushed as they come so an interrupted run keeps what it recorded.
+pub struct FixtureWriter {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl FixtureWriter {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open fixture file {}", path.display()))?;
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    pub fn record<Req: Serialize, Resp: Serialize>(&self, request: &Req, response: &Resp) -> Result<()> {
+        let mut line = serde_json::to_vec(&FixtureEntry { key: fixture_key(request)?, request, response })?;
+        line.push(b'\n');
+        let mut file = self.file.lock().expect("fixture file poisoned");
+        file.write_all(&line).with_context(|| format!("failed to write fixture file {}", self.path.display()))?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Recorded responses by request hash; a request recorded twice replays its
+/// last response.
+pub struct Fixtures<Resp> {
+    path: PathBuf,
+    responses: HashMap<String, Resp>,
+}
+
+impl<Resp: DeserializeOwned + Clone> Fixtures<Resp> {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).with_context(|| format!("failed to open fixture file {}", path.display()))?;
+        let mut responses = HashMap::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: FixtureEntry<serde::de::IgnoredAny, Resp> = serde_json::from_str(&line)
+                .with_context(|| format!("invalid fixture at {}:{}", path.display(), n + 1))?;
+            responses.insert(entry.key, entry.response);
+        }
+        Ok(Self { path, responses })
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    /// The recorded response, or an error quoting the unrecorded request.
+    pub fn get<Req: Serialize>(&self, request: &Req) -> Result<Resp> {
+        let key = fixture_key(request)?;
+        self.responses.get(&key).cloned().ok_or_else(|| {
+            let request = serde_json::to_string(request).unwrap_or_default();
+            let preview: String = request.chars().take(MISS_PREVIEW_CHARS).collect();
+            anyhow!("no recorded response for request {key} in {}: {preview}", self.path.display())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_the_last_response_recorded_for_a_request() {
+        let dir = std::env::temp_dir().join(format!("veriscore-common-fixtures-{}", std::process::id()));
+        let path = dir.join("calls.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        let writer = FixtureWriter::create(&path).unwrap();
+        writer.record(&("q", 1), &"first").unwrap();
+        writer.record(&("q", 2), &"other").unwrap();
+        writer.record(&("q", 1), &"second").unwrap();
+
+        let fixtures = Fixtures::<String>::load(&path).unwrap();
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures.get(&("q", 1)).unwrap(), "second");
+        let err = fixtures.get(&("q", 3)).unwrap_err();
+        assert!(err.to_string().contains(r#"["q",3]"#), "{err}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Latencies kept for [`HedgeDelay::Quantile`].
+const LATENCY_WINDOW: usize = 512;
+/// Calls seen before a quantile delay starts hedging.
+const MIN_SAMPLES: usize = 20;
+/// Most hedges the load budget can save up while calls are fast.
+const MAX_BURST: f64 = 10.0;
+
+/// When a slow call gets a duplicate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HedgeDelay {
+    Fixed(Duration),
+    /// That quantile (e.g. 0.95) of recent successful call latencies.
+    Quantile(f64),
+}
+
+impl FromStr for HedgeDelay {
+    type Err = anyhow::Error;
+
+    /// `p95`, `p99.5`, `250ms` or `2s`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(pct) = s.strip_prefix('p') {
+            let pct: f64 = pct.parse().map_err(|_| anyhow::anyhow!("invalid percentile {s:?}"))?;
+            if !(pct > 0.0 && pct < 100.0) {
+                bail!("percentile must be between 0 and 100, got {s:?}");
+            }
+            return Ok(Self::Quantile(pct / 100.0));
+        }
+        let (value, scale) = match s.strip_suffix("ms") {
+            Some(ms) => (ms, 1e-3),
+            None => (s.strip_suffix('s').unwrap_or(s), 1.0),
+        };
+        match value.parse::<f64>() {
+            Ok(v) if v >= 0.0 && v.is_finite() => Ok(Self::Fixed(Duration::from_secs_f64(v * scale))),
+            _ => bail!("invalid hedge delay {s:?}; expected e.g. p95, 250ms or 2s"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    pub delay: HedgeDelay,
+    /// Extra requests allowed per call, e.g. 0.05 for at most 5% more load.
+    /// A negative or non-finite value disables hedging.
+    pub max_extra_load: f64,
+    /// Most duplicates in flight at once.
+    pub max_in_flight: usize,
+}
+
+impl HedgeConfig {
+    /// Parses an extra-load fraction such as `0.05`, rejecting negative and
+    /// non-finite values.
+    pub fn parse_max_extra_load(s: &str) -> Result<f64> {
+        match s.trim().parse::<f64>() {
+            Ok(v) if v >= 0.0 && v.is_finite() => Ok(v),
+            _ => bail!("invalid extra hedge load {s:?}; expected a non-negative fraction such as 0.05"),
+        }
+    }
+}
+
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self { delay: HedgeDelay::Quantile(0.95), max_extra_load: 0.05, max_in_flight: 8 }
+    }
+}
+
+/// Hedging counters for one backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HedgeStats {
+    pub calls: u64,
+    /// Calls that got a duplicate.
+    pub hedged: u64,
+    /// Hedged calls the duplicate answered first.
+    pub hedge_wins: u64,
+    /// The current hedge delay; `None` while a quantile still lacks samples.
+    pub delay: Option<Duration>,
+}
+
+/// Sends a duplicate of a call that has not answered within the hedge delay
+/// and takes whichever answers first; the slower one is dropped, which
+/// cancels its request, unless [`Hedger::run_with_loser`] keeps it. Extra
+/// load is capped by [`HedgeConfig`], so a backend that is slow across the
+/// board is not sent twice the traffic.
+pub struct Hedger {
+    config: HedgeConfig,
+    latencies: Mutex<VecDeque<Duration>>,
+    credit: Mutex<f64>,
+    in_flight: AtomicUsize,
+    calls: AtomicU64,
+    hedged: AtomicU64,
+    hedge_wins: AtomicU64,
+}
+
+impl Hedger {
+    /// A negative or non-finite `max_extra_load` disables hedging; NaN in
+    /// particular would refill the credit to its burst cap on every call.
+    pub fn new(mut config: HedgeConfig) -> Self {
+        if !config.max_extra_load.is_finite() || config.max_extra_load < 0.0 {
+            config.max_extra_load = 0.0;
+        }
+        Self {
+            config,
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            credit: Mutex::new(0.0),
+            in_flight: AtomicUsize::new(0),
+            calls: AtomicU64::new(0),
+            hedged: AtomicU64::new(0),
+            hedge_wins: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> HedgeStats {
+        HedgeStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            hedged: self.hedged.load(Ordering::Relaxed),
+            hedge_wins: self.hedge_wins.load(Ordering::Relaxed),
+            delay: self.delay(),
+        }
+    }
+
+    fn delay(&self) -> Option<Duration> {
+        match self.config.delay {
+            HedgeDelay::Fixed(delay) => Some(delay),
+            HedgeDelay::Quantile(q) => {
+                let latencies = self.latencies.lock().expect("hedge latencies poisoned");
+                if latencies.len() < MIN_SAMPLES {
+                    return None;
+                }
+                let mut sorted: Vec<_> = latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                let rank = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len());
+                Some(sorted[rank - 1])
+            }
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().expect("hedge latencies poisoned");
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    /// Spends one hedge from the load budget, if there is one to spend.
+    fn admit(&self) -> Option<HedgeSlot<'_>> {
+        if self.in_flight.load(Ordering::Relaxed) >= self.config.max_in_flight {
+            return None;
+        }
+        let mut credit = self.credit.lock().expect("hedge credit poisoned");
+        if *credit < 1.0 {
+            return None;
+        }
+        *credit -= 1.0;
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        self.hedged.fetch_add(1, Ordering::Relaxed);
+        Some(HedgeSlot(&self.in_flight))
+    }
+
+    /// Runs `call`, and runs it a second time if the first has not answered
+    /// within the hedge delay. A failure waits for the other attempt; if
+    /// both fail the first error is returned.
+    pub async fn run<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run_with_loser(call, drop).await
+    }
+
+    /// Like [`Hedger::run`], but hands the slower attempt of a hedged call to
+    /// `on_loser` instead of dropping it, e.g. to let it finish and meter
+    /// what it cost.
+    pub async fn run_with_loser<T, F, Fut>(&self, call: F, on_loser: impl FnOnce(Pin<Box<Fut>>)) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut credit = self.credit.lock().expect("hedge credit poisoned");
+            *credit = (*credit + self.config.max_extra_load).min(MAX_BURST);
+        }
+        let start = Instant::now();
+        let mut first = Box::pin(call());
+
+        let Some(delay) = self.delay() else {
+            return self.finish(start, first.await);
+        };
+        if let Ok(out) = tokio::time::timeout(delay, &mut first).await {
+            return self.finish(start, out);
+        }
+        let Some(_slot) = self.admit() else {
+            return self.finish(start, first.await);
+        };
+        let mut second = Box::pin(call());
+        let (first_answered, out) = tokio::select! {
+            out = &mut first => (true, out),
+            out = &mut second => (false, out),
+        };
+        match (first_answered, out) {
+            (true, Ok(v)) => {
+                on_loser(second);
+                self.finish(start, Ok(v))
+            }
+            (true, Err(err)) => self.finish_hedge(start, second.await).or(Err(err)),
+            (false, Ok(v)) => {
+                on_loser(first);
+                self.finish_hedge(start, Ok(v))
+            }
+            (false, Err(_)) => self.finish(start, first.await),
+        }
+    }
+
+    /// Latency is measured from the first attempt, so hedge wins do not pull
+    /// the quantile below the delay that triggered them.
+    fn finish<T>(&self, start: Instant, out: Result<T>) -> Result<T> {
+        if out.is_ok() {
+            self.record(start.elapsed());
+        }
+        out
+    }
+
+    fn finish_hedge<T>(&self, start: Instant, out: Result<T>) -> Result<T> {
+        if out.is_ok() {
+            self.hedge_wins.fetch_add(1, Ordering::Relaxed);
+        }
+        self.finish(start, out)
+    }
+}
+
+struct HedgeSlot<'a>(&'a AtomicUsize);
+
+impl Drop for HedgeSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    /// Answers after `delays[n]` on its n-th call, or fails when that delay is `None`.
+    struct Scripted {
+        delays: Vec<Option<Duration>>,
+        calls: AtomicUsize,
+    }
+
+    impl Scripted {
+        fn new(delays: &[Option<u64>]) -> Self {
+            Self { delays: delays.iter().map(|d| d.map(Duration::from_millis)).collect(), calls: AtomicUsize::new(0) }
+        }
+
+        async fn call(&self) -> Result<String> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.delays.get(n).copied().flatten() {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    Ok(format!("call {n}"))
+                }
+                None => Err(anyhow!("down")),
+            }
+        }
+    }
+
+    fn hedger(delay: HedgeDelay, max_extra_load: f64) -> Hedger {
+        Hedger::new(HedgeConfig { delay, max_extra_load, max_in_flight: 8 })
+    }
+
+    #[test]
+    fn parses_percentiles_and_fixed_delays() {
+        assert_eq!("p95".parse::<HedgeDelay>().unwrap(), HedgeDelay::Quantile(0.95));
+        assert_eq!("250ms".parse::<HedgeDelay>().unwrap(), HedgeDelay::Fixed(Duration::from_millis(250)));
+        assert_eq!("1.5s".parse::<HedgeDelay>().unwrap(), HedgeDelay::Fixed(Duration::from_millis(1500)));
+        assert!("p100".parse::<HedgeDelay>().is_err());
+        assert!("soon".parse::<HedgeDelay>().is_err());
+    }
+
+    #[test]
+    fn extra_load_must_be_a_finite_non_negative_fraction() {
+        assert_eq!(HedgeConfig::parse_max_extra_load("0.05").unwrap(), 0.05);
+        assert_eq!(HedgeConfig::parse_max_extra_load("0").unwrap(), 0.0);
+        for bad in ["NaN", "inf", "-0.1", "lots"] {
+            assert!(HedgeConfig::parse_max_extra_load(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_duplicate_falls_back_to_the_original() {
+        let inner = Scripted::new(&[Some(300), None]);
+        let hedger = hedger(HedgeDelay::Fixed(Duration::from_millis(100)), 1.0);
+        assert_eq!(hedger.run(|| inner.call()).await.unwrap(), "call 0");
+        let stats = hedger.stats();
+        assert_eq!((stats.hedged, stats.hedge_wins), (1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn extra_load_is_capped() {
+        let inner = Scripted::new(&[Some(200); 40]);
+        let hedger = hedger(HedgeDelay::Fixed(Duration::from_millis(100)), 0.25);
+        for _ in 0..20 {
+            hedger.run(|| inner.call()).await.unwrap();
+        }
+        // every call was slow, but only a quarter of them got a duplicate
+        assert_eq!(hedger.stats().hedged, 5);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 25);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn invalid_extra_load_never_hedges() {
+        for max_extra_load in [f64::NAN, f64::INFINITY, -1.0] {
+            let inner = Scripted::new(&[Some(200); 10]);
+            let hedger = hedger(HedgeDelay::Fixed(Duration::from_millis(100)), max_extra_load);
+            for _ in 0..10 {
+                hedger.run(|| inner.call()).await.unwrap();
+            }
+            assert_eq!(hedger.stats().hedged, 0, "{max_extra_load}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quantile_delay_waits_for_samples_then_tracks_latency() {
+        let mut delays = vec![Some(50); MIN_SAMPLES - 2];
+        delays.extend([Some(100), Some(100), Some(500), Some(20)]);
+        let inner = Scripted::new(&delays);
+        let hedger = hedger(HedgeDelay::Quantile(0.95), 1.0);
+        for _ in 0..MIN_SAMPLES {
+            hedger.run(|| inner.call()).await.unwrap();
+        }
+        assert_eq!(hedger.stats().hedged, 0);
+        assert_eq!(hedger.stats().delay, Some(Duration::from_millis(100)));
+
+        let started = Instant::now();
+        assert_eq!(hedger.run(|| inner.call()).await.unwrap(), format!("call {}", MIN_SAMPLES + 1));
+        assert_eq!(started.elapsed(), Duration::from_millis(120));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_slower_attempt_is_handed_over() {
+        let inner = Scripted::new(&[Some(1000), Some(10)]);
+        let hedger = hedger(HedgeDelay::Fixed(Duration::from_millis(100)), 1.0);
+        let mut loser = None;
+        assert_eq!(hedger.run_with_loser(|| inner.call(), |l| loser = Some(l)).await.unwrap(), "call 1");
+        assert_eq!(loser.unwrap().await.unwrap(), "call 0");
+    }
+}
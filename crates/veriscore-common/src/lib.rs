@@ -0,0 +1,7 @@
+//! Plumbing shared by the LLM and web backends that depends on neither.
+
+pub mod fixtures;
+pub mod hedge;
+
+pub use fixtures::{fixture_key, FixtureWriter, Fixtures};
+pub use hedge::{HedgeConfig, HedgeDelay, HedgeStats, Hedger};
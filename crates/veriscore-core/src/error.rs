@@ -0,0 +1,18 @@
+/// Errors raised by the core pipeline stages themselves, as opposed to the
+/// LLM or evidence backends they call.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CoreError {
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    #[error("{stage}: model returned {got} outputs for {expected} prompts")]
+    OutputCountMismatch { stage: &'static str, expected: usize, got: usize },
+    /// A run's dollar or token cap was reached before the next stage started.
+    #[error("budget exceeded: {0}")]
+    BudgetExceeded(String),
+}
+
+impl CoreError {
+    pub fn find(err: &anyhow::Error) -> Option<&CoreError> {
+        err.chain().find_map(|e| e.downcast_ref::<CoreError>())
+    }
+}
@@ -1,40 +1,63 @@
+use crate::error::CoreError;
+use crate::prompts::PromptTemplates;
 use crate::segment::{segment_sentences, sliding_windows};
+use crate::stages::ClaimExtractor;
 use crate::types::*;
 use anyhow::Result;
-use async_openai::types::ChatCompletionRequestMessage;
+use std::sync::Arc;
 use veriscore_llm::traits::Llm;
 
-fn build_extraction_prompt(win: &str) -> Vec<ChatCompletionRequestMessage> {
-    use async_openai::types::{ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs};
-    let system = ChatCompletionRequestSystemMessageArgs::default()
-        .content("You are an expert at extracting verifiable factual claims. Extract only verifiable claims; ignore unverifiable content (opinions, advice, fiction). Return a JSON array of strings.")
-        .build().unwrap().into();
-    let user = ChatCompletionRequestUserMessageArgs::default()
-        .content(format!("Text window:\n{win}\n\nReturn JSON array of verifiable claims."))
-        .build().unwrap().into();
-    vec![system, user]
+/// The default extractor: prompts an LLM over sliding windows of the response.
+#[derive(Clone)]
+pub struct LlmClaimExtractor {
+    llm: Arc<dyn Llm>,
+}
+
+impl LlmClaimExtractor {
+    pub fn new(llm: Arc<dyn Llm>) -> Self {
+        Self { llm }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClaimExtractor for LlmClaimExtractor {
+    async fn extract(&self, record: &InputRecord, prompts: &PromptTemplates) -> Result<ExtractedClaimsRecord> {
+        extract_record_with_prompts(self.llm.as_ref(), record, prompts).await
+    }
 }
 
 pub async fn extract_record(client: &dyn Llm, rec: &InputRecord) -> Result<ExtractedClaimsRecord> {
+    extract_record_with_prompts(client, rec, &PromptTemplates::default()).await
+}
+
+pub async fn extract_record_with_prompts(client: &dyn Llm, rec: &InputRecord, templates: &PromptTemplates) -> Result<ExtractedClaimsRecord> {
     let sents = segment_sentences(&rec.response);
     let wins = sliding_windows(rec.question.as_deref(), &sents, crate::segment::SlidingWinCfg { left: 3, right: 1, qa_mode: rec.question.is_some() });
 
-    let prompts = wins.iter().map(|w| build_extraction_prompt(w)).collect::<Vec<_>>();
-    let raw = client.chat_many(prompts).await?;
+    let prompts = wins.iter().map(|w| templates.extraction(w)).collect::<Vec<_>>();
+    let expected = prompts.len();
+    let raw = client.chat_many_with_usage(prompts).await?;
+    if raw.len() != expected {
+        return Err(CoreError::OutputCountMismatch { stage: "extraction", expected, got: raw.len() }.into());
+    }
+    let usage = StageUsage::of(&raw);
 
     let mut claim_list = Vec::with_capacity(raw.len());
     let mut all_claims = Vec::new();
     for r in raw {
-        let claims: Vec<String> = serde_json::from_str(r.trim()).unwrap_or_default();
+        let claims: Vec<String> = serde_json::from_str(r.text.trim()).unwrap_or_default();
         all_claims.extend(claims.clone());
         claim_list.push(claims);
     }
 
     Ok(ExtractedClaimsRecord {
         input: rec.clone(),
-        prompt_tok_cnt: None, response_tok_cnt: None,
+        // token counts of the extraction calls, when the backend reported any
+        prompt_tok_cnt: (usage.prompt_tokens > 0).then(|| usage.prompt_tokens.try_into().unwrap_or(u32::MAX)),
+        response_tok_cnt: (usage.completion_tokens > 0).then(|| usage.completion_tokens.try_into().unwrap_or(u32::MAX)),
         abstained: false,
         claim_list, all_claims,
+        extraction_usage: Some(usage),
     })
 }
 
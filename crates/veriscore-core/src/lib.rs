@@ -1,8 +1,13 @@
 pub mod types;
+pub mod error;
 pub mod jsonl;
 pub mod segment;
+pub mod prompts;
 pub mod extraction;
 pub mod verification;
+pub mod nli;
+pub mod stages;
+pub mod voting;
 pub mod scoring;
 pub mod util;
 
@@ -16,9 +21,14 @@ pub use types::{
     VerificationRecord,
 };
 
-pub use extraction::extract_record;
-pub use verification::verify_record;
-pub use scoring::{score_response, PerResponseScore, ScoreConf};
+pub use error::CoreError;
+pub use extraction::{extract_record, extract_record_with_prompts, LlmClaimExtractor};
+pub use nli::{verify_record_nli, NliClaimVerifier, NliVerifierConfig};
+pub use prompts::PromptTemplates;
+pub use stages::{ClaimExtractor, ClaimVerifier};
+pub use verification::{supported_probability, verify_record, verify_record_packed, verify_record_with_logprobs, verify_record_with_prompts, LlmClaimVerifier};
+pub use voting::VotingVerifier;
+pub use scoring::{score_response, score_response_soft, PerResponseScore, ScoreConf};
 
 use anyhow::Result;
 
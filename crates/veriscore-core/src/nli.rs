@@ -0,0 +1,162 @@
+use crate::error::CoreError;
+use crate::prompts::PromptTemplates;
+use crate::stages::ClaimVerifier;
+use crate::types::*;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use veriscore_llm::nli::{NliClass, NliModel};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NliVerifierConfig {
+    /// Entailment probability a snippet needs to support a claim.
+    pub entailment_threshold: f32,
+    /// Premises are cut to this many characters; cross-encoders usually see at most 512 tokens.
+    pub max_premise_chars: usize,
+}
+
+impl Default for NliVerifierConfig {
+    fn default() -> Self {
+        Self { entailment_threshold: 0.5, max_premise_chars: 1500 }
+    }
+}
+
+/// [`verify_record_nli`] as a pipeline verifier; prompts are ignored.
+#[derive(Clone)]
+pub struct NliClaimVerifier {
+    model: Arc<dyn NliModel>,
+    config: NliVerifierConfig,
+}
+
+impl NliClaimVerifier {
+    pub fn new(model: Arc<dyn NliModel>, config: NliVerifierConfig) -> Self {
+        Self { model, config }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClaimVerifier for NliClaimVerifier {
+    async fn verify(&self, evidence: EvidenceRecord, _binary: bool, _prompts: &PromptTemplates) -> Result<VerificationRecord> {
+        verify_record_nli(self.model.as_ref(), evidence, &self.config).await
+    }
+}
+
+/// Verifies claims with an NLI cross-encoder instead of an LLM. Each snippet is
+/// a premise and the claim the hypothesis. A claim is supported when some
+/// snippet entails it. Contradiction and neutral collapse to unsupported, like
+/// the ternary labels of `verify_record`.
+pub async fn verify_record_nli(model: &dyn NliModel, ev: EvidenceRecord, config: &NliVerifierConfig) -> Result<VerificationRecord> {
+    let mut pairs = Vec::new();
+    let mut owners = Vec::new();
+    for (i, (claim, hits)) in ev.claim_snippets_dict.iter().enumerate() {
+        for hit in hits {
+            let premise = format!("{}. {}", hit.title, hit.snippet).chars().take(config.max_premise_chars).collect::<String>();
+            pairs.push((premise, claim.clone()));
+            owners.push(i);
+        }
+    }
+
+    let scores = if pairs.is_empty() { Vec::new() } else { model.classify(&pairs).await? };
+    if scores.len() != pairs.len() {
+        return Err(CoreError::OutputCountMismatch { stage: "nli verification", expected: pairs.len(), got: scores.len() }.into());
+    }
+
+    let mut supported = vec![false; ev.claim_snippets_dict.len()];
+    for (owner, s) in owners.into_iter().zip(scores) {
+        if s.class() == NliClass::Entailment && s.entailment >= config.entailment_threshold {
+            supported[owner] = true;
+        }
+    }
+
+    let results = ev
+        .claim_snippets_dict
+        .iter()
+        .zip(supported)
+        .map(|((claim, hits), supported)| ClaimVerification {
+            claim: claim.clone(),
+            search_results: hits.clone(),
+            verification_result: if supported { VerificationLabel::Supported } else { VerificationLabel::Unsupported },
+            confidence: None,
+        })
+        .collect();
+    Ok(VerificationRecord { evidence: ev, claim_verification_result: results, verification_usage: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use veriscore_llm::nli::NliScores;
+
+    /// Entails when the premise mentions the hypothesis' last word; records premises.
+    struct FakeNli {
+        premises: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl NliModel for FakeNli {
+        async fn classify(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>> {
+            self.premises.lock().unwrap().extend(pairs.iter().map(|(p, _)| p.clone()));
+            Ok(pairs
+                .iter()
+                .map(|(premise, hypothesis)| {
+                    let key = hypothesis.trim_end_matches('.').split(' ').next_back().unwrap();
+                    if premise.contains(key) {
+                        NliScores { entailment: 0.9, neutral: 0.05, contradiction: 0.05 }
+                    } else {
+                        NliScores { entailment: 0.2, neutral: 0.3, contradiction: 0.5 }
+                    }
+                })
+                .collect())
+        }
+    }
+
+    fn item(snippet: &str) -> EvidenceItem {
+        EvidenceItem { title: "t".into(), snippet: snippet.into(), link: "l".into() }
+    }
+
+    fn record(claims: Vec<(&str, Vec<EvidenceItem>)>) -> EvidenceRecord {
+        EvidenceRecord {
+            claims: ExtractedClaimsRecord {
+                input: InputRecord { question: None, response: "r".into(), model: None, prompt_source: None },
+                prompt_tok_cnt: None,
+                response_tok_cnt: None,
+                abstained: false,
+                claim_list: vec![],
+                all_claims: claims.iter().map(|(c, _)| c.to_string()).collect(),
+                extraction_usage: None,
+            },
+            claim_snippets_dict: claims.into_iter().map(|(c, hits)| (c.to_string(), hits)).collect(),
+            retrieval_usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn any_entailing_snippet_supports_the_claim() {
+        let model = FakeNli { premises: Mutex::new(Vec::new()) };
+        let ev = record(vec![
+            ("The capital is Paris.", vec![item("Lyon is large."), item("Paris is the capital.")]),
+            ("The river is Seine.", vec![item("The Loire is long.")]),
+            ("No evidence here.", vec![]),
+        ]);
+
+        let out = verify_record_nli(&model, ev, &NliVerifierConfig::default()).await.unwrap();
+
+        let labels = out.claim_verification_result.iter().map(|c| c.verification_result.clone()).collect::<Vec<_>>();
+        assert!(matches!(labels[..], [VerificationLabel::Supported, VerificationLabel::Unsupported, VerificationLabel::Unsupported]));
+        assert_eq!(out.claim_verification_result[0].search_results.len(), 2);
+        assert_eq!(model.premises.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn threshold_and_premise_length_apply() {
+        let model = FakeNli { premises: Mutex::new(Vec::new()) };
+        let config = NliVerifierConfig { entailment_threshold: 0.95, max_premise_chars: 8 };
+        let ev = record(vec![("The capital is Paris.", vec![item("Paris is the capital.")])]);
+
+        let out = verify_record_nli(&model, ev, &config).await.unwrap();
+
+        assert!(matches!(out.claim_verification_result[0].verification_result, VerificationLabel::Unsupported));
+        assert_eq!(model.premises.lock().unwrap()[0], "t. Paris");
+    }
+}
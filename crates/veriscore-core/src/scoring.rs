@@ -6,10 +6,24 @@ pub struct PerResponseScore { pub supported: usize, pub total: usize, pub precis
 
 pub fn score_response(vr: &VerificationRecord, k: usize) -> PerResponseScore {
     let supported = vr.claim_verification_result.iter().filter(|c| matches!(c.verification_result, VerificationLabel::Supported)).count();
-    let total = vr.claim_verification_result.len().max(1);
-    let precision = supported as f32 / total as f32;
+    score_from_support(supported, supported as f32, vr.claim_verification_result.len(), k)
+}
+
+/// Like [`score_response`], but precision and recall@K use the expected
+/// number of supported claims: each claim counts by its probability of support
+/// ([`ClaimVerification::support`](crate::types::ClaimVerification::support))
+/// instead of 0 or 1. `supported` stays the hard count.
+pub fn score_response_soft(vr: &VerificationRecord, k: usize) -> PerResponseScore {
+    let supported = vr.claim_verification_result.iter().filter(|c| matches!(c.verification_result, VerificationLabel::Supported)).count();
+    let mass = vr.claim_verification_result.iter().map(|c| c.support()).sum::<f32>();
+    score_from_support(supported, mass, vr.claim_verification_result.len(), k)
+}
+
+fn score_from_support(supported: usize, mass: f32, claims: usize, k: usize) -> PerResponseScore {
+    let total = claims.max(1);
+    let precision = mass / total as f32;
     // recall uses K as the target count for perfect recall
-    let recall = (supported as f32 / k as f32).min(1.0);
+    let recall = (mass / k as f32).min(1.0);
     let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
     PerResponseScore { supported, total, precision, recall, f1 }
 }
@@ -36,6 +50,7 @@ mod tests {
                 } else {
                     VerificationLabel::Unsupported
                 },
+                confidence: None,
             })
             .collect();
 
@@ -53,10 +68,13 @@ mod tests {
                     abstained: false,
                     claim_list: vec![],
                     all_claims: vec![],
+                    extraction_usage: None,
                 },
                 claim_snippets_dict: vec![],
+                retrieval_usage: None,
             },
             claim_verification_result,
+            verification_usage: None,
         }
     }
 
@@ -94,6 +112,21 @@ mod tests {
         assert!((s.f1 - 0.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn soft_score_weights_claims_by_vote_share() {
+        let mut vr = mk_record(2, 4);
+        vr.claim_verification_result[0].confidence = Some(0.5);
+        vr.claim_verification_result[2].confidence = Some(0.75);
+
+        let hard = score_response(&vr, 4);
+        let soft = score_response_soft(&vr, 4);
+
+        assert_eq!(soft.supported, hard.supported);
+        // 0.5 + 1.0 + 0.25 + 0.0 supported out of 4
+        assert!((soft.precision - 0.4375).abs() < 1e-6);
+        assert!((hard.precision - 0.5).abs() < 1e-6);
+    }
+
     #[test]
     fn score_response_handles_zero_claims_defensively() {
         let vr = mk_record(0, 0);
@@ -0,0 +1,21 @@
+use crate::prompts::PromptTemplates;
+use crate::types::*;
+use anyhow::Result;
+
+/// Turns a response into checkable claims. `LlmClaimExtractor` is the
+/// default; downstream crates can plug in other strategies without touching
+/// the pipeline.
+#[async_trait::async_trait]
+pub trait ClaimExtractor: Send + Sync {
+    /// `prompts` is what the server currently has loaded; non-LLM extractors ignore it.
+    async fn extract(&self, record: &InputRecord, prompts: &PromptTemplates) -> Result<ExtractedClaimsRecord>;
+}
+
+/// Labels each claim against its evidence. Implementations include
+/// `LlmClaimVerifier` and `NliClaimVerifier`, and can wrap one another to
+/// build cascades or ensembles.
+#[async_trait::async_trait]
+pub trait ClaimVerifier: Send + Sync {
+    /// Must return one `ClaimVerification` per entry of `claim_snippets_dict`, in order.
+    async fn verify(&self, evidence: EvidenceRecord, binary: bool, prompts: &PromptTemplates) -> Result<VerificationRecord>;
+}
@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
+use veriscore_llm::traits::Completion;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct InputRecord {
+    #[cfg_attr(feature = "openapi", schema(max_length = 16384))]
     pub question: Option<String>,       // optional; if present, QA mode
     pub response: String,               // model output to evaluate
+    #[cfg_attr(feature = "openapi", schema(max_length = 256))]
     pub model: Option<String>,          // generator id
+    #[cfg_attr(feature = "openapi", schema(max_length = 256))]
     pub prompt_source: Option<String>,  // dataset tag/domain
 }
 
@@ -17,6 +22,69 @@ pub struct ExtractedClaimsRecord {
     pub abstained: bool,                // when extractor refuses
     pub claim_list: Vec<Vec<String>>,   // claims per-snippet (sliding window)
     pub all_claims: Vec<String>,        // flattened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction_usage: Option<StageUsage>,
+}
+
+/// LLM calls and tokens one pipeline stage spent on a record. Cache hits
+/// count as calls but cost no tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StageUsage {
+    pub calls: u32,
+    pub cached_calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// USD, for backends that were given a price for their model.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+impl StageUsage {
+    pub fn of(completions: &[Completion]) -> Self {
+        let mut usage = Self::default();
+        for c in completions {
+            usage.calls += 1;
+            usage.cached_calls += u32::from(c.cached);
+            if let Some(tokens) = c.usage {
+                usage.prompt_tokens += tokens.prompt_tokens;
+                usage.completion_tokens += tokens.completion_tokens;
+            }
+            usage.cost_usd += c.cost_usd.unwrap_or(0.0);
+        }
+        usage
+    }
+
+    pub fn merge(&mut self, other: &StageUsage) {
+        self.calls += other.calls;
+        self.cached_calls += other.cached_calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Web searches run for a record's claims. Cache hits are free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchUsage {
+    pub searches: u32,
+    pub cached_searches: u32,
+    /// USD, when a per-search price is configured.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+impl SearchUsage {
+    pub fn merge(&mut self, other: &SearchUsage) {
+        self.searches += other.searches;
+        self.cached_searches += other.cached_searches;
+        self.cost_usd += other.cost_usd;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +95,9 @@ pub struct EvidenceRecord {
     #[serde(flatten)]
     pub claims: ExtractedClaimsRecord,
     pub claim_snippets_dict: Vec<(String, Vec<EvidenceItem>)>, // (claim, evidence[])
+    /// `None` for evidence providers that do not report their searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval_usage: Option<SearchUsage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +108,23 @@ pub struct ClaimVerification {
     pub claim: String,
     pub search_results: Vec<EvidenceItem>,   // concatenated or per-item
     pub verification_result: VerificationLabel,
+    /// How sure the verifier is of `verification_result`, in 0..=1: the share
+    /// of (weighted) votes from `VotingVerifier`, or the label's token
+    /// probability from a logprob-enabled `LlmClaimVerifier`. Unset for hard labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+impl ClaimVerification {
+    /// How much this claim counts as supported: 1 or 0 for a hard label,
+    /// otherwise the probability (vote share or token probability) of `Supported`.
+    pub fn support(&self) -> f32 {
+        let confidence = self.confidence.unwrap_or(1.0);
+        match self.verification_result {
+            VerificationLabel::Supported => confidence,
+            VerificationLabel::Unsupported => 1.0 - confidence,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,4 +132,7 @@ pub struct VerificationRecord {
     #[serde(flatten)]
     pub evidence: EvidenceRecord,
     pub claim_verification_result: Vec<ClaimVerification>,
+    /// `None` for verifiers that do not call an LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_usage: Option<StageUsage>,
 }
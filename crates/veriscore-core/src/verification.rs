@@ -1,46 +1,232 @@
+use crate::error::CoreError;
+use crate::prompts::PromptTemplates;
+use crate::stages::ClaimVerifier;
 use crate::types::*;
 use anyhow::Result;
-use async_openai::types::{
-    ChatCompletionRequestMessage,
-    ChatCompletionRequestSystemMessageArgs,
-    ChatCompletionRequestUserMessageArgs,
-};
-use veriscore_llm::traits::Llm;
-
-fn build_verify_prompt(claim: &str, hits: &[EvidenceItem], binary: bool) -> Vec<ChatCompletionRequestMessage> {
-    let label_desc = if binary {
-        "Return JSON: {\"label\": \"supported\" | \"unsupported\", \"rationale\": \"...\"}"
-    } else {
-        "Return JSON: {\"label\": \"supported\" | \"contradicted\" | \"inconclusive\", \"rationale\": \"...\"}"
-    };
-    let evidence = hits.iter().map(|h| format!("- {} [{}]\n{}", h.title, h.link, h.snippet)).collect::<Vec<_>>().join("\n");
-    let sys = ChatCompletionRequestSystemMessageArgs::default()
-        .content("You are a meticulous fact checker. Judge the claim ONLY using the provided web snippets.")
-        .build().unwrap().into();
-    let usr = ChatCompletionRequestUserMessageArgs::default()
-        .content(format!("Claim:\n{claim}\n\nEvidence:\n{evidence}\n\n{label_desc}"))
-        .build().unwrap().into();
-    vec![sys, usr]
+use std::sync::Arc;
+use veriscore_llm::traits::{Llm, Completion};
+
+/// The default verifier: prompts an LLM with each claim and its snippets.
+#[derive(Clone)]
+pub struct LlmClaimVerifier {
+    llm: Arc<dyn Llm>,
+    logprobs: bool,
+    claims_per_prompt: usize,
+}
+
+impl LlmClaimVerifier {
+    pub fn new(llm: Arc<dyn Llm>) -> Self {
+        Self { llm, logprobs: false, claims_per_prompt: 1 }
+    }
+
+    /// Pack up to `claims` claims into each prompt (see [`verify_record_packed`]).
+    /// Packed prompts take precedence over `with_logprobs`, which needs one
+    /// label per completion.
+    pub fn with_claims_per_prompt(mut self, claims: usize) -> Self {
+        self.claims_per_prompt = claims.max(1);
+        self
+    }
+
+    /// Ask for token logprobs and record the probability of the
+    /// `supported` label as each claim's confidence.
+    pub fn with_logprobs(mut self, logprobs: bool) -> Self {
+        self.logprobs = logprobs;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ClaimVerifier for LlmClaimVerifier {
+    async fn verify(&self, evidence: EvidenceRecord, binary: bool, prompts: &PromptTemplates) -> Result<VerificationRecord> {
+        if self.claims_per_prompt > 1 {
+            verify_record_packed(self.llm.as_ref(), evidence, binary, self.claims_per_prompt, prompts).await
+        } else if self.logprobs {
+            verify_record_with_logprobs(self.llm.as_ref(), evidence, binary, prompts).await
+        } else {
+            verify_record_with_prompts(self.llm.as_ref(), evidence, binary, 1, prompts).await
+        }
+    }
 }
 
-pub async fn verify_record(client: &dyn Llm, ev: EvidenceRecord, binary: bool, _concurrency: usize)
+pub async fn verify_record(client: &dyn Llm, ev: EvidenceRecord, binary: bool, concurrency: usize)
 -> Result<VerificationRecord> {
-    let prompts = ev.claim_snippets_dict.iter().map(|(c, hits)| build_verify_prompt(c, hits, binary)).collect::<Vec<_>>();
-    let outs = client.chat_many(prompts).await?;
+    verify_record_with_prompts(client, ev, binary, concurrency, &PromptTemplates::default()).await
+}
+
+pub async fn verify_record_with_prompts(client: &dyn Llm, ev: EvidenceRecord, binary: bool, _concurrency: usize, templates: &PromptTemplates)
+-> Result<VerificationRecord> {
+    let prompts = ev.claim_snippets_dict.iter().map(|(c, hits)| templates.verification(c, hits, binary)).collect::<Vec<_>>();
+    let outs = client.chat_many_with_usage(prompts).await?;
+    label_claims(ev, outs)
+}
+
+/// Like [`verify_record_with_prompts`], but asks the backend for logprobs and
+/// sets each claim's `confidence` from [`supported_probability`]. Claims whose
+/// output has no usable logprobs keep a hard label.
+pub async fn verify_record_with_logprobs(client: &dyn Llm, ev: EvidenceRecord, binary: bool, templates: &PromptTemplates)
+-> Result<VerificationRecord> {
+    let prompts = ev.claim_snippets_dict.iter().map(|(c, hits)| templates.verification(c, hits, binary)).collect::<Vec<_>>();
+    let outs = client.chat_many_scored(prompts).await?;
+    label_claims(ev, outs)
+}
+
+/// Verifies up to `claims_per_prompt` claims per prompt with
+/// [`PromptTemplates::verification_batch`], which lists shared snippets once.
+/// A chunk whose answer is not a JSON array with one label per claim, in
+/// order, is asked again with one prompt per claim.
+pub async fn verify_record_packed(client: &dyn Llm, ev: EvidenceRecord, binary: bool, claims_per_prompt: usize, templates: &PromptTemplates)
+-> Result<VerificationRecord> {
+    let size = claims_per_prompt.max(1);
+    let chunks = ev.claim_snippets_dict.chunks(size).collect::<Vec<_>>();
+    let prompts = chunks.iter().map(|chunk| templates.verification_batch(chunk, binary)).collect::<Vec<_>>();
+    let outs = client.chat_many_with_usage(prompts).await?;
+    if outs.len() != chunks.len() {
+        return Err(CoreError::OutputCountMismatch { stage: "packed verification", expected: chunks.len(), got: outs.len() }.into());
+    }
+
+    let mut usage = StageUsage::of(&outs);
+    let mut labels: Vec<Option<VerificationLabel>> = vec![None; ev.claim_snippets_dict.len()];
+    for (c, (chunk, out)) in chunks.iter().zip(outs).enumerate() {
+        if let Some(parsed) = parse_label_array(&out.text, chunk.len()) {
+            for (j, label) in parsed.into_iter().enumerate() {
+                labels[c * size + j] = Some(label);
+            }
+        }
+    }
+
+    let retry = labels.iter().enumerate().filter(|(_, l)| l.is_none()).map(|(i, _)| i).collect::<Vec<_>>();
+    if !retry.is_empty() {
+        let prompts = retry
+            .iter()
+            .map(|&i| {
+                let (claim, hits) = &ev.claim_snippets_dict[i];
+                templates.verification(claim, hits, binary)
+            })
+            .collect::<Vec<_>>();
+        let outs = client.chat_many_with_usage(prompts).await?;
+        if outs.len() != retry.len() {
+            return Err(CoreError::OutputCountMismatch { stage: "verification", expected: retry.len(), got: outs.len() }.into());
+        }
+        usage.merge(&StageUsage::of(&outs));
+        for (i, out) in retry.into_iter().zip(outs) {
+            labels[i] = Some(parse_label(&out.text));
+        }
+    }
+
+    let results = ev
+        .claim_snippets_dict
+        .iter()
+        .zip(labels)
+        .map(|((claim, hits), label)| ClaimVerification {
+            claim: claim.clone(),
+            search_results: hits.clone(),
+            verification_result: label.unwrap_or(VerificationLabel::Unsupported),
+            confidence: None,
+        })
+        .collect();
+    Ok(VerificationRecord { evidence: ev, claim_verification_result: results, verification_usage: Some(usage) })
+}
+
+/// Labels from a packed answer: a JSON array (optionally wrapped in prose or a
+/// code fence) of exactly `expected` entries, each `{"id": n, "label": ...}`
+/// with `n` matching its position, or a bare label string.
+fn parse_label_array(out: &str, expected: usize) -> Option<Vec<VerificationLabel>> {
+    let (s, e) = (out.find('[')?, out.rfind(']')?);
+    if e < s {
+        return None;
+    }
+    let json = out.get(s..=e)?;
+    let items: Vec<serde_json::Value> = serde_json::from_str(json).ok()?;
+    if items.len() != expected {
+        return None;
+    }
+    items
+        .iter()
+        .enumerate()
+        .map(|(j, item)| {
+            let label = match item {
+                serde_json::Value::String(label) => label.as_str(),
+                serde_json::Value::Object(obj) => {
+                    if let Some(id) = obj.get("id") {
+                        if id.as_u64() != Some(j as u64 + 1) {
+                            return None;
+                        }
+                    }
+                    obj.get("label")?.as_str()?
+                }
+                _ => return None,
+            };
+            Some(map_label(label))
+        })
+        .collect()
+}
+
+fn parse_label(out: &str) -> VerificationLabel {
+    let obj: serde_json::Value = serde_json::from_str(out).unwrap_or_else(|_| serde_json::json!({"label":"inconclusive"}));
+    map_label(obj.get("label").and_then(|v| v.as_str()).unwrap_or("inconclusive"))
+}
+
+fn map_label(label: &str) -> VerificationLabel {
+    match label {
+        "supported" => VerificationLabel::Supported,
+        _ => VerificationLabel::Unsupported, // ternary collapsed to binary per paper
+    }
+}
+
+fn label_claims(ev: EvidenceRecord, outs: Vec<Completion>) -> Result<VerificationRecord> {
+    if outs.len() != ev.claim_snippets_dict.len() {
+        return Err(CoreError::OutputCountMismatch {
+            stage: "verification",
+            expected: ev.claim_snippets_dict.len(),
+            got: outs.len(),
+        }
+        .into());
+    }
+    let usage = StageUsage::of(&outs);
     let mut results = Vec::with_capacity(outs.len());
 
     for (i, out) in outs.into_iter().enumerate() {
-        let obj: serde_json::Value = serde_json::from_str(&out).unwrap_or_else(|_| serde_json::json!({"label":"inconclusive"}));
-        let label = obj.get("label").and_then(|v| v.as_str()).unwrap_or("inconclusive");
-        let mapped = match label {
-            "supported" => VerificationLabel::Supported,
-            _ => VerificationLabel::Unsupported, // ternary collapsed to binary per paper
-        };
+        let mapped = parse_label(&out.text);
+        let confidence = supported_probability(&out).map(|p| match mapped {
+            VerificationLabel::Supported => p,
+            VerificationLabel::Unsupported => 1.0 - p,
+        });
         let (claim, hits) = &ev.claim_snippets_dict[i];
-        results.push(ClaimVerification { claim: claim.clone(), search_results: hits.clone(), verification_result: mapped });
+        results.push(ClaimVerification { claim: claim.clone(), search_results: hits.clone(), verification_result: mapped, confidence });
     }
 
-    Ok(VerificationRecord { evidence: ev, claim_verification_result: results })
+    Ok(VerificationRecord { evidence: ev, claim_verification_result: results, verification_usage: Some(usage) })
+}
+
+/// Probability mass the verifier put on `supported` at the first token of the
+/// label value, normalized over the returned alternatives. Alternatives count
+/// as `supported` when, stripped of quotes and whitespace, they are a prefix of
+/// it (`sup`, `supported`); `un…`, `in…` and `con…` do not. `None` when the
+/// output has no logprobs or no `"label"` value.
+pub fn supported_probability(completion: &Completion) -> Option<f32> {
+    let tokens = completion.logprobs.as_ref()?;
+    let text = &completion.text;
+    let key = text.find("\"label\"")?;
+    let colon = key + text[key..].find(':')?;
+    let value = colon + text[colon..].find(|c: char| c != ':' && c != '"' && !c.is_whitespace())?;
+
+    let mut offset = 0;
+    let token = tokens.iter().find(|t| {
+        offset += t.token.len();
+        offset > value
+    })?;
+
+    let is_supported = |alt: &str| {
+        let alt = alt.trim_matches(|c: char| c == '"' || c.is_whitespace()).to_ascii_lowercase();
+        !alt.is_empty() && "supported".starts_with(&alt)
+    };
+    let mut alternatives = token.top_logprobs.iter().map(|t| (t.token.as_str(), t.logprob)).collect::<Vec<_>>();
+    if !alternatives.iter().any(|(t, _)| *t == token.token) {
+        alternatives.push((&token.token, token.logprob));
+    }
+    let total = alternatives.iter().map(|(_, lp)| lp.exp()).sum::<f32>();
+    let supported = alternatives.iter().filter(|(t, _)| is_supported(t)).map(|(_, lp)| lp.exp()).sum::<f32>();
+    (total > 0.0).then(|| supported / total)
 }
 
 #[cfg(test)]
@@ -50,7 +236,7 @@ mod tests {
         EvidenceItem, EvidenceRecord, ExtractedClaimsRecord, InputRecord,
         VerificationLabel,
     };
-    use async_openai::types::ChatCompletionRequestMessage;
+    use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionTokenLogprob, TopLogprobs};
 
     struct FakeVerifier {
         outputs: Vec<String>,
@@ -84,6 +270,7 @@ mod tests {
                     "Claim 2".to_string(),
                     "Claim 3".to_string(),
                 ],
+                extraction_usage: None,
             },
             claim_snippets_dict: vec![
                 (
@@ -111,6 +298,7 @@ mod tests {
                     }],
                 ),
             ],
+            retrieval_usage: None,
         }
     }
 
@@ -186,4 +374,148 @@ mod tests {
         assert_eq!(out.claim_verification_result[1].search_results.len(), 1);
         assert_eq!(out.claim_verification_result[2].search_results[0].title, "t3");
     }
-}
\ No newline at end of file
+
+    fn token(token: &str, alternatives: &[(&str, f32)]) -> ChatCompletionTokenLogprob {
+        let top_logprobs = alternatives
+            .iter()
+            .map(|(t, p)| TopLogprobs { token: t.to_string(), logprob: p.ln(), bytes: None })
+            .collect();
+        ChatCompletionTokenLogprob { token: token.to_string(), logprob: alternatives[0].1.ln(), bytes: None, top_logprobs }
+    }
+
+    /// `{"label":"supported"}` tokenized as the OpenAI tokenizer does, with
+    /// 70% on `supported` and the rest on `unsupported` / `inconclusive`.
+    fn scored_supported() -> Completion {
+        Completion {
+            text: r#"{"label":"supported"}"#.to_string(),
+            logprobs: Some(vec![
+                token("{\"", &[("{\"", 1.0)]),
+                token("label", &[("label", 1.0)]),
+                token("\":\"", &[("\":\"", 1.0)]),
+                token("supported", &[("supported", 0.6), ("sup", 0.1), ("unsupported", 0.2), ("in", 0.1)]),
+                token("\"}", &[("\"}", 1.0)]),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn supported_probability_reads_the_label_token() {
+        let p = supported_probability(&scored_supported()).unwrap();
+        assert!((p - 0.7).abs() < 1e-4);
+
+        let plain = Completion::text(r#"{"label":"supported"}"#.to_string());
+        assert_eq!(supported_probability(&plain), None);
+    }
+
+    struct ScoredFake;
+
+    #[async_trait::async_trait]
+    impl veriscore_llm::traits::Llm for ScoredFake {
+        async fn chat_many(&self, _prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<String>> {
+            unreachable!("logprob verification uses chat_many_scored")
+        }
+
+        async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<Completion>> {
+            let mut out = vec![scored_supported()];
+            out.extend((1..prompts.len()).map(|_| Completion::text(r#"{"label":"unsupported"}"#.into())));
+            Ok(out)
+        }
+    }
+
+    #[tokio::test]
+    async fn logprob_verifier_sets_confidence_for_soft_scoring() {
+        let verifier = LlmClaimVerifier::new(Arc::new(ScoredFake)).with_logprobs(true);
+
+        let out = verifier.verify(mk_evidence_record(), true, &PromptTemplates::default()).await.unwrap();
+
+        let claims = &out.claim_verification_result;
+        assert!(matches!(claims[0].verification_result, VerificationLabel::Supported));
+        assert!((claims[0].support() - 0.7).abs() < 1e-4);
+        assert_eq!(claims[1].confidence, None);
+        assert_eq!(claims[1].support(), 0.0);
+    }
+
+    /// Answers packed prompts with `packed` (`{n}` becomes the claim count)
+    /// and single-claim prompts with `unsupported`; records prompt sizes.
+    struct PackingFake {
+        packed: &'static str,
+        calls: std::sync::Mutex<Vec<usize>>,
+    }
+
+    #[async_trait::async_trait]
+    impl veriscore_llm::traits::Llm for PackingFake {
+        async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<String>> {
+            self.calls.lock().unwrap().push(prompts.len());
+            Ok(prompts
+                .iter()
+                .map(|p| {
+                    let text = serde_json::to_string(p).unwrap();
+                    match text.find("exactly ") {
+                        Some(at) => {
+                            let n = text[at + 8..].split(' ').next().unwrap();
+                            self.packed.replace("{n}", n)
+                        }
+                        None => r#"{"label":"unsupported"}"#.to_string(),
+                    }
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn packed_verification_accepts_aligned_arrays() {
+        let llm = PackingFake {
+            packed: r#"```json
+[{"id": 1, "label": "supported"}, {"id": 2, "label": "supported"}]
+```"#,
+            calls: Default::default(),
+        };
+
+        let out = verify_record_packed(&llm, mk_evidence_record(), true, 2, &PromptTemplates::default()).await.unwrap();
+
+        // chunks of 2 and 1 claims; the single-claim chunk gets a 2-entry answer and is retried
+        assert_eq!(*llm.calls.lock().unwrap(), [2, 1]);
+        let labels = out.claim_verification_result.iter().map(|c| c.verification_result.clone()).collect::<Vec<_>>();
+        assert!(matches!(labels[..], [VerificationLabel::Supported, VerificationLabel::Supported, VerificationLabel::Unsupported]));
+        assert_eq!(out.claim_verification_result[2].claim, "Claim 3");
+    }
+
+    #[tokio::test]
+    async fn packed_verification_falls_back_on_misaligned_ids() {
+        let llm = PackingFake {
+            packed: r#"[{"id": 2, "label": "supported"}, {"id": 1, "label": "supported"}, {"id": 3, "label": "supported"}]"#,
+            calls: Default::default(),
+        };
+
+        let out = verify_record_packed(&llm, mk_evidence_record(), true, 8, &PromptTemplates::default()).await.unwrap();
+
+        assert_eq!(*llm.calls.lock().unwrap(), [1, 3]);
+        assert!(out
+            .claim_verification_result
+            .iter()
+            .all(|c| matches!(c.verification_result, VerificationLabel::Unsupported)));
+    }
+
+    #[tokio::test]
+    async fn packed_verification_falls_back_when_brackets_are_reversed() {
+        let llm = PackingFake { packed: "] see [1", calls: Default::default() };
+
+        let out = verify_record_packed(&llm, mk_evidence_record(), true, 8, &PromptTemplates::default()).await.unwrap();
+
+        assert_eq!(*llm.calls.lock().unwrap(), [1, 3]);
+        assert_eq!(out.claim_verification_result.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn verify_record_rejects_output_count_mismatch() {
+        let llm = FakeVerifier { outputs: vec![r#"{"label":"supported"}"#.to_string()] };
+
+        let err = verify_record(&llm, mk_evidence_record(), true, 8).await.unwrap_err();
+
+        assert!(matches!(
+            CoreError::find(&err),
+            Some(CoreError::OutputCountMismatch { stage: "verification", expected: 3, got: 1 })
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::error::CoreError;
+use crate::prompts::PromptTemplates;
+use crate::stages::ClaimVerifier;
+use crate::types::*;
+use anyhow::Result;
+use futures::future::join_all;
+use std::sync::Arc;
+
+/// Self-consistency verification: asks every member verifier about the same
+/// evidence and keeps the weighted majority label per claim. Members are
+/// typically the same LLM at several seeds (with temperature > 0) or
+/// different verifier models. Ties go to `Unsupported`, and the winning share
+/// of the vote is stored as `ClaimVerification::confidence`.
+///
+/// A member that errors (or answers for the wrong number of claims) abstains;
+/// the vote only fails when fewer than `quorum` members answer.
+#[derive(Clone)]
+pub struct VotingVerifier {
+    members: Vec<(Arc<dyn ClaimVerifier>, f32)>,
+    quorum: usize,
+}
+
+impl VotingVerifier {
+    /// Equal-weight majority vote.
+    pub fn new(members: Vec<Arc<dyn ClaimVerifier>>) -> Result<Self, CoreError> {
+        Self::weighted(members.into_iter().map(|m| (m, 1.0)).collect())
+    }
+
+    /// Every weight must be finite and positive.
+    pub fn weighted(members: Vec<(Arc<dyn ClaimVerifier>, f32)>) -> Result<Self, CoreError> {
+        if members.is_empty() {
+            return Err(CoreError::InvalidInput("a voting verifier needs at least one member".into()));
+        }
+        if let Some((i, (_, w))) = members.iter().enumerate().find(|(_, (_, w))| !w.is_finite() || *w <= 0.0) {
+            return Err(CoreError::InvalidInput(format!("voting member {i} has weight {w}; weights must be finite and positive")));
+        }
+        Ok(Self { members, quorum: 1 })
+    }
+
+    /// Minimum number of members that must answer (default 1).
+    pub fn with_quorum(mut self, quorum: usize) -> Result<Self, CoreError> {
+        if quorum == 0 || quorum > self.members.len() {
+            return Err(CoreError::InvalidInput(format!(
+                "voting quorum {quorum} must be between 1 and the {} members",
+                self.members.len()
+            )));
+        }
+        self.quorum = quorum;
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+#[async_trait::async_trait]
+impl ClaimVerifier for VotingVerifier {
+    async fn verify(&self, evidence: EvidenceRecord, binary: bool, prompts: &PromptTemplates) -> Result<VerificationRecord> {
+        let claims = evidence.claim_snippets_dict.len();
+        let answers = join_all(self.members.iter().map(|(m, _)| m.verify(evidence.clone(), binary, prompts))).await;
+        let mut ballots = Vec::new();
+        let mut first_error = None;
+        for (answer, (_, weight)) in answers.into_iter().zip(&self.members) {
+            let answer = answer.and_then(|ballot| {
+                if ballot.claim_verification_result.len() == claims {
+                    Ok(ballot)
+                } else {
+                    Err(CoreError::OutputCountMismatch {
+                        stage: "verification vote",
+                        expected: claims,
+                        got: ballot.claim_verification_result.len(),
+                    }
+                    .into())
+                }
+            });
+            match answer {
+                Ok(ballot) => ballots.push((ballot, *weight)),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if ballots.len() < self.quorum {
+            let err = first_error.expect("a missing ballot has an error");
+            return Err(err.context(format!("only {} of {} voters answered; the quorum is {}", ballots.len(), self.members.len(), self.quorum)));
+        }
+
+        let mut support = vec![0.0f32; claims];
+        let mut total = vec![0.0f32; claims];
+        for (ballot, weight) in &ballots {
+            for (i, c) in ballot.claim_verification_result.iter().enumerate() {
+                total[i] += weight;
+                if matches!(c.verification_result, VerificationLabel::Supported) {
+                    support[i] += weight;
+                }
+            }
+        }
+
+        let results = evidence
+            .claim_snippets_dict
+            .iter()
+            .enumerate()
+            .map(|(i, (claim, hits))| {
+                let share = if total[i] > 0.0 { support[i] / total[i] } else { 0.0 };
+                let (label, confidence) =
+                    if share > 0.5 { (VerificationLabel::Supported, share) } else { (VerificationLabel::Unsupported, 1.0 - share) };
+                ClaimVerification {
+                    claim: claim.clone(),
+                    search_results: hits.clone(),
+                    verification_result: label,
+                    confidence: Some(confidence),
+                }
+            })
+            .collect();
+        let usage = ballots.iter().filter_map(|(b, _)| b.verification_usage).reduce(|mut total, u| {
+            total.merge(&u);
+            total
+        });
+        Ok(VerificationRecord { evidence, claim_verification_result: results, verification_usage: usage })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Supports the claims at the given indices.
+    struct Fixed(Vec<usize>);
+
+    #[async_trait::async_trait]
+    impl ClaimVerifier for Fixed {
+        async fn verify(&self, evidence: EvidenceRecord, _binary: bool, _prompts: &PromptTemplates) -> Result<VerificationRecord> {
+            let results = evidence
+                .claim_snippets_dict
+                .iter()
+                .enumerate()
+                .map(|(i, (claim, hits))| ClaimVerification {
+                    claim: claim.clone(),
+                    search_results: hits.clone(),
+                    verification_result: if self.0.contains(&i) { VerificationLabel::Supported } else { VerificationLabel::Unsupported },
+                    confidence: None,
+                })
+                .collect();
+            Ok(VerificationRecord { evidence, claim_verification_result: results, verification_usage: None })
+        }
+    }
+
+    fn record(claims: usize) -> EvidenceRecord {
+        EvidenceRecord {
+            claims: ExtractedClaimsRecord {
+                input: InputRecord { question: None, response: "r".into(), model: None, prompt_source: None },
+                prompt_tok_cnt: None,
+                response_tok_cnt: None,
+                abstained: false,
+                claim_list: vec![],
+                all_claims: vec![],
+                extraction_usage: None,
+            },
+            claim_snippets_dict: (0..claims).map(|i| (format!("claim {i}"), vec![])).collect(),
+            retrieval_usage: None,
+        }
+    }
+
+    fn outcome(out: &VerificationRecord) -> Vec<(bool, f32)> {
+        out.claim_verification_result
+            .iter()
+            .map(|c| (matches!(c.verification_result, VerificationLabel::Supported), c.confidence.unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn majority_wins_and_ties_are_unsupported() {
+        let voter = VotingVerifier::new(vec![
+            Arc::new(Fixed(vec![0, 1])),
+            Arc::new(Fixed(vec![0])),
+            Arc::new(Fixed(vec![0, 2])),
+            Arc::new(Fixed(vec![1])),
+        ])
+        .unwrap();
+
+        let out = voter.verify(record(4), true, &PromptTemplates::default()).await.unwrap();
+
+        assert_eq!(outcome(&out), [(true, 0.75), (false, 0.5), (false, 0.75), (false, 1.0)]);
+        assert!((out.claim_verification_result[1].support() - 0.5).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn weights_shift_the_vote() {
+        let voter = VotingVerifier::weighted(vec![(Arc::new(Fixed(vec![0])), 3.0), (Arc::new(Fixed(vec![])), 1.0)]).unwrap();
+
+        let out = voter.verify(record(1), true, &PromptTemplates::default()).await.unwrap();
+
+        assert_eq!(outcome(&out), [(true, 0.75)]);
+    }
+
+    struct Failing;
+
+    #[async_trait::async_trait]
+    impl ClaimVerifier for Failing {
+        async fn verify(&self, _evidence: EvidenceRecord, _binary: bool, _prompts: &PromptTemplates) -> Result<VerificationRecord> {
+            anyhow::bail!("voter unavailable")
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failing_member_abstains_until_the_quorum_is_missed() {
+        let members: Vec<Arc<dyn ClaimVerifier>> = vec![Arc::new(Fixed(vec![0])), Arc::new(Failing), Arc::new(Fixed(vec![0, 1]))];
+
+        let voter = VotingVerifier::new(members.clone()).unwrap();
+        let out = voter.verify(record(2), true, &PromptTemplates::default()).await.unwrap();
+        assert_eq!(outcome(&out), [(true, 1.0), (false, 0.5)]);
+
+        let strict = VotingVerifier::new(members).unwrap().with_quorum(3).unwrap();
+        let err = strict.verify(record(2), true, &PromptTemplates::default()).await.unwrap_err();
+        assert!(format!("{err:#}").contains("voter unavailable"));
+    }
+
+    #[test]
+    fn rejects_empty_members_and_bad_weights() {
+        assert!(matches!(VotingVerifier::new(vec![]), Err(CoreError::InvalidInput(_))));
+        for w in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(VotingVerifier::weighted(vec![(Arc::new(Fixed(vec![])), w)]).is_err(), "weight {w}");
+        }
+        assert!(VotingVerifier::new(vec![Arc::new(Fixed(vec![]))]).unwrap().with_quorum(2).is_err());
+    }
+}
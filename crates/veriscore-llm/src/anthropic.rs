@@ -0,0 +1,421 @@
+use crate::cache::LlmCache;
+use crate::error::LlmError;
+use crate::sampling::SamplingParams;
+use crate::traits::{Completion, Llm};
+use crate::usage::{ModelPrice, TokenUsage};
+use anyhow::Result;
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPart, ChatCompletionRequestUserMessageContent};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`; used when the sampling params leave it unset.
+pub const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Native Anthropic Messages API backend.
+#[derive(Clone)]
+pub struct AnthropicLlm {
+    http: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+    max_concurrency: usize,
+    sampling: SamplingParams,
+    cache: Option<Arc<LlmCache>>,
+    price: Option<ModelPrice>,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop_sequences: &'a [String],
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Message {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+}
+
+impl AnthropicLlm {
+    pub fn new(
+        model: impl Into<String>,
+        api_base: Option<String>,
+        api_key: impl Into<String>,
+        max_concurrency: usize,
+        cache: Option<Arc<LlmCache>>,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_base: api_base.unwrap_or_else(|| ANTHROPIC_API_BASE.to_string()).trim_end_matches('/').to_string(),
+            api_key: api_key.into(),
+            model: model.into(),
+            max_concurrency,
+            sampling: SamplingParams::default(),
+            cache,
+            price: None,
+        }
+    }
+
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Prices the reported usage of every call.
+    pub fn with_price(mut self, price: ModelPrice) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Looks the configured model (an id or alias) up directly and returns
+    /// the id it resolves to; cheap enough for health probes.
+    pub async fn retrieve_model(&self) -> Result<String> {
+        let response = self
+            .http
+            .get(format!("{}/v1/models/{}", self.api_base, self.model))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .send()
+            .await
+            .map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        if !status.is_success() {
+            return Err(classify_error(status, &body).into());
+        }
+        let model: ModelInfo = serde_json::from_str(&body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+        Ok(model.id)
+    }
+
+    async fn complete(&self, messages: &[ChatCompletionRequestMessage]) -> Result<Completion> {
+        let (system, messages) = to_anthropic(messages)?;
+        let request = MessagesRequest {
+            model: &self.model,
+            max_tokens: self.sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system,
+            messages,
+            temperature: self.sampling.temperature,
+            top_p: self.sampling.top_p,
+            stop_sequences: &self.sampling.stop,
+        };
+        let response = self
+            .http
+            .post(format!("{}/v1/messages", self.api_base))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        if !status.is_success() {
+            return Err(classify_error(status, &body).into());
+        }
+        let parsed: MessagesResponse = serde_json::from_str(&body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+        let usage = parsed.usage.map(|u| TokenUsage { prompt_tokens: u.input_tokens, completion_tokens: u.output_tokens });
+        Ok(Completion {
+            text: parsed.content.into_iter().filter(|b| b.kind == "text").filter_map(|b| b.text).collect(),
+            usage,
+            cost_usd: self.price.zip(usage).map(|(p, u)| p.cost(&u)),
+            ..Default::default()
+        })
+    }
+}
+
+/// Splits out the system prompt and merges consecutive same-role turns,
+/// which the Messages API rejects.
+fn to_anthropic(messages: &[ChatCompletionRequestMessage]) -> Result<(Option<String>, Vec<Message>), LlmError> {
+    let mut system = Vec::new();
+    let mut out: Vec<Message> = Vec::new();
+    for message in messages {
+        let (role, content) = match message {
+            ChatCompletionRequestMessage::System(m) => {
+                system.push(m.content.clone());
+                continue;
+            }
+            ChatCompletionRequestMessage::User(m) => ("user", user_text(&m.content)?),
+            ChatCompletionRequestMessage::Assistant(m) => ("assistant", m.content.clone().unwrap_or_default()),
+            ChatCompletionRequestMessage::Tool(_) | ChatCompletionRequestMessage::Function(_) => {
+                return Err(LlmError::Rejected("tool and function messages are not supported by the Anthropic backend".into()));
+            }
+        };
+        match out.last_mut() {
+            Some(last) if last.role == role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&content);
+            }
+            _ => out.push(Message { role, content }),
+        }
+    }
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    Ok((system, out))
+}
+
+fn user_text(content: &ChatCompletionRequestUserMessageContent) -> Result<String, LlmError> {
+    match content {
+        ChatCompletionRequestUserMessageContent::Text(text) => Ok(text.clone()),
+        ChatCompletionRequestUserMessageContent::Array(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ChatCompletionRequestMessageContentPart::Text(t) => Ok(t.text.as_str()),
+                ChatCompletionRequestMessageContentPart::ImageUrl(_) => {
+                    Err(LlmError::Rejected("image content is not supported by the Anthropic backend".into()))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|texts| texts.join("\n")),
+    }
+}
+
+/// Maps a non-success response using the `error.type` of Anthropic's error body.
+fn classify_error(status: reqwest::StatusCode, body: &str) -> LlmError {
+    let error = serde_json::from_str::<serde_json::Value>(body).ok();
+    let kind = error.as_ref().and_then(|v| v["error"]["type"].as_str()).unwrap_or_default();
+    let message = format!(
+        "{status}: {}",
+        error.as_ref().and_then(|v| v["error"]["message"].as_str()).unwrap_or(body.trim())
+    );
+    match (status.as_u16(), kind) {
+        (_, "rate_limit_error") | (429, _) => LlmError::RateLimited(message),
+        (_, "overloaded_error" | "api_error") | (500..=599, _) => LlmError::Unavailable(message),
+        (_, "authentication_error" | "permission_error" | "not_found_error") | (401 | 403 | 404, _) => {
+            LlmError::Misconfigured(message)
+        }
+        _ if message.contains("credit balance") => LlmError::QuotaExhausted(message),
+        _ => LlmError::Rejected(message),
+    }
+}
+
+#[async_trait::async_trait]
+impl Llm for AnthropicLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let out = self.chat_many_with_usage(prompts).await?;
+        Ok(out.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let requests = prompts.into_iter().enumerate().map(|(idx, messages)| async move {
+            let cache_key = match &self.cache {
+                Some(_) => Some(LlmCache::make_key(&self.model, &self.sampling.cache_input(&messages)?)),
+                None => None,
+            };
+            if let (Some(cache), Some(key)) = (self.cache.as_ref(), cache_key.as_deref()) {
+                if let Some(hit) = cache.get(key)? {
+                    return Ok::<_, anyhow::Error>((idx, Completion { cached: true, ..Completion::text(hit) }));
+                }
+            }
+            let completion = self.complete(&messages).await?;
+            if let (Some(cache), Some(key)) = (self.cache.as_ref(), cache_key.as_deref()) {
+                cache.put(key, &completion.text)?;
+            }
+            Ok::<_, anyhow::Error>((idx, completion))
+        });
+
+        let mut results = stream::iter(requests)
+            .buffer_unordered(self.max_concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        results.sort_by_key(|item| item.as_ref().map(|(idx, _)| *idx).unwrap_or(usize::MAX));
+        let mut out = Vec::with_capacity(results.len());
+        for result in results {
+            let (_, completion) = result?;
+            out.push(completion);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::{
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs,
+    };
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use std::sync::Mutex;
+
+    type Seen = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    fn system(text: &str) -> ChatCompletionRequestMessage {
+        ChatCompletionRequestSystemMessageArgs::default().content(text).build().unwrap().into()
+    }
+
+    fn user(text: &str) -> ChatCompletionRequestMessage {
+        ChatCompletionRequestUserMessageArgs::default().content(text).build().unwrap().into()
+    }
+
+    /// Local stand-in for the Messages API: echoes the last user turn, and
+    /// answers "overloaded" to prompts containing that word.
+    async fn mock_server() -> (String, Seen) {
+        async fn messages(
+            State(seen): State<Seen>,
+            headers: HeaderMap,
+            Json(body): Json<serde_json::Value>,
+        ) -> (StatusCode, Json<serde_json::Value>) {
+            assert_eq!(headers["x-api-key"], "test-key");
+            assert_eq!(headers["anthropic-version"], ANTHROPIC_VERSION);
+            seen.lock().unwrap().push(body.clone());
+            let last = body["messages"].as_array().unwrap().last().unwrap()["content"].as_str().unwrap().to_string();
+            if last.contains("overloaded") {
+                let error = serde_json::json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}});
+                return (StatusCode::from_u16(529).unwrap(), Json(error));
+            }
+            let reply = serde_json::json!({
+                "content": [{"type": "text", "text": format!("echo: {last}")}],
+                "usage": {"input_tokens": 12, "output_tokens": 3},
+            });
+            (StatusCode::OK, Json(reply))
+        }
+
+        /// Knows one model, also under a `-latest` alias.
+        async fn model(headers: HeaderMap, Path(id): Path<String>) -> (StatusCode, Json<serde_json::Value>) {
+            assert_eq!(headers["x-api-key"], "test-key");
+            match id.as_str() {
+                "claude-test" | "claude-test-latest" => (StatusCode::OK, Json(serde_json::json!({"type": "model", "id": "claude-test"}))),
+                _ => {
+                    let error = serde_json::json!({"type": "error", "error": {"type": "not_found_error", "message": format!("model: {id}")}});
+                    (StatusCode::NOT_FOUND, Json(error))
+                }
+            }
+        }
+
+        let seen = Seen::default();
+        let app = Router::new()
+            .route("/v1/messages", post(messages))
+            .route("/v1/models/:id", get(model))
+            .with_state(seen.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), seen)
+    }
+
+    #[test]
+    fn maps_system_prompt_and_merges_same_role_turns() {
+        let assistant = ChatCompletionRequestAssistantMessageArgs::default().content("ok").build().unwrap().into();
+        let (system_prompt, messages) =
+            to_anthropic(&[system("be brief"), user("a"), user("b"), assistant, system("and exact")]).unwrap();
+
+        assert_eq!(system_prompt.as_deref(), Some("be brief\n\nand exact"));
+        assert_eq!(
+            messages,
+            vec![Message { role: "user", content: "a\n\nb".into() }, Message { role: "assistant", content: "ok".into() }]
+        );
+    }
+
+    #[test]
+    fn classifies_anthropic_errors() {
+        let err = |status: u16, body: &str| classify_error(reqwest::StatusCode::from_u16(status).unwrap(), body);
+        let body = |kind: &str, message: &str| format!(r#"{{"type":"error","error":{{"type":"{kind}","message":"{message}"}}}}"#);
+
+        assert!(matches!(err(429, &body("rate_limit_error", "slow down")), LlmError::RateLimited(_)));
+        assert!(matches!(err(529, &body("overloaded_error", "Overloaded")), LlmError::Unavailable(_)));
+        assert!(matches!(
+            err(400, &body("invalid_request_error", "Your credit balance is too low")),
+            LlmError::QuotaExhausted(_)
+        ));
+        assert!(matches!(err(400, &body("invalid_request_error", "max_tokens: too large")), LlmError::Rejected(_)));
+        assert!(matches!(err(502, "bad gateway"), LlmError::Unavailable(_)));
+    }
+
+    #[tokio::test]
+    async fn chat_many_calls_messages_api_and_uses_cache() {
+        let (base, seen) = mock_server().await;
+        let cache = Arc::new(LlmCache::open(":memory:").unwrap());
+        let llm = AnthropicLlm::new("claude-test", Some(base.clone()), "test-key", 4, Some(cache))
+            .with_sampling(SamplingParams { temperature: Some(0.0), stop: vec!["\n\n".into()], ..Default::default() });
+
+        let prompts = vec![vec![system("judge"), user("first")], vec![user("second")]];
+        let out = llm.chat_many(prompts.clone()).await.unwrap();
+        assert_eq!(out, vec!["echo: first", "echo: second"]);
+
+        let requests = seen.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        let first = requests.iter().find(|r| r["system"] == "judge").unwrap();
+        assert_eq!(first["model"], "claude-test");
+        assert_eq!(first["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(first["temperature"], 0.0);
+        assert_eq!(first["stop_sequences"], serde_json::json!(["\n\n"]));
+        assert!(first.get("top_p").is_none());
+
+        // second pass is served from the cache
+        assert_eq!(llm.chat_many(prompts.clone()).await.unwrap(), out);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+
+        let price = ModelPrice { input_per_mtok: 1.0, output_per_mtok: 5.0 };
+        let fresh = AnthropicLlm::new("claude-test", Some(base), "test-key", 1, None).with_price(price);
+        let with_usage = fresh.chat_many_with_usage(vec![prompts[1].clone()]).await.unwrap();
+        assert_eq!(with_usage[0].usage, Some(TokenUsage { prompt_tokens: 12, completion_tokens: 3 }));
+        assert!((with_usage[0].cost_usd.unwrap() - 27e-6).abs() < 1e-12);
+        assert!(!with_usage[0].cached);
+    }
+
+    #[tokio::test]
+    async fn http_errors_surface_as_llm_errors() {
+        let (base, _) = mock_server().await;
+        let llm = AnthropicLlm::new("claude-test", Some(base), "test-key", 1, None);
+
+        let err = llm.chat_one(vec![user("you are overloaded")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Unavailable(msg)) if msg.contains("Overloaded")));
+    }
+
+    #[tokio::test]
+    async fn retrieve_model_resolves_ids_and_aliases() {
+        let (base, _) = mock_server().await;
+        let llm = |model: &str| AnthropicLlm::new(model, Some(base.clone()), "test-key", 1, None);
+
+        assert_eq!(llm("claude-test").retrieve_model().await.unwrap(), "claude-test");
+        assert_eq!(llm("claude-test-latest").retrieve_model().await.unwrap(), "claude-test");
+        let err = llm("claude-missing").retrieve_model().await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Misconfigured(msg)) if msg.contains("claude-missing")));
+    }
+}
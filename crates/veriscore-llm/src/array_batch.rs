@@ -0,0 +1,336 @@
+use crate::cache::LlmCache;
+use crate::error::LlmError;
+use crate::openai::{cache_entry, cache_input, cached_completion, chat_request};
+use crate::sampling::SamplingParams;
+use crate::traits::{Completion, Llm};
+use crate::usage::{ModelPrice, TokenCounter, TokenUsage};
+use anyhow::Result;
+use async_openai::error::{ApiError, OpenAIError};
+use async_openai::types::{ChatChoiceLogprobs, ChatCompletionRequestMessage};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Prompts per request unless set with [`ArrayBatchLlm::with_max_batch_size`].
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 64;
+
+/// Transport for servers with an array batch endpoint (e.g. some Matrix
+/// builds): `chat_many` posts the prompts as one JSON array of chat
+/// completions request bodies, split into requests of at most
+/// `max_batch_size`. The server answers with an array of chat completions
+/// responses, each with an optional `index` into the request array (its
+/// position otherwise) or an OpenAI-style `error` object.
+///
+/// Cache hits are answered locally and never sent. Pairs with
+/// [`crate::BatchedLlm`], whose micro-batches become one request each.
+#[derive(Clone)]
+pub struct ArrayBatchLlm {
+    http: reqwest::Client,
+    batch_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_batch_size: usize,
+    max_concurrency: usize,
+    sampling: SamplingParams,
+    cache: Option<Arc<LlmCache>>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    price: Option<ModelPrice>,
+}
+
+/// One chat completions answer in a batch: a response body or an error.
+#[derive(Debug, Deserialize)]
+pub(crate) struct BatchItem {
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+    #[serde(default)]
+    error: Option<ApiError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+    #[serde(default)]
+    logprobs: Option<ChatChoiceLogprobs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+impl ArrayBatchLlm {
+    /// `max_concurrency` bounds the batch requests in flight, not the prompts.
+    pub fn new(
+        model: impl Into<String>,
+        batch_url: impl Into<String>,
+        api_key: Option<String>,
+        max_concurrency: usize,
+        cache: Option<Arc<LlmCache>>,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            batch_url: batch_url.into(),
+            api_key,
+            model: model.into(),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_concurrency,
+            sampling: SamplingParams::default(),
+            cache,
+            token_counter: None,
+            price: None,
+        }
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Counts tokens locally when the server's responses carry no `usage`.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
+    /// Prices the reported (or counted) usage of every prompt.
+    pub fn with_price(mut self, price: ModelPrice) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>, logprobs: bool) -> Result<Vec<Completion>> {
+        let mut out: Vec<Option<Completion>> = vec![None; prompts.len()];
+        let mut misses = Vec::new();
+        for (idx, messages) in prompts.into_iter().enumerate() {
+            let key = match &self.cache {
+                Some(_) => Some(LlmCache::make_key(&self.model, &cache_input(&self.sampling, &messages, logprobs)?)),
+                None => None,
+            };
+            if let (Some(cache), Some(key)) = (self.cache.as_ref(), key.as_deref()) {
+                if let Some(hit) = cache.get(key)? {
+                    out[idx] = Some(cached_completion(hit, logprobs)?);
+                    continue;
+                }
+            }
+            misses.push((idx, messages, key));
+        }
+
+        let chunks: Vec<Vec<_>> = misses.chunks(self.max_batch_size).map(<[_]>::to_vec).collect();
+        let mut results = stream::iter(chunks.into_iter().map(|chunk| self.post(chunk, logprobs)))
+            .buffer_unordered(self.max_concurrency.max(1));
+        while let Some(result) = results.next().await {
+            for (idx, completion) in result? {
+                out[idx] = Some(completion);
+            }
+        }
+        Ok(out.into_iter().map(|c| c.expect("every prompt answered")).collect())
+    }
+
+    /// One batch request; answers are keyed by the prompts' positions in the
+    /// whole `chat_many` call.
+    async fn post(
+        &self,
+        chunk: Vec<(usize, Vec<ChatCompletionRequestMessage>, Option<String>)>,
+        logprobs: bool,
+    ) -> Result<Vec<(usize, Completion)>> {
+        let body = chunk
+            .iter()
+            .map(|(_, messages, _)| chat_request(&self.model, messages.clone(), &self.sampling, logprobs))
+            .collect::<Result<Vec<_>>>()?;
+        let mut request = self.http.post(&self.batch_url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await.map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        if !status.is_success() {
+            return Err(classify_status(status, &text).into());
+        }
+        let items: Vec<BatchItem> =
+            serde_json::from_str(&text).map_err(|e| LlmError::BadResponse(format!("invalid batch response: {e}")))?;
+        if items.len() != chunk.len() {
+            return Err(LlmError::BadResponse(format!("batch of {} prompts got {} answers", chunk.len(), items.len())).into());
+        }
+
+        let mut answers: Vec<Option<BatchItem>> = (0..chunk.len()).map(|_| None).collect();
+        for (position, item) in items.into_iter().enumerate() {
+            let index = item.index.unwrap_or(position);
+            match answers.get_mut(index) {
+                Some(slot @ None) => *slot = Some(item),
+                _ => return Err(LlmError::BadResponse(format!("batch answer index {index} is out of range or repeated")).into()),
+            }
+        }
+
+        let mut out = Vec::with_capacity(chunk.len());
+        for ((idx, messages, key), item) in chunk.into_iter().zip(answers) {
+            let item = item.expect("one answer per prompt");
+            let completion = item.into_completion(&messages, self.token_counter.as_deref(), self.price)?;
+            if let (Some(cache), Some(key)) = (self.cache.as_ref(), key.as_deref()) {
+                cache.put(key, &cache_entry(&completion, logprobs)?)?;
+            }
+            out.push((idx, completion));
+        }
+        Ok(out)
+    }
+}
+
+impl BatchItem {
+    pub(crate) fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub(crate) fn into_completion(
+        self,
+        messages: &[ChatCompletionRequestMessage],
+        counter: Option<&dyn TokenCounter>,
+        price: Option<ModelPrice>,
+    ) -> Result<Completion, LlmError> {
+        if let Some(error) = self.error {
+            return Err(LlmError::from(OpenAIError::ApiError(error)));
+        }
+        let choice = self.choices.into_iter().next();
+        let text = choice.as_ref().and_then(|c| c.message.content.clone()).unwrap_or_default();
+        let usage = match (self.usage, counter) {
+            (Some(u), _) => Some(TokenUsage { prompt_tokens: u.prompt_tokens, completion_tokens: u.completion_tokens }),
+            (None, Some(counter)) => Some(counter.usage(messages, &text)),
+            (None, None) => None,
+        };
+        Ok(Completion {
+            text,
+            logprobs: choice.and_then(|c| c.logprobs).and_then(|l| l.content),
+            usage,
+            cost_usd: price.zip(usage).map(|(p, u)| p.cost(&u)),
+            cached: false,
+        })
+    }
+}
+
+/// Maps a failed batch request by status; the body is kept as the message.
+pub(crate) fn classify_status(status: reqwest::StatusCode, body: &str) -> LlmError {
+    LlmError::from_status(status.as_u16(), format!("{status}: {}", body.trim()))
+}
+
+#[async_trait::async_trait]
+impl Llm for ArrayBatchLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let out = self.complete(prompts, false).await?;
+        Ok(out.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, false).await
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use std::sync::Mutex;
+
+    type Seen = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    fn user(text: &str) -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content(text).build().unwrap().into()]
+    }
+
+    /// Echoes each prompt, answering in reverse order with explicit indices;
+    /// prompts containing "bad" get an item error, and "busy" fails the batch.
+    async fn mock_server() -> (String, Seen) {
+        async fn batch(State(seen): State<Seen>, Json(body): Json<serde_json::Value>) -> (StatusCode, Json<serde_json::Value>) {
+            seen.lock().unwrap().push(body.clone());
+            let prompts: Vec<String> =
+                body.as_array().unwrap().iter().map(|r| r["messages"][0]["content"].as_str().unwrap().to_string()).collect();
+            if prompts.iter().any(|p| p.contains("busy")) {
+                return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "busy"})));
+            }
+            let items: Vec<_> = prompts
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, prompt)| {
+                    if prompt.contains("bad") {
+                        return serde_json::json!({"index": index, "error": {"message": "prompt too long", "type": "invalid_request_error"}});
+                    }
+                    serde_json::json!({
+                        "index": index,
+                        "choices": [{"message": {"role": "assistant", "content": format!("echo: {prompt}")}}],
+                        "usage": {"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12},
+                    })
+                })
+                .collect();
+            (StatusCode::OK, Json(serde_json::Value::Array(items)))
+        }
+
+        let seen = Seen::default();
+        let app = Router::new().route("/batch", post(batch)).with_state(seen.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}/batch"), seen)
+    }
+
+    #[tokio::test]
+    async fn splits_batches_maps_answers_by_index_and_caches() {
+        let (url, seen) = mock_server().await;
+        let cache = Arc::new(LlmCache::open(":memory:").unwrap());
+        let llm = ArrayBatchLlm::new("m", url, None, 2, Some(cache))
+            .with_max_batch_size(2)
+            .with_sampling(SamplingParams { temperature: Some(0.0), ..Default::default() });
+
+        let prompts: Vec<_> = ["a", "b", "c"].map(user).into_iter().collect();
+        let out = llm.chat_many_with_usage(prompts.clone()).await.unwrap();
+        assert_eq!(out.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(), ["echo: a", "echo: b", "echo: c"]);
+        assert_eq!(out[0].usage, Some(TokenUsage { prompt_tokens: 10, completion_tokens: 2 }));
+
+        let requests = seen.lock().unwrap().clone();
+        let mut sizes: Vec<_> = requests.iter().map(|r| r.as_array().unwrap().len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, [1, 2]);
+        assert_eq!(requests[0][0]["model"], "m");
+        assert_eq!(requests[0][0]["temperature"], 0.0);
+
+        // cached prompts are not resent
+        let more = vec![prompts[1].clone(), user("d")];
+        assert_eq!(llm.chat_many(more).await.unwrap(), ["echo: b", "echo: d"]);
+        let last = seen.lock().unwrap().last().unwrap().clone();
+        assert_eq!(last.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn item_and_batch_errors_surface_as_llm_errors() {
+        let (url, _) = mock_server().await;
+        let llm = ArrayBatchLlm::new("m", url, None, 1, None);
+
+        let err = llm.chat_many(vec![user("ok"), user("bad")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Rejected(msg)) if msg.contains("prompt too long")));
+        let err = llm.chat_many(vec![user("busy")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Unavailable(msg)) if msg.starts_with("503")));
+    }
+}
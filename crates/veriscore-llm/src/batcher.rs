@@ -1,9 +1,11 @@
-use crate::traits::Llm;
+use crate::error::LlmError;
+use crate::traits::{Llm, Completion};
 use anyhow::{anyhow, Result};
 use async_openai::types::ChatCompletionRequestMessage;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::{sleep, Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
 #[derive(Debug, Clone)]
@@ -25,19 +27,41 @@ impl Default for MicroBatchConfig {
 
 struct BatchItem {
     prompt: Vec<ChatCompletionRequestMessage>,
-    tx: oneshot::Sender<Result<String>>,
+    /// Sent through `chat_many_scored` instead of `chat_many_with_usage`.
+    scored: bool,
+    tx: oneshot::Sender<Result<Completion>>,
 }
 
 #[derive(Clone)]
 pub struct BatchedLlm {
     tx: mpsc::Sender<BatchItem>,
+    shutdown: CancellationToken,
+    stopped: CancellationToken,
 }
 
 impl BatchedLlm {
     pub fn spawn(inner: Arc<dyn Llm>, cfg: MicroBatchConfig) -> Self {
         let (tx, mut rx) = mpsc::channel::<BatchItem>(cfg.queue_capacity);
+        let shutdown = CancellationToken::new();
+        let stopped = CancellationToken::new();
+        let (worker_shutdown, worker_stopped) = (shutdown.clone(), stopped.clone());
         tokio::spawn(async move {
-            while let Some(first) = rx.recv().await {
+            let _stopped = worker_stopped.drop_guard();
+            let mut draining = false;
+            loop {
+                let first = tokio::select! {
+                    biased;
+                    maybe_item = rx.recv() => match maybe_item {
+                        Some(item) => item,
+                        None => break,
+                    },
+                    // refuse new prompts but keep flushing the ones already queued
+                    _ = worker_shutdown.cancelled(), if !draining => {
+                        rx.close();
+                        draining = true;
+                        continue;
+                    }
+                };
                 let mut batch = vec![first];
                 let deadline = Instant::now() + cfg.max_wait;
 
@@ -56,40 +80,82 @@ impl BatchedLlm {
                             }
                         }
                         _ = sleep(remaining) => break,
+                        // flush partial batches right away while draining
+                        _ = worker_shutdown.cancelled() => break,
                     }
                 }
 
-                let prompts = batch.iter().map(|b| b.prompt.clone()).collect::<Vec<_>>();
-                match inner.chat_many(prompts).await {
-                    Ok(outputs) => {
-                        if outputs.len() != batch.len() {
-                            let err = anyhow!("batch size mismatch: got {} outputs for {} prompts", outputs.len(), batch.len());
-                            for item in batch {
-                                let _ = item.tx.send(Err(anyhow!(err.to_string())));
-                            }
-                        } else {
-                            for (item, text) in batch.into_iter().zip(outputs) {
-                                let _ = item.tx.send(Ok(text));
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        warn!(error = %err, "batched LLM call failed");
-                        for item in batch {
-                            let _ = item.tx.send(Err(anyhow!(err.to_string())));
-                        }
-                    }
-                }
+                let (scored, plain) = batch.into_iter().partition::<Vec<_>, _>(|b| b.scored);
+                tokio::join!(flush(inner.as_ref(), scored, true), flush(inner.as_ref(), plain, false));
                 debug!("flushed micro-batch");
             }
         });
-        Self { tx }
+        Self { tx, shutdown, stopped }
+    }
+
+    /// Stops accepting prompts, flushes everything already queued and waits
+    /// for the last batch to be answered. Shared by every clone.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.stopped.cancelled().await;
     }
 
     pub async fn submit(&self, prompt: Vec<ChatCompletionRequestMessage>) -> Result<String> {
+        Ok(self.enqueue(prompt, false).await?.text)
+    }
+
+    pub async fn submit_scored(&self, prompt: Vec<ChatCompletionRequestMessage>) -> Result<Completion> {
+        self.enqueue(prompt, true).await
+    }
+
+    async fn enqueue(&self, prompt: Vec<ChatCompletionRequestMessage>, scored: bool) -> Result<Completion> {
         let (tx, rx) = oneshot::channel();
-        self.tx.send(BatchItem { prompt, tx }).await.map_err(|_| anyhow!("micro-batcher closed"))?;
-        rx.await.map_err(|_| anyhow!("micro-batch response channel closed"))?
+        self.tx
+            .send(BatchItem { prompt, scored, tx })
+            .await
+            .map_err(|_| LlmError::Unavailable("micro-batcher closed".into()))?;
+        rx.await.map_err(|_| LlmError::Unavailable("micro-batch response channel closed".into()))?
+    }
+}
+
+/// Sends one homogeneous part of a micro-batch and answers its callers.
+async fn flush(inner: &dyn Llm, batch: Vec<BatchItem>, scored: bool) {
+    if batch.is_empty() {
+        return;
+    }
+    let prompts = batch.iter().map(|b| b.prompt.clone()).collect::<Vec<_>>();
+    let result = if scored { inner.chat_many_scored(prompts).await } else { inner.chat_many_with_usage(prompts).await };
+    match result {
+        Ok(outputs) => {
+            if outputs.len() != batch.len() {
+                let err = LlmError::BadResponse(format!(
+                    "batch size mismatch: got {} outputs for {} prompts",
+                    outputs.len(),
+                    batch.len()
+                ));
+                for item in batch {
+                    let _ = item.tx.send(Err(err.clone().into()));
+                }
+            } else {
+                for (item, completion) in batch.into_iter().zip(outputs) {
+                    let _ = item.tx.send(Ok(completion));
+                }
+            }
+        }
+        Err(err) => {
+            warn!(error = %err, "batched LLM call failed");
+            for item in batch {
+                let _ = item.tx.send(Err(share_error(&err)));
+            }
+        }
+    }
+}
+
+/// Copies a batch failure for each waiting caller, keeping its `LlmError` class.
+fn share_error(err: &anyhow::Error) -> anyhow::Error {
+    match LlmError::find(err) {
+        Some(llm_err) => llm_err.clone().into(),
+        None => anyhow!(err.to_string()),
     }
 }
 
@@ -102,4 +168,80 @@ impl Llm for BatchedLlm {
         }
         Ok(out)
     }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let mut out = Vec::with_capacity(prompts.len());
+        for prompt in prompts {
+            out.push(self.enqueue(prompt, false).await?);
+        }
+        Ok(out)
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let mut out = Vec::with_capacity(prompts.len());
+        for prompt in prompts {
+            out.push(self.submit_scored(prompt).await?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    struct EchoLlm;
+
+    #[async_trait::async_trait]
+    impl Llm for EchoLlm {
+        async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+            Ok(prompts.iter().map(|p| format!("{} messages", p.len())).collect())
+        }
+
+        async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+            Ok(prompts.iter().map(|_| Completion { logprobs: Some(vec![]), ..Completion::text("scored".into()) }).collect())
+        }
+    }
+
+    fn prompt() -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content("hi").build().unwrap().into()]
+    }
+
+    #[tokio::test]
+    async fn shutdown_flushes_queued_prompts_then_rejects_new_ones() {
+        let llm = BatchedLlm::spawn(
+            Arc::new(EchoLlm),
+            MicroBatchConfig { max_batch_size: 64, max_wait: Duration::from_secs(60), queue_capacity: 16 },
+        );
+        let pending = (0..3)
+            .map(|_| {
+                let llm = llm.clone();
+                tokio::spawn(async move { llm.submit(prompt()).await })
+            })
+            .collect::<Vec<_>>();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // without the shutdown these would wait out the 60s batch window
+        tokio::time::timeout(Duration::from_secs(5), llm.shutdown()).await.unwrap();
+        for handle in pending {
+            assert_eq!(handle.await.unwrap().unwrap(), "1 messages");
+        }
+
+        let err = llm.submit(prompt()).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Unavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn scored_and_plain_prompts_share_a_batch_window() {
+        let llm = BatchedLlm::spawn(
+            Arc::new(EchoLlm),
+            MicroBatchConfig { max_batch_size: 8, max_wait: Duration::from_millis(20), queue_capacity: 16 },
+        );
+
+        let (plain, scored) = tokio::join!(llm.submit(prompt()), llm.submit_scored(prompt()));
+
+        assert_eq!(plain.unwrap(), "1 messages");
+        assert_eq!(scored.unwrap().logprobs, Some(vec![]));
+    }
 }
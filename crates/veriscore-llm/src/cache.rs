@@ -5,7 +5,8 @@ use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct LlmCache {
-    conn: Arc<Mutex<Connection>>,
+    // `None` once closed; later lookups miss and writes are dropped
+    conn: Arc<Mutex<Option<Connection>>>,
 }
 
 impl LlmCache {
@@ -19,15 +20,18 @@ impl LlmCache {
             );
             "#,
         )?;
-        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+        Ok(Self { conn: Arc::new(Mutex::new(Some(conn))) })
     }
 
     pub fn make_key(model: &str, prompt_json: &str) -> String {
-        format!("{}:{}", model, format!("{:x}", md5::compute(prompt_json.as_bytes())))
+        format!("{}:{:x}", model, md5::compute(prompt_json.as_bytes()))
     }
 
     pub fn get(&self, key: &str) -> Result<Option<String>> {
-        let conn = self.conn.lock().expect("llm cache poisoned");
+        let guard = self.conn.lock().expect("llm cache poisoned");
+        let Some(conn) = guard.as_ref() else {
+            return Ok(None);
+        };
         let mut stmt = conn.prepare("SELECT response FROM llm_cache WHERE cache_key = ?1")?;
         let mut rows = stmt.query(params![key])?;
         if let Some(row) = rows.next()? {
@@ -38,11 +42,50 @@ impl LlmCache {
     }
 
     pub fn put(&self, key: &str, response: &str) -> Result<()> {
-        let conn = self.conn.lock().expect("llm cache poisoned");
+        let guard = self.conn.lock().expect("llm cache poisoned");
+        let Some(conn) = guard.as_ref() else {
+            return Ok(());
+        };
         conn.execute(
             "INSERT OR REPLACE INTO llm_cache(cache_key, response) VALUES(?1, ?2)",
             params![key, response],
         )?;
         Ok(())
     }
+
+    /// Checks the database is open and can take a write lock.
+    pub fn probe(&self) -> Result<()> {
+        let guard = self.conn.lock().expect("llm cache poisoned");
+        let conn = guard.as_ref().ok_or_else(|| anyhow::anyhow!("llm cache is closed"))?;
+        conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")?;
+        Ok(())
+    }
+
+    /// Waits for any in-progress write and closes the connection. Shared by
+    /// every clone of this cache.
+    pub fn close(&self) -> Result<()> {
+        let conn = self.conn.lock().expect("llm cache poisoned").take();
+        if let Some(conn) = conn {
+            conn.close().map_err(|(_, err)| err)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_cache_misses_and_drops_writes() {
+        let cache = LlmCache::open(":memory:").unwrap();
+        cache.put("k", "v").unwrap();
+        assert_eq!(cache.get("k").unwrap().as_deref(), Some("v"));
+
+        let clone = cache.clone();
+        cache.close().unwrap();
+        assert_eq!(clone.get("k").unwrap(), None);
+        clone.put("k2", "v2").unwrap();
+        cache.close().unwrap();
+    }
 }
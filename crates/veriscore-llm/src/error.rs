@@ -0,0 +1,131 @@
+use async_openai::error::OpenAIError;
+
+/// Failures talking to an LLM backend, classified so callers can tell
+/// transient upstream trouble from requests that will never succeed.
+///
+/// `Llm` methods still return `anyhow::Result`; these errors travel inside the
+/// `anyhow` chain and can be recovered with [`LlmError::find`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum LlmError {
+    #[error("LLM backend rate limited the request: {0}")]
+    RateLimited(String),
+    #[error("LLM backend quota exhausted: {0}")]
+    QuotaExhausted(String),
+    #[error("LLM backend unavailable: {0}")]
+    Unavailable(String),
+    #[error("LLM backend rejected the request: {0}")]
+    Rejected(String),
+    /// The endpoint refused the key or does not serve the model (401, 403,
+    /// 404): its configuration is wrong, not the request.
+    #[error("LLM endpoint is misconfigured: {0}")]
+    Misconfigured(String),
+    #[error("LLM backend returned a malformed response: {0}")]
+    BadResponse(String),
+}
+
+impl LlmError {
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, LlmError::RateLimited(_) | LlmError::Unavailable(_) | LlmError::BadResponse(_))
+    }
+
+    /// Classifies a non-success HTTP status; `message` is kept as is.
+    pub fn from_status(status: u16, message: String) -> Self {
+        match status {
+            429 => LlmError::RateLimited(message),
+            401 | 403 | 404 => LlmError::Misconfigured(message),
+            400..=499 => LlmError::Rejected(message),
+            _ => LlmError::Unavailable(message),
+        }
+    }
+
+    /// The first `LlmError` in an error's source chain.
+    pub fn find(err: &anyhow::Error) -> Option<&LlmError> {
+        err.chain().find_map(|e| e.downcast_ref::<LlmError>())
+    }
+}
+
+impl From<OpenAIError> for LlmError {
+    fn from(err: OpenAIError) -> Self {
+        match err {
+            OpenAIError::Reqwest(e) => match e.status() {
+                Some(status) => LlmError::from_status(status.as_u16(), e.to_string()),
+                None => LlmError::Unavailable(e.to_string()),
+            },
+            OpenAIError::ApiError(api) => {
+                let kind = [api.r#type.as_deref(), api.code.as_deref()];
+                let message = api.to_string();
+                if kind.contains(&Some("insufficient_quota")) {
+                    LlmError::QuotaExhausted(message)
+                } else if kind.iter().flatten().any(|k| k.contains("rate_limit")) {
+                    LlmError::RateLimited(message)
+                } else if kind.iter().flatten().any(|k| k.contains("server_error") || k.contains("overloaded")) {
+                    LlmError::Unavailable(message)
+                } else if kind.iter().flatten().any(|k| ["api_key", "authentication", "permission", "not_found"].iter().any(|m| k.contains(m))) {
+                    LlmError::Misconfigured(message)
+                } else {
+                    LlmError::Rejected(message)
+                }
+            }
+            OpenAIError::JSONDeserialize(e) => LlmError::BadResponse(e.to_string()),
+            OpenAIError::StreamError(e) => LlmError::Unavailable(e),
+            OpenAIError::InvalidArgument(e) => LlmError::Rejected(e),
+            other => LlmError::Unavailable(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::ApiError;
+
+    fn api_error(r#type: Option<&str>, code: Option<&str>) -> OpenAIError {
+        OpenAIError::ApiError(ApiError {
+            message: "boom".to_string(),
+            r#type: r#type.map(str::to_string),
+            param: None,
+            code: code.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn classifies_openai_api_errors() {
+        let quota = LlmError::from(api_error(Some("insufficient_quota"), None));
+        assert!(matches!(quota, LlmError::QuotaExhausted(_)));
+        assert!(!quota.is_retryable());
+
+        let rate = LlmError::from(api_error(Some("requests"), Some("rate_limit_exceeded")));
+        assert!(matches!(rate, LlmError::RateLimited(_)));
+        assert!(rate.is_retryable());
+
+        let server = LlmError::from(api_error(Some("server_error"), None));
+        assert!(matches!(server, LlmError::Unavailable(_)));
+
+        let bad = LlmError::from(api_error(Some("invalid_request_error"), Some("context_length_exceeded")));
+        assert!(matches!(bad, LlmError::Rejected(_)));
+        assert!(!bad.is_retryable());
+
+        let key = LlmError::from(api_error(Some("invalid_request_error"), Some("invalid_api_key")));
+        assert!(matches!(key, LlmError::Misconfigured(_)));
+        let model = LlmError::from(api_error(Some("invalid_request_error"), Some("model_not_found")));
+        assert!(matches!(model, LlmError::Misconfigured(_)));
+        assert!(!model.is_retryable());
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        assert!(matches!(LlmError::from_status(401, "bad key".into()), LlmError::Misconfigured(_)));
+        assert!(matches!(LlmError::from_status(404, "no such model".into()), LlmError::Misconfigured(_)));
+        assert!(matches!(LlmError::from_status(400, "too long".into()), LlmError::Rejected(_)));
+        assert!(matches!(LlmError::from_status(429, "slow down".into()), LlmError::RateLimited(_)));
+        assert!(matches!(LlmError::from_status(503, "down".into()), LlmError::Unavailable(_)));
+    }
+
+    #[test]
+    fn find_looks_through_context() {
+        let err = anyhow::Error::from(LlmError::Unavailable("down".into())).context("extraction failed");
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Unavailable(_))));
+        assert!(LlmError::find(&anyhow::anyhow!("other")).is_none());
+    }
+}
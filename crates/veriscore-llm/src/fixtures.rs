@@ -0,0 +1,166 @@
+use crate::sampling::SamplingParams;
+use crate::traits::{Completion, Llm};
+use anyhow::Result;
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use veriscore_common::fixtures::{FixtureWriter, Fixtures};
+
+/// What an `Llm` call is recorded under: the model, sampling settings, prompt
+/// and whether logprobs were asked for.
+#[derive(Serialize)]
+struct LlmRequest<'a> {
+    model: &'a str,
+    #[serde(skip_serializing_if = "SamplingParams::is_default")]
+    sampling: &'a SamplingParams,
+    messages: &'a [ChatCompletionRequestMessage],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    scored: bool,
+}
+
+/// Passes calls through to another [`Llm`] and records every prompt and its
+/// completion, to be served later by [`ReplayLlm`].
+pub struct RecordingLlm {
+    inner: Arc<dyn Llm>,
+    model: String,
+    sampling: SamplingParams,
+    writer: FixtureWriter,
+}
+
+impl RecordingLlm {
+    /// `model` and `sampling` are what `inner` was built with; they are part
+    /// of each request's hash.
+    pub fn new(inner: Arc<dyn Llm>, model: impl Into<String>, sampling: SamplingParams, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { inner, model: model.into(), sampling, writer: FixtureWriter::create(path)? })
+    }
+
+    fn record(&self, prompts: &[Vec<ChatCompletionRequestMessage>], completions: &[Completion], scored: bool) -> Result<()> {
+        for (messages, completion) in prompts.iter().zip(completions) {
+            let request = LlmRequest { model: &self.model, sampling: &self.sampling, messages, scored };
+            self.writer.record(&request, completion)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Llm for RecordingLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let texts = self.inner.chat_many(prompts.clone()).await?;
+        let completions: Vec<_> = texts.iter().cloned().map(Completion::text).collect();
+        self.record(&prompts, &completions, false)?;
+        Ok(texts)
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let completions = self.inner.chat_many_with_usage(prompts.clone()).await?;
+        self.record(&prompts, &completions, false)?;
+        Ok(completions)
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let completions = self.inner.chat_many_scored(prompts.clone()).await?;
+        self.record(&prompts, &completions, true)?;
+        Ok(completions)
+    }
+}
+
+/// Serves completions recorded by [`RecordingLlm`], matched by a hash of the
+/// model, sampling settings and prompt. An unrecorded request fails the call
+/// rather than falling back to a backend.
+pub struct ReplayLlm {
+    model: String,
+    sampling: SamplingParams,
+    fixtures: Fixtures<Completion>,
+}
+
+impl ReplayLlm {
+    pub fn from_file(path: impl AsRef<Path>, model: impl Into<String>, sampling: SamplingParams) -> Result<Self> {
+        Ok(Self { model: model.into(), sampling, fixtures: Fixtures::load(path)? })
+    }
+
+    fn replay(&self, prompts: &[Vec<ChatCompletionRequestMessage>], scored: bool) -> Result<Vec<Completion>> {
+        prompts
+            .iter()
+            .map(|messages| self.fixtures.get(&LlmRequest { model: &self.model, sampling: &self.sampling, messages, scored }))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl Llm for ReplayLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        Ok(self.replay(&prompts, false)?.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.replay(&prompts, false)
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.replay(&prompts, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::TokenUsage;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    /// Answers with the prompt's length, so each prompt gets a distinct reply.
+    struct Measure;
+
+    #[async_trait::async_trait]
+    impl Llm for Measure {
+        async fn chat_many(&self, _prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+            unreachable!("chat_many_with_usage is overridden")
+        }
+
+        async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+            Ok(prompts
+                .iter()
+                .map(|p| Completion {
+                    text: serde_json::to_string(p).unwrap().len().to_string(),
+                    usage: Some(TokenUsage { prompt_tokens: 7, completion_tokens: 1 }),
+                    ..Default::default()
+                })
+                .collect())
+        }
+    }
+
+    fn user(text: &str) -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content(text).build().unwrap().into()]
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_completions_and_fails_on_misses() {
+        let dir = std::env::temp_dir().join(format!("veriscore-fixtures-{}", std::process::id()));
+        let path = dir.join("llm.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        let recorder = RecordingLlm::new(Arc::new(Measure), "m", SamplingParams::default(), &path).unwrap();
+        let recorded = recorder.chat_many_with_usage(vec![user("short"), user("a longer prompt")]).await.unwrap();
+        // logprob requests are recorded apart from plain ones
+        recorder.chat_many_scored(vec![user("short")]).await.unwrap();
+
+        let replay = ReplayLlm::from_file(&path, "m", SamplingParams::default()).unwrap();
+        assert_eq!(replay.fixtures.len(), 3);
+        let replayed = replay.chat_many_with_usage(vec![user("a longer prompt"), user("short")]).await.unwrap();
+        assert_eq!(replayed, [recorded[1].clone(), recorded[0].clone()]);
+        assert_eq!(replay.chat_many(vec![user("short")]).await.unwrap(), [recorded[0].text.clone()]);
+
+        let err = replay.chat_many_scored(vec![user("a longer prompt")]).await.unwrap_err();
+        assert!(err.to_string().contains("no recorded response"), "{err}");
+        assert!(err.to_string().contains("a longer prompt"), "{err}");
+
+        // another model or other sampling settings do not match the recording
+        let other_model = ReplayLlm::from_file(&path, "other", SamplingParams::default()).unwrap();
+        assert!(other_model.chat_many(vec![user("short")]).await.is_err());
+        let hot = SamplingParams { temperature: Some(0.7), ..Default::default() };
+        let other_sampling = ReplayLlm::from_file(&path, "m", hot).unwrap();
+        assert!(other_sampling.chat_many(vec![user("short")]).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
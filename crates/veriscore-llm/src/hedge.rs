@@ -0,0 +1,188 @@
+use crate::error::LlmError;
+use crate::traits::{Completion, Llm};
+use anyhow::Result;
+use async_openai::types::ChatCompletionRequestMessage;
+use futures::future::try_join_all;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use veriscore_common::hedge::{HedgeConfig, HedgeStats, Hedger};
+
+/// Receives the completion of a hedged call's slower attempt.
+pub type LoserSink = Arc<dyn Fn(Completion) + Send + Sync>;
+
+/// An [`Llm`] whose slow prompts are hedged. Each prompt of a batch is sent
+/// and hedged on its own, so one slow prompt duplicates only itself and the
+/// extra-load cap counts prompts. The caller only sees the winning attempt's
+/// usage; with [`HedgedLlm::with_loser_sink`] the slower attempt runs to
+/// completion so its usage can be metered too, and otherwise it is cancelled
+/// (though the provider may still bill it).
+pub struct HedgedLlm {
+    inner: Arc<dyn Llm>,
+    hedger: Hedger,
+    loser_sink: Option<LoserSink>,
+}
+
+impl HedgedLlm {
+    pub fn new(inner: Arc<dyn Llm>, config: HedgeConfig) -> Self {
+        Self { inner, hedger: Hedger::new(config), loser_sink: None }
+    }
+
+    /// Lets the slower attempt of each hedged prompt finish in the background
+    /// and hands its completion to `sink`.
+    pub fn with_loser_sink(mut self, sink: LoserSink) -> Self {
+        self.loser_sink = Some(sink);
+        self
+    }
+
+    pub fn stats(&self) -> HedgeStats {
+        self.hedger.stats()
+    }
+
+    async fn hedge_each(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>, scored: bool) -> Result<Vec<Completion>> {
+        let calls: Vec<_> = prompts
+            .into_iter()
+            .map(|prompt| {
+                let call = move || {
+                    let inner = self.inner.clone();
+                    let prompt = prompt.clone();
+                    async move {
+                        let mut out = if scored {
+                            inner.chat_many_scored(vec![prompt]).await?
+                        } else {
+                            inner.chat_many_with_usage(vec![prompt]).await?
+                        };
+                        Ok(out.pop().ok_or_else(|| LlmError::BadResponse("empty LLM response batch".into()))?)
+                    }
+                };
+                self.hedger.run_with_loser(call, |loser| self.finish_loser(loser))
+            })
+            .collect();
+        try_join_all(calls).await
+    }
+
+    fn finish_loser(&self, loser: Pin<Box<impl Future<Output = Result<Completion>> + Send + 'static>>) {
+        if let Some(sink) = self.loser_sink.clone() {
+            tokio::spawn(async move {
+                if let Ok(completion) = loser.await {
+                    sink(completion);
+                }
+            });
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Llm for HedgedLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        Ok(self.hedge_each(prompts, false).await?.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.hedge_each(prompts, false).await
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.hedge_each(prompts, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::TokenUsage;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tokio::time::{Duration, Instant};
+    use veriscore_common::hedge::HedgeDelay;
+
+    /// Answers after `delays[n]` on its n-th call, or fails when that delay is `None`.
+    struct Scripted {
+        delays: Vec<Option<Duration>>,
+        calls: AtomicUsize,
+    }
+
+    impl Scripted {
+        fn new(delays: &[Option<u64>]) -> Arc<Self> {
+            Arc::new(Self { delays: delays.iter().map(|d| d.map(Duration::from_millis)).collect(), calls: AtomicUsize::new(0) })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Llm for Scripted {
+        async fn chat_many(&self, _prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.delays.get(n).copied().flatten() {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    Ok(vec![format!("call {n}")])
+                }
+                None => Err(LlmError::Unavailable("down".into()).into()),
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_call_is_hedged_and_the_faster_answer_wins() {
+        let inner = Scripted::new(&[Some(1000), Some(10)]);
+        let config = HedgeConfig { delay: HedgeDelay::Fixed(Duration::from_millis(100)), max_extra_load: 1.0, max_in_flight: 8 };
+        let llm = HedgedLlm::new(inner, config);
+        let started = Instant::now();
+        assert_eq!(llm.chat_one(vec![]).await.unwrap(), "call 1");
+        assert_eq!(started.elapsed(), Duration::from_millis(110));
+        let stats = llm.stats();
+        assert_eq!((stats.calls, stats.hedged, stats.hedge_wins), (1, 1, 1));
+    }
+
+    fn user(text: &str) -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content(text).build().unwrap().into()]
+    }
+
+    /// The first attempt at a prompt containing "slow" takes a second; every
+    /// other attempt takes 10ms.
+    #[derive(Default)]
+    struct SlowPrompt {
+        attempts: AtomicUsize,
+        slow_seen: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl Llm for SlowPrompt {
+        async fn chat_many(&self, _prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+            unreachable!("hedging asks for usage")
+        }
+
+        async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+            assert_eq!(prompts.len(), 1, "each prompt is sent on its own");
+            let n = self.attempts.fetch_add(1, Ordering::SeqCst);
+            let slow = serde_json::to_string(&prompts[0]).unwrap().contains("slow") && !self.slow_seen.swap(true, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(if slow { 1000 } else { 10 })).await;
+            let usage = TokenUsage { prompt_tokens: 1, completion_tokens: 1 };
+            Ok(vec![Completion { text: format!("attempt {n}"), usage: Some(usage), ..Default::default() }])
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_the_slow_prompt_of_a_batch_is_hedged_and_the_loser_is_metered() {
+        let inner = Arc::new(SlowPrompt::default());
+        let config = HedgeConfig { delay: HedgeDelay::Fixed(Duration::from_millis(100)), max_extra_load: 1.0, max_in_flight: 8 };
+        let losers = Arc::new(Mutex::new(Vec::new()));
+        let sink = losers.clone();
+        let llm = HedgedLlm::new(inner.clone(), config)
+            .with_loser_sink(Arc::new(move |c: Completion| sink.lock().unwrap().push(c.text)));
+
+        let started = Instant::now();
+        let out = llm.chat_many_with_usage(vec![user("fast"), user("slow"), user("fast")]).await.unwrap();
+        assert_eq!(started.elapsed(), Duration::from_millis(110));
+        assert_eq!(out.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(), ["attempt 0", "attempt 3", "attempt 2"]);
+        let stats = llm.stats();
+        assert_eq!((stats.calls, stats.hedged, stats.hedge_wins), (3, 1, 1));
+        assert_eq!(inner.attempts.load(Ordering::SeqCst), 4);
+
+        // the slow first attempt still finishes and reports its usage
+        assert!(losers.lock().unwrap().is_empty());
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(*losers.lock().unwrap(), ["attempt 1"]);
+    }
+}
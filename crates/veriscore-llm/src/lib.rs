@@ -1,8 +1,34 @@
+pub mod anthropic;
+pub mod array_batch;
 pub mod batcher;
 pub mod cache;
+pub mod error;
+pub mod fixtures;
+pub mod hedge;
+pub mod nli;
+#[cfg(feature = "candle")]
+pub mod nli_candle;
 pub mod openai;
+pub mod openai_batch;
+pub mod routing;
+pub mod sampling;
 pub mod traits;
+pub mod usage;
 
+pub use anthropic::AnthropicLlm;
+pub use array_batch::ArrayBatchLlm;
 pub use batcher::{BatchedLlm, MicroBatchConfig};
+pub use error::LlmError;
+pub use fixtures::{RecordingLlm, ReplayLlm};
+pub use hedge::{HedgedLlm, LoserSink};
+pub use nli::{NliModel, NliScores, TeiNliModel};
+#[cfg(feature = "candle")]
+pub use nli_candle::CandleNliModel;
 pub use openai::OpenAiCompatibleLlm;
-pub use traits::Llm;
+pub use openai_batch::{BatchStore, OpenAiBatchLlm};
+pub use routing::{CircuitConfig, EndpointStats, RoutingLlm, RoutingPolicy};
+pub use sampling::SamplingParams;
+pub use traits::{Completion, Llm};
+pub use usage::{ApproxTokenCounter, ModelPrice, TokenCounter, TokenUsage};
+#[cfg(feature = "candle")]
+pub use usage::TokenizerCounter;
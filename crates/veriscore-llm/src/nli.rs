@@ -0,0 +1,183 @@
+use crate::error::LlmError;
+use anyhow::Result;
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+
+/// Class probabilities from an NLI cross-encoder for one (premise, hypothesis) pair.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct NliScores {
+    pub entailment: f32,
+    pub neutral: f32,
+    pub contradiction: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NliClass {
+    Entailment,
+    Neutral,
+    Contradiction,
+}
+
+impl NliScores {
+    pub fn class(&self) -> NliClass {
+        if self.entailment >= self.neutral && self.entailment >= self.contradiction {
+            NliClass::Entailment
+        } else if self.contradiction >= self.neutral {
+            NliClass::Contradiction
+        } else {
+            NliClass::Neutral
+        }
+    }
+
+    /// Fills the score for a model label such as `ENTAILMENT` or `neutral`;
+    /// labels that are not NLI classes are ignored.
+    pub(crate) fn set(&mut self, label: &str, score: f32) {
+        match label.to_ascii_lowercase().as_str() {
+            "entailment" | "entails" => self.entailment = score,
+            "neutral" => self.neutral = score,
+            "contradiction" | "contradicts" => self.contradiction = score,
+            _ => {}
+        }
+    }
+}
+
+/// A sentence-pair entailment classifier, e.g. a DeBERTa or RoBERTa MNLI
+/// cross-encoder, run in-process (`CandleNliModel`) or behind a server.
+#[async_trait::async_trait]
+pub trait NliModel: Send + Sync {
+    /// Scores each `(premise, hypothesis)` pair, in order.
+    async fn classify(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>>;
+}
+
+/// Cross-encoder served by a text-embeddings-inference (`/predict`) server,
+/// for deployments that already run one (e.g. on a GPU host).
+#[derive(Clone)]
+pub struct TeiNliModel {
+    http: reqwest::Client,
+    base_url: String,
+    max_batch: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct PredictRequest<'a> {
+    inputs: Vec<[&'a str; 2]>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Prediction {
+    label: String,
+    score: f32,
+}
+
+impl TeiNliModel {
+    /// `max_batch` should not exceed the server's `--max-client-batch-size`.
+    pub fn new(base_url: impl Into<String>, max_batch: usize) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            max_batch: max_batch.max(1),
+        }
+    }
+
+    pub async fn health(&self) -> Result<()> {
+        let response = self
+            .http
+            .get(format!("{}/health", self.base_url))
+            .send()
+            .await
+            .map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(LlmError::Unavailable(format!("NLI server health check returned {}", response.status())).into());
+        }
+        Ok(())
+    }
+
+    async fn predict(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>> {
+        let request = PredictRequest { inputs: pairs.iter().map(|(p, h)| [p.as_str(), h.as_str()]).collect() };
+        let response = self
+            .http
+            .post(format!("{}/predict", self.base_url))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| LlmError::Unavailable(e.to_string()))?;
+        if status.as_u16() == 429 {
+            return Err(LlmError::RateLimited(body).into());
+        }
+        if !status.is_success() {
+            return Err(LlmError::from_status(status.as_u16(), format!("{status}: {body}")).into());
+        }
+        let predictions: Vec<Vec<Prediction>> =
+            serde_json::from_str(&body).map_err(|e| LlmError::BadResponse(format!("NLI predictions: {e}")))?;
+        if predictions.len() != pairs.len() {
+            return Err(LlmError::BadResponse(format!("{} predictions for {} pairs", predictions.len(), pairs.len())).into());
+        }
+        Ok(predictions
+            .into_iter()
+            .map(|labels| {
+                let mut scores = NliScores::default();
+                for p in labels {
+                    scores.set(&p.label, p.score);
+                }
+                scores
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl NliModel for TeiNliModel {
+    async fn classify(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>> {
+        let batches = try_join_all(pairs.chunks(self.max_batch).map(|chunk| self.predict(chunk))).await?;
+        Ok(batches.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+
+    /// Mimics TEI `/predict`: premises containing "Paris" entail the hypothesis.
+    async fn mock_server() -> String {
+        async fn predict(Json(body): Json<serde_json::Value>) -> Json<serde_json::Value> {
+            let out = body["inputs"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|pair| {
+                    let (e, c) = if pair[0].as_str().unwrap().contains("Paris") { (0.9, 0.05) } else { (0.1, 0.8) };
+                    serde_json::json!([
+                        {"label": "ENTAILMENT", "score": e},
+                        {"label": "CONTRADICTION", "score": c},
+                        {"label": "NEUTRAL", "score": 1.0 - e - c},
+                    ])
+                })
+                .collect::<Vec<_>>();
+            Json(serde_json::Value::Array(out))
+        }
+
+        let app = Router::new().route("/predict", post(predict));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn tei_model_maps_labels_and_keeps_order_across_batches() {
+        let model = TeiNliModel::new(mock_server().await, 2);
+        let pairs = ["Paris is in France.", "Lyon is big.", "Paris again.", "Nothing."]
+            .iter()
+            .map(|p| (p.to_string(), "The capital of France is Paris.".to_string()))
+            .collect::<Vec<_>>();
+
+        let scores = model.classify(&pairs).await.unwrap();
+
+        let classes = scores.iter().map(NliScores::class).collect::<Vec<_>>();
+        assert_eq!(classes, [NliClass::Entailment, NliClass::Contradiction, NliClass::Entailment, NliClass::Contradiction]);
+        assert!((scores[0].entailment - 0.9).abs() < 1e-6);
+    }
+}
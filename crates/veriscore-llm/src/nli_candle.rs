@@ -0,0 +1,262 @@
+use crate::nli::{NliModel, NliScores};
+use anyhow::{anyhow, bail, Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::debertav2::{self, DebertaV2SeqClassificationModel};
+use candle_transformers::models::xlm_roberta::{self, XLMRobertaForSequenceClassification};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams, TruncationStrategy};
+
+/// Cross-encoders see at most this many tokens per pair.
+const MAX_TOKENS: usize = 512;
+
+/// The parts of `config.json` needed besides the architecture's own config.
+#[derive(Debug, Deserialize)]
+struct CheckpointConfig {
+    model_type: String,
+    id2label: HashMap<String, String>,
+    max_position_embeddings: usize,
+    #[serde(default)]
+    type_vocab_size: usize,
+    #[serde(default)]
+    pad_token_id: Option<u32>,
+}
+
+enum Classifier {
+    Deberta(Box<DebertaV2SeqClassificationModel>),
+    Roberta(Box<XLMRobertaForSequenceClassification>),
+}
+
+impl Classifier {
+    fn forward(&self, input_ids: &Tensor, type_ids: &Tensor, attention_mask: &Tensor) -> candle_core::Result<Tensor> {
+        match self {
+            Classifier::Deberta(model) => model.forward(input_ids, Some(type_ids.clone()), Some(attention_mask.clone())),
+            Classifier::Roberta(model) => model.forward(input_ids, attention_mask, type_ids),
+        }
+    }
+}
+
+/// NLI cross-encoder run in-process on candle (CPU), so verification needs no
+/// separate model server. Loads a Hugging Face sequence-classification
+/// checkpoint directory with `config.json`, `tokenizer.json` and
+/// `model.safetensors`: DeBERTa-v2/v3 (e.g. `cross-encoder/nli-deberta-v3-base`)
+/// or (XLM-)RoBERTa MNLI models. `id2label` must name the three NLI classes.
+#[derive(Clone)]
+pub struct CandleNliModel {
+    model: Arc<Classifier>,
+    tokenizer: Arc<Tokenizer>,
+    /// NLI label of each logit.
+    labels: Arc<Vec<String>>,
+    /// Models without segment embeddings get all-zero token types.
+    zero_type_ids: bool,
+    device: Device,
+    max_batch: usize,
+}
+
+impl CandleNliModel {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let read = |name: &str| std::fs::read(dir.join(name)).with_context(|| format!("failed to read {}", dir.join(name).display()));
+        let raw_config = read("config.json")?;
+        let checkpoint: CheckpointConfig = serde_json::from_slice(&raw_config).context("invalid config.json")?;
+        let labels = nli_labels(&checkpoint.id2label)?;
+
+        let device = Device::Cpu;
+        let vb = VarBuilder::from_buffered_safetensors(read("model.safetensors")?, DType::F32, &device)?;
+        let model = match checkpoint.model_type.as_str() {
+            "deberta-v2" => {
+                let config: debertav2::Config = serde_json::from_slice(&raw_config).context("invalid DeBERTa config.json")?;
+                Classifier::Deberta(Box::new(DebertaV2SeqClassificationModel::load(vb.pp("deberta"), &config, None)?))
+            }
+            "roberta" | "xlm-roberta" => {
+                let config: xlm_roberta::Config = serde_json::from_slice(&raw_config).context("invalid RoBERTa config.json")?;
+                Classifier::Roberta(Box::new(XLMRobertaForSequenceClassification::new(labels.len(), &config, vb)?))
+            }
+            other => bail!("unsupported NLI model type {other:?} (expected deberta-v2, roberta or xlm-roberta)"),
+        };
+
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json")).map_err(|e| anyhow!("invalid tokenizer.json: {e}"))?;
+        let pad_id = checkpoint.pad_token_id.unwrap_or(0);
+        let pad_token = tokenizer.id_to_token(pad_id).with_context(|| format!("pad token {pad_id} is not in the vocabulary"))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: checkpoint.max_position_embeddings.min(MAX_TOKENS),
+                strategy: TruncationStrategy::LongestFirst,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("{e}"))?
+            .with_padding(Some(PaddingParams { strategy: PaddingStrategy::BatchLongest, pad_id, pad_token, ..Default::default() }));
+
+        Ok(Self {
+            model: Arc::new(model),
+            tokenizer: Arc::new(tokenizer),
+            labels: Arc::new(labels),
+            zero_type_ids: checkpoint.type_vocab_size <= 1,
+            device,
+            max_batch: 16,
+        })
+    }
+
+    /// Pairs per forward pass; longer batches pad to their longest pair.
+    pub fn with_max_batch(mut self, max_batch: usize) -> Self {
+        self.max_batch = max_batch.max(1);
+        self
+    }
+
+    fn forward(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs.iter().map(|(p, h)| (p.as_str(), h.as_str())).collect::<Vec<_>>(), true)
+            .map_err(|e| anyhow!("failed to tokenize NLI pairs: {e}"))?;
+        let shape = (encodings.len(), encodings.first().map_or(0, |e| e.len()));
+        let column = |values: Vec<u32>| Tensor::from_vec(values, shape, &self.device);
+        let input_ids = column(encodings.iter().flat_map(|e| e.get_ids().to_vec()).collect())?;
+        let attention_mask = column(encodings.iter().flat_map(|e| e.get_attention_mask().to_vec()).collect())?;
+        let type_ids = if self.zero_type_ids {
+            Tensor::zeros(shape, DType::U32, &self.device)?
+        } else {
+            column(encodings.iter().flat_map(|e| e.get_type_ids().to_vec()).collect())?
+        };
+
+        let logits = self.model.forward(&input_ids, &type_ids, &attention_mask)?;
+        let probs = candle_nn::ops::softmax_last_dim(&logits)?.to_vec2::<f32>()?;
+        Ok(probs
+            .into_iter()
+            .map(|row| {
+                let mut scores = NliScores::default();
+                for (label, p) in self.labels.iter().zip(row) {
+                    scores.set(label, p);
+                }
+                scores
+            })
+            .collect())
+    }
+}
+
+/// Labels by logit index; all three NLI classes must be present.
+fn nli_labels(id2label: &HashMap<String, String>) -> Result<Vec<String>> {
+    let mut labels = vec![String::new(); id2label.len()];
+    for (id, label) in id2label {
+        let slot = id.parse::<usize>().ok().and_then(|i| labels.get_mut(i)).with_context(|| format!("bad id2label index {id:?}"))?;
+        *slot = label.clone();
+    }
+    let mut probe = NliScores::default();
+    for label in &labels {
+        probe.set(label, 1.0);
+    }
+    if probe != (NliScores { entailment: 1.0, neutral: 1.0, contradiction: 1.0 }) {
+        bail!("id2label {labels:?} does not name entailment, neutral and contradiction");
+    }
+    Ok(labels)
+}
+
+#[async_trait::async_trait]
+impl NliModel for CandleNliModel {
+    async fn classify(&self, pairs: &[(String, String)]) -> Result<Vec<NliScores>> {
+        // inference is CPU-bound, so keep it off the async workers
+        let this = self.clone();
+        let pairs = pairs.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let mut out = Vec::with_capacity(pairs.len());
+            for chunk in pairs.chunks(this.max_batch) {
+                out.extend(this.forward(chunk)?);
+            }
+            Ok(out)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+    use tokenizers::models::wordlevel::WordLevel;
+    use tokenizers::pre_tokenizers::whitespace::Whitespace;
+    use tokenizers::processors::template::TemplateProcessing;
+
+    const WORDS: &[&str] = &["[PAD]", "[CLS]", "[SEP]", "[UNK]", "paris", "is", "the", "capital", "of", "france", "lyon", "big"];
+
+    /// Writes a randomly initialised two-layer DeBERTa-v3-style checkpoint.
+    fn tiny_checkpoint(dir: &Path, id2label: serde_json::Value) {
+        let config = serde_json::json!({
+            "model_type": "deberta-v2",
+            "vocab_size": WORDS.len(),
+            "hidden_size": 16,
+            "num_hidden_layers": 2,
+            "num_attention_heads": 2,
+            "intermediate_size": 32,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.1,
+            "attention_probs_dropout_prob": 0.1,
+            "max_position_embeddings": 64,
+            "type_vocab_size": 0,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-7,
+            "relative_attention": true,
+            "max_relative_positions": -1,
+            "position_buckets": 16,
+            "norm_rel_ebd": "layer_norm",
+            "share_att_key": true,
+            "pad_token_id": 0,
+            "position_biased_input": false,
+            "pos_att_type": ["p2c", "c2p"],
+            "pooler_dropout": 0,
+            "pooler_hidden_act": "gelu",
+            "pooler_hidden_size": 16,
+            "id2label": id2label,
+        });
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let config: debertav2::Config = serde_json::from_value(config).unwrap();
+        DebertaV2SeqClassificationModel::load(vb.pp("deberta"), &config, None).unwrap();
+        varmap.save(dir.join("model.safetensors")).unwrap();
+
+        let vocab = WORDS.iter().enumerate().map(|(i, w)| (w.to_string(), i as u32)).collect();
+        let mut tokenizer = Tokenizer::new(WordLevel::builder().vocab(vocab).unk_token("[UNK]".into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(Whitespace {})).with_post_processor(Some(
+            TemplateProcessing::builder()
+                .try_single("[CLS] $A [SEP]")
+                .unwrap()
+                .try_pair("[CLS] $A [SEP] $B:1 [SEP]:1")
+                .unwrap()
+                .special_tokens(vec![("[CLS]", 1), ("[SEP]", 2)])
+                .build()
+                .unwrap(),
+        ));
+        tokenizer.save(dir.join("tokenizer.json"), false).unwrap();
+    }
+
+    #[tokio::test]
+    async fn scores_pairs_in_process_and_keeps_order_across_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        tiny_checkpoint(dir.path(), serde_json::json!({"0": "contradiction", "1": "entailment", "2": "neutral"}));
+        let pairs = ["paris is the capital of france", "lyon is big", "paris", "the capital of france is big lyon"]
+            .iter()
+            .map(|p| (p.to_string(), "paris is the capital of france".to_string()))
+            .collect::<Vec<_>>();
+
+        let batched = CandleNliModel::load(dir.path()).unwrap().with_max_batch(3).classify(&pairs).await.unwrap();
+        let single = CandleNliModel::load(dir.path()).unwrap().with_max_batch(1).classify(&pairs).await.unwrap();
+
+        assert_eq!(batched.len(), pairs.len());
+        for (b, s) in batched.iter().zip(&single) {
+            assert!((b.entailment + b.neutral + b.contradiction - 1.0).abs() < 1e-4, "{b:?}");
+            // padding within a batch must not change a pair's scores
+            assert!((b.entailment - s.entailment).abs() < 1e-4 && (b.contradiction - s.contradiction).abs() < 1e-4, "{b:?} vs {s:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_checkpoints_without_nli_labels() {
+        let dir = tempfile::tempdir().unwrap();
+        tiny_checkpoint(dir.path(), serde_json::json!({"0": "LABEL_0", "1": "LABEL_1", "2": "LABEL_2"}));
+        let err = CandleNliModel::load(dir.path()).err().unwrap();
+        assert!(err.to_string().contains("does not name entailment"), "{err}");
+    }
+}
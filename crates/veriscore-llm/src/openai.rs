@@ -1,18 +1,29 @@
 use crate::cache::LlmCache;
-use crate::traits::Llm;
+use crate::error::LlmError;
+use crate::sampling::SamplingParams;
+use crate::traits::{Completion, Llm};
+use crate::usage::{ModelPrice, TokenCounter, TokenUsage};
 use anyhow::{Context, Result};
 use async_openai::config::OpenAIConfig;
-use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequestArgs};
+use async_openai::types::{ChatCompletionRequestMessage, CreateChatCompletionRequest, CreateChatCompletionRequestArgs, Stop};
 use async_openai::Client;
 use futures::{stream, StreamExt};
 use std::sync::Arc;
 
+pub use async_openai::config::OPENAI_API_BASE;
+
+/// Alternatives requested per token when asking for logprobs.
+const TOP_LOGPROBS: u8 = 5;
+
 #[derive(Clone)]
 pub struct OpenAiCompatibleLlm {
     client: Client<OpenAIConfig>,
     model: String,
     max_concurrency: usize,
+    sampling: SamplingParams,
     cache: Option<Arc<LlmCache>>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    price: Option<ModelPrice>,
 }
 
 impl OpenAiCompatibleLlm {
@@ -34,40 +45,84 @@ impl OpenAiCompatibleLlm {
             client: Client::with_config(cfg),
             model: model.into(),
             max_concurrency,
+            sampling: SamplingParams::default(),
             cache,
+            token_counter: None,
+            price: None,
         }
     }
+
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Counts tokens locally when the server's responses carry no `usage`.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
+    /// Prices the reported (or counted) usage of every call.
+    pub fn with_price(mut self, price: ModelPrice) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Ids of the models served by the backend; cheap enough for health probes.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let models = self.client.models().list().await.map_err(LlmError::from)?;
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
 }
 
-#[async_trait::async_trait]
-impl Llm for OpenAiCompatibleLlm {
-    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+impl OpenAiCompatibleLlm {
+    /// Runs the prompts with bounded concurrency, in order. With `logprobs`
+    /// the request asks for the top alternatives per token, and the cache
+    /// stores the whole `Completion` under a separate key.
+    async fn complete(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>, logprobs: bool) -> Result<Vec<Completion>> {
         let requests = prompts.into_iter().enumerate().map(|(idx, messages)| {
             let client = self.client.clone();
             let model = self.model.clone();
             let cache = self.cache.clone();
+            let sampling = &self.sampling;
+            let counter = self.token_counter.clone();
+            let price = self.price;
             async move {
-                let prompt_json = serde_json::to_string(&messages)?;
-                let cache_key = cache.as_ref().map(|_| LlmCache::make_key(&model, &prompt_json));
+                let cache_key = match &cache {
+                    Some(_) => Some(LlmCache::make_key(&model, &cache_input(sampling, &messages, logprobs)?)),
+                    None => None,
+                };
                 if let (Some(cache), Some(key)) = (cache.as_ref(), cache_key.as_deref()) {
                     if let Some(hit) = cache.get(key)? {
-                        return Ok::<_, anyhow::Error>((idx, hit));
+                        return Ok::<_, anyhow::Error>((idx, cached_completion(hit, logprobs)?));
                     }
                 }
 
-                let req = CreateChatCompletionRequestArgs::default()
-                    .model(model.clone())
-                    .messages(messages)
-                    .build()
-                    .context("failed to build chat completion request")?;
-                let resp = client.chat().create(req).await?;
-                let text = resp.choices.first()
-                    .and_then(|c| c.message.content.clone())
-                    .unwrap_or_default();
+                let req = chat_request(&model, messages.clone(), sampling, logprobs)?;
+                let resp = client.chat().create(req).await.map_err(LlmError::from)?;
+                let choice = resp.choices.into_iter().next();
+                let text = choice.as_ref().and_then(|c| c.message.content.clone()).unwrap_or_default();
+                let usage = match (resp.usage, counter) {
+                    (Some(u), _) => Some(TokenUsage { prompt_tokens: u.prompt_tokens.into(), completion_tokens: u.completion_tokens.into() }),
+                    (None, Some(counter)) => Some(counter.usage(&messages, &text)),
+                    (None, None) => None,
+                };
+                let completion = Completion {
+                    text,
+                    logprobs: choice.and_then(|c| c.logprobs).and_then(|l| l.content),
+                    usage,
+                    cost_usd: price.zip(usage).map(|(p, u)| p.cost(&u)),
+                    cached: false,
+                };
                 if let (Some(cache), Some(key)) = (cache.as_ref(), cache_key.as_deref()) {
-                    cache.put(key, &text)?;
+                    cache.put(key, &cache_entry(&completion, logprobs)?)?;
                 }
-                Ok::<_, anyhow::Error>((idx, text))
+                Ok::<_, anyhow::Error>((idx, completion))
             }
         });
 
@@ -79,9 +134,74 @@ impl Llm for OpenAiCompatibleLlm {
         results.sort_by_key(|item| item.as_ref().map(|(idx, _)| *idx).unwrap_or(usize::MAX));
         let mut out = Vec::with_capacity(results.len());
         for result in results {
-            let (_, text) = result?;
-            out.push(text);
+            let (_, completion) = result?;
+            out.push(completion);
         }
         Ok(out)
     }
 }
+
+/// What a prompt is cached under; logprob requests are cached apart from
+/// plain ones since their entries hold the whole `Completion`.
+pub(crate) fn cache_input(sampling: &SamplingParams, messages: &[ChatCompletionRequestMessage], logprobs: bool) -> Result<String> {
+    let prompt_json = sampling.cache_input(messages)?;
+    Ok(if logprobs { serde_json::to_string(&(prompt_json, "logprobs"))? } else { prompt_json })
+}
+
+pub(crate) fn cached_completion(hit: String, logprobs: bool) -> Result<Completion> {
+    Ok(if logprobs {
+        Completion { usage: None, cost_usd: None, cached: true, ..serde_json::from_str(&hit)? }
+    } else {
+        Completion { cached: true, ..Completion::text(hit) }
+    })
+}
+
+pub(crate) fn cache_entry(completion: &Completion, logprobs: bool) -> Result<String> {
+    Ok(if logprobs { serde_json::to_string(completion)? } else { completion.text.clone() })
+}
+
+/// A chat completions request body for one prompt.
+pub(crate) fn chat_request(
+    model: &str,
+    messages: Vec<ChatCompletionRequestMessage>,
+    sampling: &SamplingParams,
+    logprobs: bool,
+) -> Result<CreateChatCompletionRequest> {
+    let mut args = CreateChatCompletionRequestArgs::default();
+    args.model(model).messages(messages);
+    if let Some(temperature) = sampling.temperature {
+        args.temperature(temperature);
+    }
+    if let Some(top_p) = sampling.top_p {
+        args.top_p(top_p);
+    }
+    if let Some(max_tokens) = sampling.max_tokens {
+        args.max_tokens(max_tokens);
+    }
+    if !sampling.stop.is_empty() {
+        args.stop(Stop::StringArray(sampling.stop.clone()));
+    }
+    if let Some(seed) = sampling.seed {
+        args.seed(seed);
+    }
+    if logprobs {
+        args.logprobs(true).top_logprobs(TOP_LOGPROBS);
+    }
+    args.build().map_err(LlmError::from).context("failed to build chat completion request")
+}
+
+#[async_trait::async_trait]
+impl Llm for OpenAiCompatibleLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let out = self.complete(prompts, false).await?;
+        Ok(out.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, false).await
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, true).await
+    }
+}
@@ -0,0 +1,665 @@
+use crate::array_batch::{classify_status, BatchItem};
+use crate::cache::LlmCache;
+use crate::error::LlmError;
+use crate::openai::{cache_entry, cache_input, cached_completion, chat_request};
+use crate::sampling::SamplingParams;
+use crate::traits::{Completion, Llm};
+use crate::usage::{ModelPrice, TokenCounter};
+use anyhow::Result;
+use async_openai::config::OpenAIConfig;
+use async_openai::types::{
+    BatchCompletionWindow, BatchEndpoint, BatchRequest, BatchStatus, ChatCompletionRequestMessage, CreateFileRequest,
+    FileInput, FilePurpose,
+};
+use async_openai::Client;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// A failing poll waits twice as long as the last one, up to 16 poll intervals.
+const MAX_POLL_BACKOFF_DOUBLINGS: u32 = 4;
+/// Consecutive failed polls before a call gives up on its batch; about two
+/// and a half hours at the default 30s interval.
+const DEFAULT_MAX_FAILED_POLLS: u32 = 20;
+
+/// Which submitted batch each pending prompt went into, so a restarted run
+/// resumes polling that batch instead of paying for the prompt again.
+#[derive(Clone)]
+pub struct BatchStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl BatchStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS openai_batch_requests (
+                request_key TEXT PRIMARY KEY,
+                batch_id TEXT NOT NULL,
+                submitted_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            "#,
+        )?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    pub fn batch_of(&self, request_key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().expect("batch store poisoned");
+        let batch_id = conn
+            .query_row("SELECT batch_id FROM openai_batch_requests WHERE request_key = ?1", params![request_key], |row| row.get(0))
+            .optional()?;
+        Ok(batch_id)
+    }
+
+    fn submitted(&self, batch_id: &str, request_keys: &[String]) -> Result<()> {
+        let mut conn = self.conn.lock().expect("batch store poisoned");
+        let tx = conn.transaction()?;
+        for key in request_keys {
+            tx.execute(
+                "INSERT OR REPLACE INTO openai_batch_requests(request_key, batch_id) VALUES(?1, ?2)",
+                params![key, batch_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn forget(&self, request_keys: &[String]) -> Result<()> {
+        let mut conn = self.conn.lock().expect("batch store poisoned");
+        let tx = conn.transaction()?;
+        for key in request_keys {
+            tx.execute("DELETE FROM openai_batch_requests WHERE request_key = ?1", params![key])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Batches that still have prompts waiting on them.
+    pub fn pending_batches(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().expect("batch store poisoned");
+        let mut stmt = conn.prepare("SELECT DISTINCT batch_id FROM openai_batch_requests ORDER BY batch_id")?;
+        let ids = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+}
+
+/// One line of a batch output or error file.
+#[derive(Debug, Deserialize)]
+struct OutputLine {
+    custom_id: String,
+    #[serde(default)]
+    response: Option<OutputResponse>,
+    #[serde(default)]
+    error: Option<OutputError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutputResponse {
+    status_code: u16,
+    body: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutputError {
+    #[serde(default)]
+    code: Option<String>,
+    message: String,
+}
+
+/// OpenAI Batch API backend for offline runs, where waiting hours is fine
+/// and batch pricing (about half) is not. `chat_many` uploads its uncached
+/// prompts as a JSONL file, submits one batch, polls until it ends, then
+/// downloads the results and maps them back by `custom_id`.
+///
+/// Each prompt's batch id is kept in a [`BatchStore`] until its answer is
+/// delivered, so rerunning the same prompts after a restart picks up the
+/// earlier batch. Pair it with large micro-batches, since every `chat_many`
+/// call becomes its own batch.
+pub struct OpenAiBatchLlm {
+    client: Client<OpenAIConfig>,
+    model: String,
+    store: BatchStore,
+    poll_interval: Duration,
+    max_failed_polls: u32,
+    sampling: SamplingParams,
+    cache: Option<Arc<LlmCache>>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    price: Option<ModelPrice>,
+}
+
+impl OpenAiBatchLlm {
+    pub fn new(
+        model: impl Into<String>,
+        api_base: Option<String>,
+        api_key: Option<String>,
+        store: BatchStore,
+        cache: Option<Arc<LlmCache>>,
+    ) -> Self {
+        let mut cfg = OpenAIConfig::default();
+        if let Some(api_base) = api_base {
+            cfg = cfg.with_api_base(api_base);
+        }
+        if let Some(api_key) = api_key {
+            cfg = cfg.with_api_key(api_key);
+        }
+        Self {
+            client: Client::with_config(cfg),
+            model: model.into(),
+            store,
+            poll_interval: Duration::from_secs(30),
+            max_failed_polls: DEFAULT_MAX_FAILED_POLLS,
+            sampling: SamplingParams::default(),
+            cache,
+            token_counter: None,
+            price: None,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Consecutive transient poll failures after which a call fails with
+    /// [`LlmError::Unavailable`]; the batch stays recorded for the next run.
+    pub fn with_max_failed_polls(mut self, max_failed_polls: u32) -> Self {
+        self.max_failed_polls = max_failed_polls.max(1);
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Counts tokens locally when the results carry no `usage`.
+    pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+        self.token_counter = Some(counter);
+        self
+    }
+
+    /// Prices the reported (or counted) usage; give the model's batch price.
+    pub fn with_price(mut self, price: ModelPrice) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>, logprobs: bool) -> Result<Vec<Completion>> {
+        let mut out: Vec<Option<Completion>> = vec![None; prompts.len()];
+        // identical prompts share one batch request
+        let mut wanted: BTreeMap<String, (Vec<ChatCompletionRequestMessage>, Vec<usize>)> = BTreeMap::new();
+        for (idx, messages) in prompts.into_iter().enumerate() {
+            let key = LlmCache::make_key(&self.model, &cache_input(&self.sampling, &messages, logprobs)?);
+            if let Some(hit) = self.cache.as_ref().map(|cache| cache.get(&key)).transpose()?.flatten() {
+                out[idx] = Some(cached_completion(hit, logprobs)?);
+                continue;
+            }
+            wanted.entry(key).or_insert_with(|| (messages, Vec::new())).1.push(idx);
+        }
+        if wanted.is_empty() {
+            return Ok(out.into_iter().map(|c| c.expect("cache hit")).collect());
+        }
+
+        let mut batches: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut fresh = Vec::new();
+        for key in wanted.keys() {
+            match self.store.batch_of(key)? {
+                Some(batch_id) => batches.entry(batch_id).or_default().push(key.clone()),
+                None => fresh.push(key.clone()),
+            }
+        }
+        if !batches.is_empty() {
+            info!(batches = batches.len(), "resuming submitted OpenAI batches");
+        }
+        if !fresh.is_empty() {
+            let batch_id = self.submit(&fresh, &wanted, logprobs).await?;
+            batches.insert(batch_id, fresh);
+        }
+
+        let results = futures::future::join_all(batches.iter().map(|(batch_id, keys)| self.collect(batch_id, keys))).await;
+        // cache every answer of every batch that ended before failing on a bad
+        // one; a batch that could not be collected stays recorded for a retry
+        let mut first_err: Option<anyhow::Error> = None;
+        for (result, keys) in results.into_iter().zip(batches.values()) {
+            let mut items = match result {
+                Ok(items) => items,
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                    continue;
+                }
+            };
+            for key in keys {
+                let (messages, positions) = &wanted[key];
+                let item = items.remove(key).expect("collected for every key");
+                match item.and_then(|item| item.into_completion(messages, self.token_counter.as_deref(), self.price)) {
+                    Ok(completion) => {
+                        if let Some(cache) = &self.cache {
+                            cache.put(key, &cache_entry(&completion, logprobs)?)?;
+                        }
+                        for &idx in positions {
+                            out[idx] = Some(completion.clone());
+                        }
+                    }
+                    Err(err) => {
+                        first_err.get_or_insert(err.into());
+                    }
+                }
+            }
+            self.store.forget(keys)?;
+        }
+        if let Some(err) = first_err {
+            return Err(err);
+        }
+        Ok(out.into_iter().map(|c| c.expect("every prompt answered")).collect())
+    }
+
+    /// Uploads the prompts and starts a batch; `custom_id` is the prompt's cache key.
+    async fn submit(
+        &self,
+        keys: &[String],
+        wanted: &BTreeMap<String, (Vec<ChatCompletionRequestMessage>, Vec<usize>)>,
+        logprobs: bool,
+    ) -> Result<String> {
+        let mut jsonl = Vec::new();
+        for key in keys {
+            let body = chat_request(&self.model, wanted[key].0.clone(), &self.sampling, logprobs)?;
+            let line = serde_json::json!({"custom_id": key, "method": "POST", "url": "/v1/chat/completions", "body": body});
+            serde_json::to_writer(&mut jsonl, &line)?;
+            jsonl.push(b'\n');
+        }
+        let file = self
+            .client
+            .files()
+            .create(CreateFileRequest {
+                file: FileInput::from_vec_u8("veriscore-batch.jsonl".into(), jsonl),
+                purpose: FilePurpose::Batch,
+            })
+            .await
+            .map_err(LlmError::from)?;
+        let created = self
+            .client
+            .batches()
+            .create(BatchRequest {
+                input_file_id: file.id.clone(),
+                endpoint: BatchEndpoint::V1ChatCompletions,
+                completion_window: BatchCompletionWindow::W24H,
+                metadata: None,
+            })
+            .await;
+        let batch = match created {
+            Ok(batch) => batch,
+            Err(err) => {
+                // nothing will read the upload, so don't leave it in the account's storage
+                if let Err(e) = self.client.files().delete(&file.id).await {
+                    warn!(file_id = %file.id, error = %e, "failed to delete the input file of an OpenAI batch that was not created");
+                }
+                return Err(LlmError::from(err).into());
+            }
+        };
+        self.store.submitted(&batch.id, keys)?;
+        info!(batch_id = %batch.id, prompts = keys.len(), model = %self.model, "submitted OpenAI batch");
+        Ok(batch.id)
+    }
+
+    /// Polls the batch until it ends and returns the answer for each of `keys`.
+    /// A batch that fails, expires or is cancelled is forgotten, so the next
+    /// call resubmits its prompts.
+    async fn collect(&self, batch_id: &str, keys: &[String]) -> Result<HashMap<String, Result<BatchItem, LlmError>>> {
+        let mut failed_polls = 0;
+        let batch = loop {
+            let batch = match self.client.batches().retrieve(batch_id).await.map_err(LlmError::from) {
+                Ok(batch) => batch,
+                // the batch keeps running upstream, so outages only delay the next poll
+                Err(err) if err.is_retryable() => {
+                    failed_polls += 1;
+                    if failed_polls >= self.max_failed_polls {
+                        return Err(LlmError::Unavailable(format!(
+                            "gave up on OpenAI batch {batch_id} after {failed_polls} failed polls: {err}"
+                        ))
+                        .into());
+                    }
+                    let backoff = self.poll_interval * 2u32.pow(failed_polls.min(MAX_POLL_BACKOFF_DOUBLINGS));
+                    warn!(batch_id, error = %err, retry_in = ?backoff, "failed to poll OpenAI batch");
+                    tokio::time::sleep(backoff).await;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            failed_polls = 0;
+            match batch.status {
+                BatchStatus::Completed => break batch,
+                BatchStatus::Failed | BatchStatus::Expired | BatchStatus::Cancelling | BatchStatus::Cancelled => {
+                    self.store.forget(keys)?;
+                    let reasons: Vec<_> =
+                        batch.errors.iter().flat_map(|e| &e.data).map(|e| e.message.as_str()).collect();
+                    return Err(LlmError::Unavailable(format!(
+                        "OpenAI batch {batch_id} ended as {:?}: {}",
+                        batch.status,
+                        reasons.join("; ")
+                    ))
+                    .into());
+                }
+                BatchStatus::Validating | BatchStatus::InProgress | BatchStatus::Finalizing => {
+                    tokio::time::sleep(self.poll_interval).await;
+                }
+            }
+        };
+
+        let mut lines = Vec::new();
+        for file_id in batch.output_file_id.iter().chain(&batch.error_file_id) {
+            let content = self.client.files().content(file_id).await.map_err(LlmError::from)?;
+            for line in content.split(|b| *b == b'\n').filter(|l| !l.iter().all(u8::is_ascii_whitespace)) {
+                let line: OutputLine = serde_json::from_slice(line)
+                    .map_err(|e| LlmError::BadResponse(format!("invalid line in OpenAI batch {batch_id} output: {e}")))?;
+                lines.push(line);
+            }
+        }
+        let mut items: HashMap<String, Result<BatchItem, LlmError>> =
+            lines.into_iter().map(|line| (line.custom_id.clone(), output_item(line))).collect();
+        let mut out = HashMap::new();
+        for key in keys {
+            let item = items
+                .remove(key)
+                .unwrap_or_else(|| Err(LlmError::BadResponse(format!("OpenAI batch {batch_id} has no result for {key}"))));
+            out.insert(key.clone(), item);
+        }
+        Ok(out)
+    }
+}
+
+fn output_item(line: OutputLine) -> Result<BatchItem, LlmError> {
+    if let Some(error) = line.error {
+        let code = error.code.map(|c| format!("{c}: ")).unwrap_or_default();
+        return Err(LlmError::Unavailable(format!("{code}{}", error.message)));
+    }
+    let response = line.response.ok_or_else(|| LlmError::BadResponse("batch result has neither response nor error".into()))?;
+    let status = reqwest::StatusCode::from_u16(response.status_code)
+        .map_err(|e| LlmError::BadResponse(format!("invalid status in batch result: {e}")))?;
+    let item: BatchItem =
+        serde_json::from_value(response.body.clone()).map_err(|e| LlmError::BadResponse(format!("invalid batch result body: {e}")))?;
+    if !status.is_success() && !item.has_error() {
+        return Err(classify_status(status, &response.body.to_string()));
+    }
+    Ok(item)
+}
+
+#[async_trait::async_trait]
+impl Llm for OpenAiBatchLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let out = self.complete(prompts, false).await?;
+        Ok(out.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, false).await
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.complete(prompts, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::TokenUsage;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+    use axum::body::Bytes;
+    use axum::extract::{Path as UrlPath, State};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Stand-in for the Files and Batches APIs. A batch reports `in_progress`
+    /// on its first poll and then completes, echoing each prompt; prompts
+    /// containing "bad" land in the error file. Batches it never created are
+    /// not found.
+    #[derive(Default)]
+    struct MockBatches {
+        uploads: Mutex<Vec<Vec<serde_json::Value>>>,
+        batches: AtomicUsize,
+        polls: Mutex<HashMap<String, usize>>,
+        /// Polls still to answer with this status and OpenAI error type.
+        failing_polls: Mutex<Option<(usize, u16, &'static str)>>,
+        /// Refuses to create batches.
+        failing_creates: AtomicBool,
+        deleted_files: Mutex<Vec<String>>,
+    }
+
+    async fn mock_server() -> (String, Arc<MockBatches>) {
+        type Mock = State<Arc<MockBatches>>;
+
+        async fn upload(State(mock): Mock, body: Bytes) -> Json<serde_json::Value> {
+            // the multipart body holds the JSONL lines verbatim
+            let text = String::from_utf8_lossy(&body);
+            let lines: Vec<serde_json::Value> =
+                text.lines().filter(|l| l.starts_with('{') && l.contains("\"custom_id\"")).map(|l| serde_json::from_str(l).unwrap()).collect();
+            let mut uploads = mock.uploads.lock().unwrap();
+            uploads.push(lines);
+            Json(serde_json::json!({
+                "id": format!("file-{}", uploads.len() - 1), "object": "file", "bytes": body.len(),
+                "created_at": 0, "filename": "veriscore-batch.jsonl", "purpose": "batch",
+            }))
+        }
+
+        fn batch(id: &str, input: &str, status: &str) -> serde_json::Value {
+            let n = id.trim_start_matches("batch-");
+            serde_json::json!({
+                "id": id, "object": "batch", "endpoint": "/v1/chat/completions", "input_file_id": input,
+                "completion_window": "24h", "status": status, "created_at": 0,
+                "output_file_id": (status == "completed").then(|| format!("out-{n}")),
+                "error_file_id": (status == "completed").then(|| format!("err-{n}")),
+            })
+        }
+
+        async fn create(State(mock): Mock, Json(body): Json<serde_json::Value>) -> axum::response::Response {
+            use axum::response::IntoResponse;
+            if mock.failing_creates.load(Ordering::SeqCst) {
+                let body = serde_json::json!({"error": {"message": "too many tokens enqueued", "type": "invalid_request_error", "param": null, "code": null}});
+                return (axum::http::StatusCode::BAD_REQUEST, Json(body)).into_response();
+            }
+            let id = format!("batch-{}", mock.batches.fetch_add(1, Ordering::SeqCst));
+            Json(batch(&id, body["input_file_id"].as_str().unwrap(), "validating")).into_response()
+        }
+
+        async fn delete_file(State(mock): Mock, UrlPath(id): UrlPath<String>) -> Json<serde_json::Value> {
+            mock.deleted_files.lock().unwrap().push(id.clone());
+            Json(serde_json::json!({"id": id, "object": "file", "deleted": true}))
+        }
+
+        async fn retrieve(State(mock): Mock, UrlPath(id): UrlPath<String>) -> axum::response::Response {
+            use axum::response::IntoResponse;
+            if let Some((left, status, kind)) = mock.failing_polls.lock().unwrap().as_mut().filter(|(left, ..)| *left > 0) {
+                *left -= 1;
+                let body = serde_json::json!({"error": {"message": "poll failed", "type": kind, "param": null, "code": null}});
+                return (axum::http::StatusCode::from_u16(*status).unwrap(), Json(body)).into_response();
+            }
+            let n = id.trim_start_matches("batch-");
+            if n.parse::<usize>().map_or(true, |n| n >= mock.batches.load(Ordering::SeqCst)) {
+                let body = serde_json::json!({"error": {"message": "no such batch", "type": "invalid_request_error", "param": null, "code": "not_found"}});
+                return (axum::http::StatusCode::NOT_FOUND, Json(body)).into_response();
+            }
+            let mut polls = mock.polls.lock().unwrap();
+            let seen = polls.entry(id.clone()).or_default();
+            *seen += 1;
+            Json(batch(&id, &format!("file-{n}"), if *seen == 1 { "in_progress" } else { "completed" })).into_response()
+        }
+
+        async fn content(State(mock): Mock, UrlPath(id): UrlPath<String>) -> String {
+            let (kind, n) = id.split_once('-').unwrap();
+            let lines = mock.uploads.lock().unwrap()[n.parse::<usize>().unwrap()].clone();
+            let mut out = String::new();
+            for line in lines {
+                let prompt = line["body"]["messages"][0]["content"].as_str().unwrap().to_string();
+                let failed = prompt.contains("bad");
+                let result = if failed {
+                    serde_json::json!({"status_code": 400, "body": {"error": {"message": "prompt too long", "type": "invalid_request_error"}}})
+                } else {
+                    serde_json::json!({"status_code": 200, "body": {
+                        "choices": [{"index": 0, "message": {"role": "assistant", "content": format!("echo: {prompt}")}}],
+                        "usage": {"prompt_tokens": 8, "completion_tokens": 2, "total_tokens": 10},
+                    }})
+                };
+                if (kind == "err") == failed {
+                    let line = serde_json::json!({"id": "req", "custom_id": line["custom_id"], "response": result, "error": null});
+                    out.push_str(&format!("{line}\n"));
+                }
+            }
+            out
+        }
+
+        let mock = Arc::new(MockBatches::default());
+        let app = Router::new()
+            .route("/files", post(upload))
+            .route("/files/:id", axum::routing::delete(delete_file))
+            .route("/files/:id/content", get(content))
+            .route("/batches", post(create))
+            .route("/batches/:id", get(retrieve))
+            .with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), mock)
+    }
+
+    fn user(text: &str) -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content(text).build().unwrap().into()]
+    }
+
+    fn llm(base: &str, store: &BatchStore, cache: Option<Arc<LlmCache>>) -> OpenAiBatchLlm {
+        OpenAiBatchLlm::new("m", Some(base.to_string()), Some("test-key".into()), store.clone(), cache)
+            .with_poll_interval(Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn submits_one_batch_polls_and_maps_results_in_order() {
+        let (base, mock) = mock_server().await;
+        let store = BatchStore::open(":memory:").unwrap();
+        let cache = Arc::new(LlmCache::open(":memory:").unwrap());
+        let llm = llm(&base, &store, Some(cache));
+
+        let prompts = vec![user("b"), user("a"), user("b")];
+        let out = llm.chat_many_with_usage(prompts.clone()).await.unwrap();
+        assert_eq!(out.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(), ["echo: b", "echo: a", "echo: b"]);
+        assert_eq!(out[0].usage, Some(TokenUsage { prompt_tokens: 8, completion_tokens: 2 }));
+
+        let uploads = mock.uploads.lock().unwrap().clone();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].len(), 2, "duplicate prompts are sent once");
+        assert_eq!(uploads[0][0]["url"], "/v1/chat/completions");
+        assert_eq!(mock.polls.lock().unwrap()["batch-0"], 2);
+        assert!(store.pending_batches().unwrap().is_empty());
+
+        // answered from the cache the second time
+        assert_eq!(llm.chat_many(prompts).await.unwrap(), ["echo: b", "echo: a", "echo: b"]);
+        assert_eq!(mock.batches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn restarted_run_resumes_the_submitted_batch() {
+        let (base, mock) = mock_server().await;
+        let store = BatchStore::open(":memory:").unwrap();
+        let first = llm(&base, &store, None);
+        let prompts = vec![user("x"), user("y")];
+
+        // the run dies while the batch is still in progress
+        let run = tokio::spawn({
+            let prompts = prompts.clone();
+            async move { first.chat_many(prompts).await }
+        });
+        while mock.polls.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        run.abort();
+        assert_eq!(store.pending_batches().unwrap(), ["batch-0"]);
+
+        let restarted = llm(&base, &store, None);
+        assert_eq!(restarted.chat_many(prompts).await.unwrap(), ["echo: x", "echo: y"]);
+        assert_eq!(mock.batches.load(Ordering::SeqCst), 1, "nothing was resubmitted");
+        assert!(store.pending_batches().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_requests_surface_as_llm_errors() {
+        let (base, _) = mock_server().await;
+        let store = BatchStore::open(":memory:").unwrap();
+        let err = llm(&base, &store, None).chat_many(vec![user("ok"), user("bad")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Rejected(msg)) if msg.contains("prompt too long")));
+        assert!(store.pending_batches().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transient_poll_failures_are_retried() {
+        let (base, mock) = mock_server().await;
+        *mock.failing_polls.lock().unwrap() = Some((2, 503, "server_error"));
+        let store = BatchStore::open(":memory:").unwrap();
+
+        assert_eq!(llm(&base, &store, None).chat_many(vec![user("x")]).await.unwrap(), ["echo: x"]);
+        assert_eq!(mock.failing_polls.lock().unwrap().unwrap().0, 0);
+        assert_eq!(mock.batches.load(Ordering::SeqCst), 1);
+        assert!(store.pending_batches().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn permanent_poll_failures_propagate_and_keep_the_batch() {
+        let (base, mock) = mock_server().await;
+        *mock.failing_polls.lock().unwrap() = Some((usize::MAX, 401, "invalid_api_key"));
+        let store = BatchStore::open(":memory:").unwrap();
+
+        let err = llm(&base, &store, None).chat_many(vec![user("x")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Misconfigured(_))), "{err:#}");
+        // a restarted run with a working key resumes it
+        assert_eq!(store.pending_batches().unwrap(), ["batch-0"]);
+    }
+
+    #[tokio::test]
+    async fn polling_gives_up_after_too_many_transient_failures() {
+        let (base, mock) = mock_server().await;
+        *mock.failing_polls.lock().unwrap() = Some((usize::MAX, 503, "server_error"));
+        let store = BatchStore::open(":memory:").unwrap();
+
+        let err = llm(&base, &store, None).with_max_failed_polls(3).chat_many(vec![user("x")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Unavailable(msg)) if msg.contains("after 3 failed polls")), "{err:#}");
+        assert_eq!(store.pending_batches().unwrap(), ["batch-0"]);
+    }
+
+    #[tokio::test]
+    async fn input_file_is_deleted_when_the_batch_is_not_created() {
+        let (base, mock) = mock_server().await;
+        mock.failing_creates.store(true, Ordering::SeqCst);
+        let store = BatchStore::open(":memory:").unwrap();
+
+        let err = llm(&base, &store, None).chat_many(vec![user("x")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Rejected(_))), "{err:#}");
+        assert_eq!(*mock.deleted_files.lock().unwrap(), ["file-0"]);
+        assert!(store.pending_batches().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn answers_of_other_batches_are_cached_when_one_batch_fails() {
+        let (base, mock) = mock_server().await;
+        let store = BatchStore::open(":memory:").unwrap();
+        let cache = Arc::new(LlmCache::open(":memory:").unwrap());
+        // "y" was submitted by an earlier run to a batch the provider no longer knows
+        let lost = LlmCache::make_key("m", &cache_input(&SamplingParams::default(), &user("y"), false).unwrap());
+        store.submitted("batch-lost", &[lost]).unwrap();
+        let llm = llm(&base, &store, Some(cache));
+
+        let err = llm.chat_many(vec![user("x"), user("y")]).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Misconfigured(_))), "{err:#}");
+        assert_eq!(store.pending_batches().unwrap(), ["batch-lost"]);
+
+        // "x" came back in the new batch and is served from the cache
+        assert_eq!(llm.chat_many(vec![user("x")]).await.unwrap(), ["echo: x"]);
+        assert_eq!(mock.batches.load(Ordering::SeqCst), 1);
+    }
+}
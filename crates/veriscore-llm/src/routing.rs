@@ -0,0 +1,418 @@
+use crate::error::LlmError;
+use crate::traits::{Completion, Llm};
+use anyhow::Result;
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How a tier picks among its endpoints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingPolicy {
+    #[default]
+    RoundRobin,
+    /// The endpoint with the fewest calls in flight; ties go round robin.
+    LeastOutstanding,
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitConfig {
+    /// Consecutive failures that open an endpoint's circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit skips the endpoint before one trial call.
+    pub open_for: Duration,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        Self { failure_threshold: 5, open_for: Duration::from_secs(30) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// One trial call is in flight after the open period.
+    HalfOpen,
+}
+
+/// Routing counters for one endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EndpointStats {
+    pub name: String,
+    /// 0 for the primary endpoints, then one per fallback tier.
+    pub tier: usize,
+    pub circuit: CircuitState,
+    pub outstanding: usize,
+    /// Calls routed to this endpoint.
+    pub calls: u64,
+    /// Calls that failed and moved on to the next endpoint.
+    pub failures: u64,
+    /// Times the endpoint was passed over because its circuit was open.
+    pub short_circuited: u64,
+}
+
+/// Spreads calls over interchangeable backends, e.g. several vLLM replicas,
+/// and falls back to later tiers (other models or hosted APIs) when every
+/// endpoint of a tier is failing. A call that fails on one endpoint is
+/// retried on the next; requests the backend rejects are returned as is.
+pub struct RoutingLlm {
+    tiers: Vec<Vec<Arc<Endpoint>>>,
+    policy: RoutingPolicy,
+    circuit: CircuitConfig,
+    cursor: AtomicUsize,
+}
+
+struct Endpoint {
+    name: String,
+    tier: usize,
+    llm: Arc<dyn Llm>,
+    outstanding: AtomicUsize,
+    calls: AtomicU64,
+    failures: AtomicU64,
+    short_circuited: AtomicU64,
+    circuit: Mutex<Circuit>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Circuit {
+    Closed { consecutive_failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+impl RoutingLlm {
+    /// `primary` endpoints share the load; add fallbacks with [`Self::with_fallback_tier`].
+    pub fn new(primary: Vec<(String, Arc<dyn Llm>)>) -> Self {
+        let mut routing = Self {
+            tiers: Vec::new(),
+            policy: RoutingPolicy::default(),
+            circuit: CircuitConfig::default(),
+            cursor: AtomicUsize::new(0),
+        };
+        routing.push_tier(primary);
+        routing
+    }
+
+    /// Endpoints used only while every endpoint of the earlier tiers is failing.
+    pub fn with_fallback_tier(mut self, endpoints: Vec<(String, Arc<dyn Llm>)>) -> Self {
+        self.push_tier(endpoints);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RoutingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_circuit(mut self, circuit: CircuitConfig) -> Self {
+        self.circuit = circuit;
+        self
+    }
+
+    fn push_tier(&mut self, endpoints: Vec<(String, Arc<dyn Llm>)>) {
+        let tier = self.tiers.len();
+        self.tiers.push(
+            endpoints
+                .into_iter()
+                .map(|(name, llm)| {
+                    Arc::new(Endpoint {
+                        name,
+                        tier,
+                        llm,
+                        outstanding: AtomicUsize::new(0),
+                        calls: AtomicU64::new(0),
+                        failures: AtomicU64::new(0),
+                        short_circuited: AtomicU64::new(0),
+                        circuit: Mutex::new(Circuit::Closed { consecutive_failures: 0 }),
+                    })
+                })
+                .collect(),
+        );
+    }
+
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        self.tiers.iter().flatten().map(|e| e.stats()).collect()
+    }
+
+    /// Endpoints in the order this call should try them.
+    fn candidates(&self) -> Vec<Arc<Endpoint>> {
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let mut out = Vec::new();
+        for tier in &self.tiers {
+            let mut ordered = tier.clone();
+            if !tier.is_empty() {
+                ordered.rotate_left(start % tier.len());
+            }
+            if self.policy == RoutingPolicy::LeastOutstanding {
+                ordered.sort_by_key(|e| e.outstanding.load(Ordering::Relaxed));
+            }
+            out.extend(ordered);
+        }
+        out
+    }
+
+    async fn route(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>, scored: bool) -> Result<Vec<Completion>> {
+        let mut last_err = None;
+        for endpoint in self.candidates() {
+            let Some(attempt) = endpoint.admit(&self.circuit) else {
+                continue;
+            };
+            let result = if scored {
+                endpoint.llm.chat_many_scored(prompts.clone()).await
+            } else {
+                endpoint.llm.chat_many_with_usage(prompts.clone()).await
+            };
+            match result {
+                Ok(out) => {
+                    attempt.succeeded();
+                    return Ok(out);
+                }
+                // the backend answered, so the endpoint is fine; another one would reject the
+                // request too. A refused key or unknown model is the endpoint's fault and fails over.
+                Err(err) if matches!(LlmError::find(&err), Some(LlmError::Rejected(_))) => {
+                    attempt.succeeded();
+                    return Err(err);
+                }
+                Err(err) => {
+                    warn!(endpoint = %endpoint.name, tier = endpoint.tier, error = %err, "LLM endpoint failed; trying the next one");
+                    attempt.failed();
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| LlmError::Unavailable("every LLM endpoint's circuit is open".into()).into()))
+    }
+}
+
+impl Endpoint {
+    /// Claims a call slot unless the circuit is open.
+    fn admit<'a>(&'a self, config: &'a CircuitConfig) -> Option<Attempt<'a>> {
+        let mut circuit = self.circuit.lock().expect("circuit poisoned");
+        match *circuit {
+            Circuit::Closed { .. } => {}
+            Circuit::Open { until } if Instant::now() >= until => *circuit = Circuit::HalfOpen,
+            Circuit::Open { .. } | Circuit::HalfOpen => {
+                self.short_circuited.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        }
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.outstanding.fetch_add(1, Ordering::Relaxed);
+        Some(Attempt { endpoint: self, config, done: false })
+    }
+
+    fn stats(&self) -> EndpointStats {
+        let circuit = match *self.circuit.lock().expect("circuit poisoned") {
+            Circuit::Closed { .. } => CircuitState::Closed,
+            Circuit::Open { .. } => CircuitState::Open,
+            Circuit::HalfOpen => CircuitState::HalfOpen,
+        };
+        EndpointStats {
+            name: self.name.clone(),
+            tier: self.tier,
+            circuit,
+            outstanding: self.outstanding.load(Ordering::Relaxed),
+            calls: self.calls.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            short_circuited: self.short_circuited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// One call on an endpoint. Dropping it unresolved (the caller went away)
+/// lets the next call run the half-open trial instead.
+struct Attempt<'a> {
+    endpoint: &'a Endpoint,
+    config: &'a CircuitConfig,
+    done: bool,
+}
+
+impl Attempt<'_> {
+    fn succeeded(mut self) {
+        self.done = true;
+        let mut circuit = self.endpoint.circuit.lock().expect("circuit poisoned");
+        if !matches!(*circuit, Circuit::Closed { .. }) {
+            info!(endpoint = %self.endpoint.name, "LLM endpoint recovered; closing its circuit");
+        }
+        *circuit = Circuit::Closed { consecutive_failures: 0 };
+    }
+
+    fn failed(mut self) {
+        self.done = true;
+        self.endpoint.failures.fetch_add(1, Ordering::Relaxed);
+        let mut circuit = self.endpoint.circuit.lock().expect("circuit poisoned");
+        let failures = match *circuit {
+            Circuit::Closed { consecutive_failures } => consecutive_failures + 1,
+            // a failed trial reopens at once
+            Circuit::Open { .. } | Circuit::HalfOpen => self.config.failure_threshold,
+        };
+        if failures >= self.config.failure_threshold {
+            warn!(endpoint = %self.endpoint.name, open_for = ?self.config.open_for, "opening LLM endpoint circuit");
+            *circuit = Circuit::Open { until: Instant::now() + self.config.open_for };
+        } else {
+            *circuit = Circuit::Closed { consecutive_failures: failures };
+        }
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        self.endpoint.outstanding.fetch_sub(1, Ordering::Relaxed);
+        if !self.done {
+            let mut circuit = self.endpoint.circuit.lock().expect("circuit poisoned");
+            if matches!(*circuit, Circuit::HalfOpen) {
+                *circuit = Circuit::Open { until: Instant::now() };
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Llm for RoutingLlm {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+        let out = self.route(prompts, false).await?;
+        Ok(out.into_iter().map(|c| c.text).collect())
+    }
+
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.route(prompts, false).await
+    }
+
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.route(prompts, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+    use std::sync::atomic::AtomicBool;
+
+    /// Answers with its own name, or fails with `error` while `down` is set.
+    struct Replica {
+        name: &'static str,
+        down: AtomicBool,
+        error: fn() -> LlmError,
+        delay: Duration,
+    }
+
+    impl Replica {
+        fn up(name: &'static str) -> Arc<Self> {
+            Arc::new(Self { name, down: AtomicBool::new(false), error: || LlmError::Unavailable("down".into()), delay: Duration::ZERO })
+        }
+
+        fn down(name: &'static str, error: fn() -> LlmError) -> Arc<Self> {
+            Arc::new(Self { name, down: AtomicBool::new(true), error, delay: Duration::ZERO })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Llm for Replica {
+        async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>> {
+            tokio::time::sleep(self.delay).await;
+            if self.down.load(Ordering::SeqCst) {
+                return Err((self.error)().into());
+            }
+            Ok(prompts.iter().map(|_| self.name.to_string()).collect())
+        }
+    }
+
+    fn prompt() -> Vec<ChatCompletionRequestMessage> {
+        vec![ChatCompletionRequestUserMessageArgs::default().content("hi").build().unwrap().into()]
+    }
+
+    fn endpoint(replica: &Arc<Replica>) -> (String, Arc<dyn Llm>) {
+        (replica.name.to_string(), replica.clone())
+    }
+
+    #[tokio::test]
+    async fn round_robin_spreads_calls_and_least_outstanding_avoids_busy_replicas() {
+        let (a, b) = (Replica::up("a"), Replica::up("b"));
+        let llm = RoutingLlm::new(vec![endpoint(&a), endpoint(&b)]);
+        let mut answers = Vec::new();
+        for _ in 0..4 {
+            answers.push(llm.chat_one(prompt()).await.unwrap());
+        }
+        assert_eq!(answers, ["a", "b", "a", "b"]);
+
+        let slow = Arc::new(Replica {
+            name: "slow",
+            down: AtomicBool::new(false),
+            error: || LlmError::Unavailable("down".into()),
+            delay: Duration::from_millis(200),
+        });
+        let llm = Arc::new(RoutingLlm::new(vec![endpoint(&slow), endpoint(&b)]).with_policy(RoutingPolicy::LeastOutstanding));
+        let busy = tokio::spawn({
+            let llm = llm.clone();
+            async move { llm.chat_one(prompt()).await.unwrap() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        for _ in 0..3 {
+            assert_eq!(llm.chat_one(prompt()).await.unwrap(), "b");
+        }
+        assert_eq!(busy.await.unwrap(), "slow");
+    }
+
+    #[tokio::test]
+    async fn failing_replica_trips_its_circuit_then_falls_back_and_recovers() {
+        let flaky = Replica::down("flaky", || LlmError::Unavailable("connection refused".into()));
+        let hosted = Replica::up("hosted");
+        let llm = RoutingLlm::new(vec![endpoint(&flaky)])
+            .with_fallback_tier(vec![endpoint(&hosted)])
+            .with_circuit(CircuitConfig { failure_threshold: 2, open_for: Duration::from_millis(50) });
+
+        for _ in 0..3 {
+            assert_eq!(llm.chat_one(prompt()).await.unwrap(), "hosted");
+        }
+        let stats = llm.stats();
+        assert_eq!((stats[0].calls, stats[0].failures, stats[0].short_circuited), (2, 2, 1));
+        assert_eq!(stats[0].circuit, CircuitState::Open);
+        assert_eq!((stats[1].tier, stats[1].calls), (1, 3));
+
+        // after the open period one trial call goes through and closes the circuit
+        flaky.down.store(false, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(llm.chat_one(prompt()).await.unwrap(), "flaky");
+        assert_eq!(llm.stats()[0].circuit, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn rejected_requests_do_not_fail_over() {
+        let strict = Replica::down("strict", || LlmError::Rejected("context length exceeded".into()));
+        let other = Replica::up("other");
+        let llm = RoutingLlm::new(vec![endpoint(&strict)]).with_fallback_tier(vec![endpoint(&other)]);
+
+        let err = llm.chat_one(prompt()).await.unwrap_err();
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Rejected(_))));
+        let stats = llm.stats();
+        assert_eq!((stats[0].failures, stats[0].circuit), (0, CircuitState::Closed));
+        assert_eq!(stats[1].calls, 0);
+    }
+
+    #[tokio::test]
+    async fn misconfigured_endpoints_fail_over_and_open_the_circuit() {
+        let locked = Replica::down("locked", || LlmError::Misconfigured("401 Unauthorized: invalid api key".into()));
+        let other = Replica::up("other");
+        let llm = RoutingLlm::new(vec![endpoint(&locked)])
+            .with_fallback_tier(vec![endpoint(&other)])
+            .with_circuit(CircuitConfig { failure_threshold: 2, open_for: Duration::from_secs(60) });
+
+        for _ in 0..3 {
+            assert_eq!(llm.chat_one(prompt()).await.unwrap(), "other");
+        }
+        let stats = llm.stats();
+        assert_eq!((stats[0].calls, stats[0].failures, stats[0].short_circuited), (2, 2, 1));
+        assert_eq!(stats[0].circuit, CircuitState::Open);
+        assert_eq!(stats[1].calls, 3);
+    }
+}
@@ -0,0 +1,56 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+
+/// Decoding settings passed through to the backend. Unset fields use the
+/// backend's own defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Sent to OpenAI-compatible servers that support it. It is always part of
+    /// the cache key, so backends without seeds (Anthropic) still get one cache
+    /// entry per seed when drawing several samples.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl SamplingParams {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// What a response is cached under besides the model. Default sampling
+    /// keys on the messages alone, so caches written before sampling params
+    /// existed stay valid.
+    pub(crate) fn cache_input(&self, messages: &[ChatCompletionRequestMessage]) -> serde_json::Result<String> {
+        if self.is_default() {
+            serde_json::to_string(messages)
+        } else {
+            serde_json::to_string(&(messages, self))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    #[test]
+    fn cache_input_only_changes_with_non_default_sampling() {
+        let messages = vec![ChatCompletionRequestUserMessageArgs::default().content("hi").build().unwrap().into()];
+        let plain = serde_json::to_string(&messages).unwrap();
+
+        assert_eq!(SamplingParams::default().cache_input(&messages).unwrap(), plain);
+        let hot = SamplingParams { temperature: Some(0.7), ..Default::default() };
+        let cold = SamplingParams { temperature: Some(0.0), ..Default::default() };
+        assert_ne!(hot.cache_input(&messages).unwrap(), plain);
+        assert_ne!(hot.cache_input(&messages).unwrap(), cold.cache_input(&messages).unwrap());
+    }
+}
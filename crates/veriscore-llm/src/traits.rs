@@ -1,12 +1,52 @@
+use crate::error::LlmError;
+use crate::usage::TokenUsage;
 use anyhow::Result;
-use async_openai::types::ChatCompletionRequestMessage;
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionTokenLogprob};
+use serde::{Deserialize, Serialize};
+
+/// A completion with whatever the backend reported about it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Completion {
+    pub text: String,
+    /// One entry per generated token, with the most likely alternatives.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<ChatCompletionTokenLogprob>>,
+    /// Tokens spent on this call; `None` for cache hits and for servers that
+    /// report no usage (unless a `TokenCounter` fills it in).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// USD for `usage`, when the backend was given a price for its model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// Served from the response cache without calling the backend.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+impl Completion {
+    pub fn text(text: String) -> Self {
+        Self { text, ..Default::default() }
+    }
+}
 
 #[async_trait::async_trait]
 pub trait Llm: Send + Sync {
     async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<String>>;
 
+    /// Like `chat_many`, with token usage where the backend reports it.
+    async fn chat_many_with_usage(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        let texts = self.chat_many(prompts).await?;
+        Ok(texts.into_iter().map(Completion::text).collect())
+    }
+
+    /// Like `chat_many_with_usage`, but also asks for token logprobs.
+    /// Backends that cannot return them answer with `logprobs: None`.
+    async fn chat_many_scored(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> Result<Vec<Completion>> {
+        self.chat_many_with_usage(prompts).await
+    }
+
     async fn chat_one(&self, prompt: Vec<ChatCompletionRequestMessage>) -> Result<String> {
         let mut out = self.chat_many(vec![prompt]).await?;
-        out.pop().ok_or_else(|| anyhow::anyhow!("empty LLM response batch"))
+        Ok(out.pop().ok_or_else(|| LlmError::BadResponse("empty LLM response batch".into()))?)
     }
 }
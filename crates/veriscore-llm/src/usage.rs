@@ -0,0 +1,169 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPart, ChatCompletionRequestUserMessageContent,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// Tokens spent on one or more completions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// USD per million tokens, as providers list them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_mtok + usage.completion_tokens as f64 * self.output_per_mtok) / 1e6
+    }
+}
+
+/// Counts tokens locally for servers that leave `usage` out of their
+/// responses. `TokenizerCounter` (with the `candle` feature) runs the served
+/// model's Hugging Face tokenizer; [`ApproxTokenCounter`] is the
+/// dependency-free estimate.
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> u64;
+
+    fn usage(&self, messages: &[ChatCompletionRequestMessage], completion: &str) -> TokenUsage {
+        TokenUsage { prompt_tokens: self.count(&prompt_text(messages)), completion_tokens: self.count(completion) }
+    }
+}
+
+/// Estimates tokens from the character count; English averages about four
+/// characters per token with GPT and Llama tokenizers.
+#[derive(Debug, Clone, Copy)]
+pub struct ApproxTokenCounter {
+    pub chars_per_token: f32,
+}
+
+impl Default for ApproxTokenCounter {
+    fn default() -> Self {
+        Self { chars_per_token: 4.0 }
+    }
+}
+
+impl TokenCounter for ApproxTokenCounter {
+    fn count(&self, text: &str) -> u64 {
+        (text.chars().count() as f32 / self.chars_per_token.max(0.1)).ceil() as u64
+    }
+}
+
+/// Exact counts from a Hugging Face `tokenizer.json`. Special tokens and the
+/// chat template are not counted, so prompts come out a few tokens short.
+#[cfg(feature = "candle")]
+pub struct TokenizerCounter {
+    tokenizer: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "candle")]
+impl TokenizerCounter {
+    pub fn new(tokenizer: tokenizers::Tokenizer) -> Self {
+        Self { tokenizer }
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let tokenizer =
+            tokenizers::Tokenizer::from_file(path).map_err(|e| anyhow::anyhow!("invalid tokenizer {}: {e}", path.display()))?;
+        Ok(Self::new(tokenizer))
+    }
+}
+
+#[cfg(feature = "candle")]
+impl TokenCounter for TokenizerCounter {
+    fn count(&self, text: &str) -> u64 {
+        match self.tokenizer.encode_fast(text, false) {
+            Ok(encoding) => encoding.len() as u64,
+            Err(e) => {
+                tracing::warn!(error = %e, "tokenizer failed; estimating the token count");
+                ApproxTokenCounter::default().count(text)
+            }
+        }
+    }
+}
+
+/// The text content of a prompt, one message per line; images are skipped.
+fn prompt_text(messages: &[ChatCompletionRequestMessage]) -> String {
+    messages
+        .iter()
+        .map(|message| match message {
+            ChatCompletionRequestMessage::System(m) => m.content.clone(),
+            ChatCompletionRequestMessage::User(m) => match &m.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => text.clone(),
+                ChatCompletionRequestUserMessageContent::Array(parts) => parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ChatCompletionRequestMessageContentPart::Text(t) => Some(t.text.as_str()),
+                        ChatCompletionRequestMessageContentPart::ImageUrl(_) => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            },
+            ChatCompletionRequestMessage::Assistant(m) => m.content.clone().unwrap_or_default(),
+            ChatCompletionRequestMessage::Tool(m) => m.content.clone(),
+            ChatCompletionRequestMessage::Function(m) => m.content.clone().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::{ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs};
+
+    #[test]
+    fn approx_counter_covers_every_message() {
+        let messages = vec![
+            ChatCompletionRequestSystemMessageArgs::default().content("abcd").build().unwrap().into(),
+            ChatCompletionRequestUserMessageArgs::default().content("efgh").build().unwrap().into(),
+        ];
+
+        // "abcd\nefgh" is 9 characters
+        let usage = ApproxTokenCounter::default().usage(&messages, "xyz");
+        assert_eq!(usage, TokenUsage { prompt_tokens: 3, completion_tokens: 1 });
+        assert_eq!(usage.total(), 4);
+    }
+
+    #[cfg(feature = "candle")]
+    #[test]
+    fn tokenizer_counter_counts_words() {
+        use tokenizers::models::wordlevel::WordLevel;
+        use tokenizers::pre_tokenizers::whitespace::Whitespace;
+
+        let vocab = [("[UNK]", 0), ("the", 1), ("tower", 2)].into_iter().map(|(w, i)| (w.to_string(), i)).collect();
+        let mut tokenizer = tokenizers::Tokenizer::new(WordLevel::builder().vocab(vocab).unk_token("[UNK]".into()).build().unwrap());
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+        let counter = TokenizerCounter::new(tokenizer);
+
+        assert_eq!(counter.count("the tower is tall"), 4);
+        assert_eq!(counter.count(""), 0);
+    }
+
+    #[test]
+    fn model_price_is_per_million_tokens() {
+        let price = ModelPrice { input_per_mtok: 3.0, output_per_mtok: 15.0 };
+        let cost = price.cost(&TokenUsage { prompt_tokens: 1_000_000, completion_tokens: 2_000 });
+        assert!((cost - 3.03).abs() < 1e-9);
+    }
+}
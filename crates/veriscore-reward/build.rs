@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // vendored protoc so builds don't depend on a system install
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure()
+        .build_client(true)
+        .compile_protos(&["proto/reward.proto"], &["proto"])?;
+    println!("cargo:rerun-if-changed=proto/reward.proto");
+    Ok(())
+}
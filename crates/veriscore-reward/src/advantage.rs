@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How rewards within one `group_id` are turned into GRPO advantages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdvantageMode {
+    /// `r - mean(r)`
+    #[default]
+    MeanCentered,
+    /// `(r - mean(r)) / (std(r) + epsilon)`, using the population std.
+    ZScore,
+    /// Average rank within the group, scaled to `[-1, 1]`; ties share a rank.
+    Rank,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdvantageConfig {
+    #[serde(default)]
+    pub mode: AdvantageMode,
+    /// Clamp advantages to `[-clip, clip]`.
+    #[serde(default)]
+    pub clip: Option<f32>,
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f32,
+}
+
+fn default_epsilon() -> f32 { 1e-4 }
+
+impl Default for AdvantageConfig {
+    fn default() -> Self {
+        Self { mode: AdvantageMode::default(), clip: None, epsilon: default_epsilon() }
+    }
+}
+
+pub fn compute_advantages(rewards: &[f32], cfg: &AdvantageConfig) -> Vec<f32> {
+    if rewards.is_empty() {
+        return Vec::new();
+    }
+    let n = rewards.len() as f32;
+    let mean = rewards.iter().sum::<f32>() / n;
+
+    let raw = match cfg.mode {
+        AdvantageMode::MeanCentered => rewards.iter().map(|r| r - mean).collect::<Vec<_>>(),
+        AdvantageMode::ZScore => {
+            let var = rewards.iter().map(|r| (r - mean).powi(2)).sum::<f32>() / n;
+            let denom = var.sqrt() + cfg.epsilon;
+            rewards.iter().map(|r| (r - mean) / denom).collect()
+        }
+        AdvantageMode::Rank => {
+            if rewards.len() == 1 {
+                vec![0.0]
+            } else {
+                let max_rank = (rewards.len() - 1) as f32;
+                average_ranks(rewards).into_iter().map(|rank| 2.0 * rank / max_rank - 1.0).collect()
+            }
+        }
+    };
+
+    match cfg.clip {
+        Some(clip) => raw.into_iter().map(|a| a.clamp(-clip.abs(), clip.abs())).collect(),
+        None => raw,
+    }
+}
+
+/// 0-based ranks in ascending reward order; tied rewards get the mean of their ranks.
+fn average_ranks(values: &[f32]) -> Vec<f32> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let avg = (start + end - 1) as f32 / 2.0;
+        for &idx in &order[start..end] {
+            ranks[idx] = avg;
+        }
+        start = end;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-4)
+    }
+
+    #[test]
+    fn mean_centered_subtracts_group_mean() {
+        let cfg = AdvantageConfig::default();
+        let out = compute_advantages(&[0.2, 0.4, 0.6], &cfg);
+        assert!(approx(&out, &[-0.2, 0.0, 0.2]));
+    }
+
+    #[test]
+    fn z_score_normalizes_spread() {
+        let cfg = AdvantageConfig { mode: AdvantageMode::ZScore, epsilon: 0.0, ..Default::default() };
+        let out = compute_advantages(&[1.0, 3.0], &cfg);
+        assert!(approx(&out, &[-1.0, 1.0]));
+    }
+
+    #[test]
+    fn z_score_uses_epsilon_for_constant_groups() {
+        let cfg = AdvantageConfig { mode: AdvantageMode::ZScore, ..Default::default() };
+        let out = compute_advantages(&[0.5, 0.5, 0.5], &cfg);
+        assert!(out.iter().all(|a| a.is_finite() && a.abs() < 1e-6));
+    }
+
+    #[test]
+    fn rank_averages_ties_and_spans_unit_interval() {
+        let cfg = AdvantageConfig { mode: AdvantageMode::Rank, ..Default::default() };
+        let out = compute_advantages(&[0.9, 0.1, 0.5, 0.5], &cfg);
+        // ranks: 3, 0, 1.5, 1.5 over max rank 3
+        assert!(approx(&out, &[1.0, -1.0, 0.0, 0.0]));
+        assert!(approx(&compute_advantages(&[0.3], &cfg), &[0.0]));
+    }
+
+    #[test]
+    fn clip_bounds_advantages() {
+        let cfg = AdvantageConfig { mode: AdvantageMode::ZScore, clip: Some(0.5), epsilon: 0.0 };
+        let out = compute_advantages(&[0.0, 1.0], &cfg);
+        assert!(approx(&out, &[-0.5, 0.5]));
+    }
+
+    #[test]
+    fn empty_group_yields_no_advantages() {
+        assert!(compute_advantages(&[], &AdvantageConfig::default()).is_empty());
+    }
+}
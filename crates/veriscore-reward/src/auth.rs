@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::RewardError;
+use crate::reward_engine::UsageSink;
+use crate::reward_types::RewardRequest;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// One entry of the API keys file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyEntry {
+    pub key: String,
+    pub tenant: String,
+    /// Sustained requests per second; also the burst size.
+    #[serde(default)]
+    pub qps: Option<u32>,
+    /// Claims this key may have verified per UTC day.
+    #[serde(default)]
+    pub daily_claim_quota: Option<u64>,
+    /// Allows the `/admin` routes.
+    #[serde(default)]
+    pub admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeysFile {
+    keys: Vec<ApiKeyEntry>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("missing bearer token")]
+    MissingToken,
+    #[error("invalid API key")]
+    InvalidKey,
+    #[error("tenant {0} is not an admin")]
+    Forbidden(String),
+    #[error("tenant {tenant} exceeded its request rate")]
+    RateLimited { tenant: String, retry_after: Duration },
+    #[error("tenant {tenant} exhausted its daily claim quota of {quota}")]
+    QuotaExhausted { tenant: String, quota: u64, retry_after: Duration },
+}
+
+impl AuthError {
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AuthError::RateLimited { retry_after, .. } | AuthError::QuotaExhausted { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+pub struct Tenant {
+    pub name: String,
+    pub admin: bool,
+    limiter: Option<DefaultDirectRateLimiter>,
+    daily_claim_quota: Option<u64>,
+    // (UTC day number, claims charged that day)
+    usage: Mutex<(u64, u64)>,
+}
+
+impl std::fmt::Debug for Tenant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tenant")
+            .field("name", &self.name)
+            .field("admin", &self.admin)
+            .field("daily_claim_quota", &self.daily_claim_quota)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tenant {
+    fn new(entry: &ApiKeyEntry) -> Self {
+        Self {
+            name: entry.tenant.clone(),
+            admin: entry.admin,
+            limiter: entry.qps.and_then(NonZeroU32::new).map(|qps| RateLimiter::direct(Quota::per_second(qps))),
+            daily_claim_quota: entry.daily_claim_quota,
+            usage: Mutex::new((0, 0)),
+        }
+    }
+
+    pub fn require_admin(&self) -> Result<(), AuthError> {
+        if self.admin { Ok(()) } else { Err(AuthError::Forbidden(self.name.clone())) }
+    }
+
+    pub fn claims_used_today(&self) -> u64 {
+        let usage = self.usage.lock().expect("tenant usage poisoned");
+        if usage.0 == today() { usage.1 } else { 0 }
+    }
+
+    fn check_rate(&self) -> Result<(), AuthError> {
+        match &self.limiter {
+            Some(limiter) => limiter.check().map_err(|not_until| AuthError::RateLimited {
+                tenant: self.name.clone(),
+                retry_after: not_until.wait_time_from(DefaultClock::default().now()),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_quota(&self) -> Result<(), AuthError> {
+        match self.daily_claim_quota {
+            Some(quota) if self.claims_used_today() >= quota => Err(AuthError::QuotaExhausted {
+                tenant: self.name.clone(),
+                quota,
+                retry_after: until_next_day(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Claims are charged as completions finish, so a request that starts under
+/// quota may overshoot it by at most its own claims.
+impl UsageSink for Tenant {
+    fn record_claims(&self, claims: usize) {
+        let mut usage = self.usage.lock().expect("tenant usage poisoned");
+        let day = today();
+        if usage.0 != day {
+            *usage = (day, 0);
+        }
+        usage.1 += claims as u64;
+    }
+}
+
+/// Bearer-token authentication against a keys file, with per-key limits.
+pub struct ApiKeyAuth {
+    tenants: HashMap<String, Arc<Tenant>>,
+    /// The first key listed for each tenant name.
+    by_name: HashMap<String, Arc<Tenant>>,
+}
+
+impl ApiKeyAuth {
+    pub fn new(entries: &[ApiKeyEntry]) -> Self {
+        let mut tenants = HashMap::new();
+        let mut by_name = HashMap::new();
+        for entry in entries {
+            let tenant = Arc::new(Tenant::new(entry));
+            by_name.entry(entry.tenant.clone()).or_insert_with(|| tenant.clone());
+            tenants.insert(entry.key.clone(), tenant);
+        }
+        Self { tenants, by_name }
+    }
+
+    /// The tenant of the first key listed under `name`, e.g. to charge a job
+    /// resumed after a restart.
+    pub fn tenant(&self, name: &str) -> Option<Arc<Tenant>> {
+        self.by_name.get(name).cloned()
+    }
+
+    /// Loads `{"keys": [{"key", "tenant", "qps"?, "daily_claim_quota"?, "admin"?}, ...]}`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let raw = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("failed to read API keys file {}", path.as_ref().display()))?;
+        let file: KeysFile = serde_json::from_str(&raw).context("invalid API keys file")?;
+        Ok(Self::new(&file.keys))
+    }
+
+    /// Resolves an `Authorization` header value and applies the key's rate limit and quota.
+    pub fn authorize(&self, authorization: Option<&str>) -> Result<Arc<Tenant>, AuthError> {
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or(AuthError::MissingToken)?;
+        let tenant = self.tenants.get(token).ok_or(AuthError::InvalidKey)?;
+        tenant.check_quota()?;
+        tenant.check_rate()?;
+        Ok(tenant.clone())
+    }
+}
+
+/// Per-request size limits.
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    pub max_body_bytes: usize,
+    /// Completions per request, summed over all groups.
+    pub max_completions: usize,
+    /// Characters per completion `response`.
+    pub max_response_chars: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 16 * 1024 * 1024,
+            max_completions: 4096,
+            max_response_chars: 32_768,
+        }
+    }
+}
+
+impl RequestLimits {
+    pub fn check<'a>(&self, groups: impl IntoIterator<Item = &'a RewardRequest> + Clone) -> Result<(), RewardError> {
+        self.check_response_lengths(groups.clone())?;
+        let completions = groups.into_iter().map(|g| g.completions.len()).sum::<usize>();
+        if completions > self.max_completions {
+            return Err(RewardError::PayloadTooLarge(format!(
+                "{completions} completions in one request; the limit is {}",
+                self.max_completions
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_response_lengths<'a>(&self, groups: impl IntoIterator<Item = &'a RewardRequest>) -> Result<(), RewardError> {
+        for group in groups {
+            for (i, c) in group.completions.iter().enumerate() {
+                let chars = c.response.chars().count();
+                if chars > self.max_response_chars {
+                    return Err(RewardError::PayloadTooLarge(format!(
+                        "completion {i} of group {:?} has {chars} characters; the limit is {}",
+                        group.group_id, self.max_response_chars
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn today() -> u64 {
+    unix_secs() / SECS_PER_DAY
+}
+
+fn until_next_day() -> Duration {
+    Duration::from_secs(SECS_PER_DAY - unix_secs() % SECS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::mk_group;
+
+    fn entry(key: &str, qps: Option<u32>, quota: Option<u64>) -> ApiKeyEntry {
+        ApiKeyEntry { key: key.to_string(), tenant: format!("tenant-{key}"), qps, daily_claim_quota: quota, admin: false }
+    }
+
+    #[test]
+    fn authorize_requires_known_bearer_token() {
+        let auth = ApiKeyAuth::new(&[entry("k1", None, None)]);
+
+        assert!(matches!(auth.authorize(None), Err(AuthError::MissingToken)));
+        assert!(matches!(auth.authorize(Some("k1")), Err(AuthError::MissingToken)));
+        assert!(matches!(auth.authorize(Some("Bearer nope")), Err(AuthError::InvalidKey)));
+        assert_eq!(auth.authorize(Some("Bearer k1")).unwrap().name, "tenant-k1");
+    }
+
+    #[test]
+    fn authorize_enforces_qps() {
+        let auth = ApiKeyAuth::new(&[entry("k1", Some(2), None)]);
+
+        assert!(auth.authorize(Some("Bearer k1")).is_ok());
+        assert!(auth.authorize(Some("Bearer k1")).is_ok());
+        let err = auth.authorize(Some("Bearer k1")).unwrap_err();
+        assert!(matches!(err, AuthError::RateLimited { .. }));
+        assert!(err.retry_after().is_some());
+    }
+
+    #[test]
+    fn authorize_enforces_daily_claim_quota() {
+        let auth = ApiKeyAuth::new(&[entry("k1", None, Some(10))]);
+        let tenant = auth.authorize(Some("Bearer k1")).unwrap();
+
+        tenant.record_claims(6);
+        assert!(auth.authorize(Some("Bearer k1")).is_ok());
+        tenant.record_claims(4);
+        assert_eq!(tenant.claims_used_today(), 10);
+        assert!(matches!(auth.authorize(Some("Bearer k1")), Err(AuthError::QuotaExhausted { quota: 10, .. })));
+    }
+
+    #[test]
+    fn limits_reject_oversized_requests() {
+        let limits = RequestLimits { max_body_bytes: 1024, max_completions: 2, max_response_chars: 10 };
+
+        assert!(limits.check([&mk_group("g0", 4, &["short", "short"])]).is_ok());
+        assert!(limits.check([&mk_group("g0", 4, &["a", "b"]), &mk_group("g1", 4, &["c"])]).is_err());
+        let err = limits.check([&mk_group("g0", 4, &["this one is too long"])]).unwrap_err();
+        assert!(err.to_string().contains("characters"));
+    }
+}
@@ -0,0 +1,197 @@
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use veriscore_core::CoreError;
+use veriscore_llm::LlmError;
+use veriscore_web::SearchError;
+
+use crate::auth::AuthError;
+
+/// Everything the reward API can fail with. Backend errors arrive wrapped in
+/// `anyhow` and are recovered by [`RewardError::from_anyhow`].
+#[derive(Debug, thiserror::Error)]
+pub enum RewardError {
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("request too large: {0}")]
+    PayloadTooLarge(String),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    #[error("job API is disabled")]
+    JobsDisabled,
+    #[error("unknown job {0}")]
+    JobNotFound(String),
+    #[error("job {job_id} has no result yet (status: {status})")]
+    JobNotReady { job_id: String, status: String },
+    #[error(transparent)]
+    Core(#[from] CoreError),
+    #[error(transparent)]
+    Llm(#[from] LlmError),
+    #[error(transparent)]
+    Search(#[from] SearchError),
+    #[error("{0}")]
+    Internal(anyhow::Error),
+}
+
+/// JSON error response: `{"error": {"code": ..., "message": ..., "retryable": ...}}`.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl RewardError {
+    /// Classifies an error by the first typed error found in its chain.
+    pub fn from_anyhow(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<RewardError>() {
+            Ok(reward_err) => return reward_err,
+            Err(err) => err,
+        };
+        if let Some(e) = LlmError::find(&err) {
+            return RewardError::Llm(e.clone());
+        }
+        if let Some(e) = SearchError::find(&err) {
+            return RewardError::Search(e.clone());
+        }
+        if let Some(e) = CoreError::find(&err) {
+            return RewardError::Core(e.clone());
+        }
+        RewardError::Internal(err)
+    }
+
+    /// Machine-readable error code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RewardError::InvalidRequest(_) => "invalid_request",
+            RewardError::PayloadTooLarge(_) => "payload_too_large",
+            RewardError::Auth(AuthError::MissingToken | AuthError::InvalidKey) => "unauthorized",
+            RewardError::Auth(AuthError::Forbidden(_)) => "forbidden",
+            RewardError::Auth(AuthError::RateLimited { .. }) => "rate_limited",
+            RewardError::Auth(AuthError::QuotaExhausted { .. }) => "quota_exhausted",
+            RewardError::JobsDisabled | RewardError::JobNotFound(_) => "not_found",
+            RewardError::JobNotReady { .. } => "job_not_ready",
+            RewardError::Core(CoreError::InvalidInput(_)) => "invalid_request",
+            RewardError::Core(CoreError::OutputCountMismatch { .. }) => "llm_bad_response",
+            RewardError::Core(CoreError::BudgetExceeded(_)) => "budget_exceeded",
+            RewardError::Llm(e) => match e {
+                LlmError::RateLimited(_) => "llm_rate_limited",
+                LlmError::QuotaExhausted(_) => "llm_quota_exhausted",
+                LlmError::Unavailable(_) => "llm_unavailable",
+                LlmError::Rejected(_) => "llm_rejected",
+                LlmError::Misconfigured(_) => "llm_misconfigured",
+                LlmError::BadResponse(_) => "llm_bad_response",
+            },
+            RewardError::Search(e) => match e {
+                SearchError::RateLimited(_) => "search_rate_limited",
+                SearchError::QuotaExhausted(_) => "search_quota_exhausted",
+                SearchError::Unauthorized(_) => "search_unauthorized",
+                SearchError::Unavailable(_) => "search_unavailable",
+                SearchError::Rejected(_) => "search_rejected",
+                SearchError::BadResponse(_) => "search_bad_response",
+            },
+            RewardError::Internal(_) => "internal",
+        }
+    }
+
+    /// Whether retrying the same request later may succeed.
+    pub fn retryable(&self) -> bool {
+        match self {
+            RewardError::Auth(e) => e.retry_after().is_some(),
+            RewardError::JobNotReady { .. } => true,
+            RewardError::Core(CoreError::OutputCountMismatch { .. }) => true,
+            RewardError::Llm(e) => e.is_retryable(),
+            RewardError::Search(e) => e.is_retryable(),
+            _ => false,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RewardError::InvalidRequest(_) | RewardError::Core(CoreError::InvalidInput(_)) => StatusCode::BAD_REQUEST,
+            RewardError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            RewardError::Auth(AuthError::MissingToken | AuthError::InvalidKey) => StatusCode::UNAUTHORIZED,
+            RewardError::Auth(AuthError::Forbidden(_)) => StatusCode::FORBIDDEN,
+            RewardError::Auth(_) => StatusCode::TOO_MANY_REQUESTS,
+            RewardError::JobsDisabled | RewardError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            RewardError::JobNotReady { .. } => StatusCode::CONFLICT,
+            RewardError::Core(CoreError::BudgetExceeded(_)) => StatusCode::PAYMENT_REQUIRED,
+            // upstream throttling or outages: the reward service itself is fine
+            RewardError::Llm(LlmError::RateLimited(_) | LlmError::QuotaExhausted(_))
+            | RewardError::Search(SearchError::RateLimited(_) | SearchError::QuotaExhausted(_)) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            RewardError::Core(_) | RewardError::Llm(_) | RewardError::Search(_) => StatusCode::BAD_GATEWAY,
+            RewardError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody { code: self.code().to_string(), message: self.to_string(), retryable: self.retryable() }
+    }
+}
+
+impl From<anyhow::Error> for RewardError {
+    fn from(err: anyhow::Error) -> Self {
+        RewardError::from_anyhow(err)
+    }
+}
+
+impl IntoResponse for RewardError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::warn!(code = self.code(), error = %self, "reward request failed");
+        }
+        let mut response = (status, Json(ErrorResponse { error: self.body() })).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        if let RewardError::Auth(err) = &self {
+            if let Some(retry_after) = err.retry_after() {
+                let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn from_anyhow_finds_backend_errors_through_context() {
+        let err = anyhow::Error::from(SearchError::QuotaExhausted("Not enough credits".into())).context("evidence failed");
+        let err = RewardError::from_anyhow(err);
+        assert_eq!(err.code(), "search_quota_exhausted");
+        assert_eq!(err.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!err.retryable());
+
+        let err: Result<(), _> = Err(LlmError::Unavailable("connection reset".into()));
+        let err = RewardError::from_anyhow(err.context("extraction failed").unwrap_err());
+        assert_eq!(err.code(), "llm_unavailable");
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        assert!(err.retryable());
+    }
+
+    #[test]
+    fn from_anyhow_keeps_reward_errors_and_falls_back_to_internal() {
+        let err = RewardError::from_anyhow(RewardError::InvalidRequest("k_median must be positive".into()).into());
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.body().code, "invalid_request");
+
+        let err = RewardError::from_anyhow(anyhow::anyhow!("disk full"));
+        assert_eq!(err.code(), "internal");
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,294 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+use veriscore_core::types::{InputRecord, SearchUsage, StageUsage};
+
+use crate::advantage::{AdvantageConfig, AdvantageMode};
+use crate::auth::{ApiKeyAuth, RequestLimits};
+use crate::error::{ErrorBody, RewardError};
+use crate::reward_engine::RewardEngine;
+use crate::reward_shaping::{ShapingBreakdown, ShapingComponent};
+use crate::reward_types::{CompletionReward, RewardDetail, RewardRequest, RewardResponse, RewardStreamEvent};
+
+pub mod proto {
+    tonic::include_proto!("veriscore.reward.v1");
+}
+
+pub use proto::reward_service_server::RewardServiceServer;
+
+/// tonic `RewardService` backed by the same `RewardEngine` as the HTTP API.
+#[derive(Clone)]
+pub struct RewardGrpcService {
+    engine: Arc<RewardEngine>,
+    auth: Option<Arc<ApiKeyAuth>>,
+    limits: RequestLimits,
+}
+
+impl RewardGrpcService {
+    pub fn new(engine: Arc<RewardEngine>) -> Self {
+        Self { engine, auth: None, limits: RequestLimits::default() }
+    }
+
+    /// Requires `authorization: Bearer <key>` metadata on every call.
+    pub fn with_auth(mut self, auth: Arc<ApiKeyAuth>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: RequestLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn into_server(self) -> RewardServiceServer<Self> {
+        RewardServiceServer::new(self)
+    }
+
+    /// Authorizes the call and returns the engine that charges the caller's tenant.
+    #[allow(clippy::result_large_err)]
+    fn engine_for<T>(&self, request: &Request<T>) -> Result<Arc<RewardEngine>, Status> {
+        let Some(auth) = &self.auth else {
+            return Ok(self.engine.clone());
+        };
+        let authorization = request.metadata().get("authorization").and_then(|v| v.to_str().ok());
+        let tenant = auth.authorize(authorization).map_err(|err| to_status(err.into()))?;
+        Ok(Arc::new(self.engine.with_usage_sink(tenant)))
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::RewardStreamEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl proto::reward_service_server::RewardService for RewardGrpcService {
+    async fn score_group(&self, request: Request<proto::RewardRequest>) -> Result<Response<proto::RewardResponse>, Status> {
+        let engine = self.engine_for(&request)?;
+        let (request, include_details) = from_proto_request(request.into_inner());
+        self.limits.check([&request]).map_err(to_status)?;
+        let response = engine.score_batch(request, include_details).await.map_err(to_status)?;
+        Ok(Response::new(to_proto_response(response)))
+    }
+
+    type ScoreGroupStreamStream = EventStream;
+
+    async fn score_group_stream(&self, request: Request<proto::RewardRequest>) -> Result<Response<EventStream>, Status> {
+        let engine = self.engine_for(&request)?;
+        let (request, include_details) = from_proto_request(request.into_inner());
+        self.limits.check([&request]).map_err(to_status)?;
+        let events = engine.score_stream(request, include_details).map(to_proto_event);
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+// tonic streams carry `Status` by value
+#[allow(clippy::result_large_err)]
+fn to_proto_event(event: RewardStreamEvent) -> Result<proto::RewardStreamEvent, Status> {
+    let event = match event {
+        RewardStreamEvent::Completion(c) => proto::reward_stream_event::Event::Completion(to_proto_completion(c)),
+        RewardStreamEvent::Summary(summary) => proto::reward_stream_event::Event::Summary(to_proto_response(summary)),
+        RewardStreamEvent::Error(body) => return Err(body_to_status(&body)),
+    };
+    Ok(proto::RewardStreamEvent { event: Some(event) })
+}
+
+fn to_status(err: RewardError) -> Status {
+    body_to_status(&err.body())
+}
+
+/// Carries the JSON error code in the `x-error-code` metadata so gRPC clients
+/// can classify failures the same way HTTP clients do.
+fn body_to_status(body: &ErrorBody) -> Status {
+    let code = match body.code.as_str() {
+        "invalid_request" | "payload_too_large" => tonic::Code::InvalidArgument,
+        "unauthorized" => tonic::Code::Unauthenticated,
+        "forbidden" => tonic::Code::PermissionDenied,
+        "rate_limited" | "quota_exhausted" => tonic::Code::ResourceExhausted,
+        "not_found" => tonic::Code::NotFound,
+        "job_not_ready" => tonic::Code::FailedPrecondition,
+        "internal" => tonic::Code::Internal,
+        // upstream LLM / search failures
+        _ if body.retryable => tonic::Code::Unavailable,
+        _ => tonic::Code::FailedPrecondition,
+    };
+    let mut status = Status::new(code, body.message.clone());
+    if let Ok(value) = body.code.parse() {
+        status.metadata_mut().insert("x-error-code", value);
+    }
+    status
+}
+
+fn from_proto_request(req: proto::RewardRequest) -> (RewardRequest, bool) {
+    let advantage = req.advantage.map(|a| AdvantageConfig {
+        mode: match proto::AdvantageMode::try_from(a.mode).unwrap_or_default() {
+            proto::AdvantageMode::MeanCentered => AdvantageMode::MeanCentered,
+            proto::AdvantageMode::ZScore => AdvantageMode::ZScore,
+            proto::AdvantageMode::Rank => AdvantageMode::Rank,
+        },
+        clip: a.clip,
+        epsilon: a.epsilon.unwrap_or(AdvantageConfig::default().epsilon),
+    });
+    let request = RewardRequest {
+        group_id: req.group_id,
+        k_median: req.k_median as usize,
+        binary: req.binary.unwrap_or(true),
+        completions: req
+            .completions
+            .into_iter()
+            .map(|c| InputRecord {
+                question: c.question,
+                response: c.response,
+                model: c.model,
+                prompt_source: c.prompt_source,
+            })
+            .collect(),
+        advantage,
+    };
+    (request, req.include_details)
+}
+
+fn to_proto_response(resp: RewardResponse) -> proto::RewardResponse {
+    proto::RewardResponse {
+        rewards: resp.rewards,
+        advantages: resp.advantages.unwrap_or_default(),
+        details: resp.details.unwrap_or_default().into_iter().map(to_proto_detail).collect(),
+        usage: resp.usage.map(|u| proto::RunUsage {
+            scored: u.scored as u32,
+            skipped: u.skipped as u32,
+            extraction: Some(to_proto_usage(u.extraction)),
+            verification: Some(to_proto_usage(u.verification)),
+            retrieval: Some(to_proto_search(u.retrieval)),
+            cost_usd: u.cost_usd,
+        }),
+    }
+}
+
+fn to_proto_completion(c: CompletionReward) -> proto::CompletionReward {
+    proto::CompletionReward {
+        index: c.index as u32,
+        reward: c.reward,
+        detail: c.detail.map(to_proto_detail),
+    }
+}
+
+fn to_proto_detail(d: RewardDetail) -> proto::RewardDetail {
+    proto::RewardDetail {
+        supported: d.supported as u32,
+        total: d.total as u32,
+        precision: d.precision,
+        recall: d.recall,
+        f1: d.f1,
+        shaping: d.shaping.map(to_proto_shaping),
+        usage: d.usage.map(|u| proto::RecordUsage {
+            extraction: Some(to_proto_usage(u.extraction)),
+            verification: u.verification.map(to_proto_usage),
+            retrieval: u.retrieval.map(to_proto_search),
+            cost_usd: u.cost_usd,
+        }),
+        budget_exhausted: d.budget_exhausted,
+    }
+}
+
+fn to_proto_usage(u: StageUsage) -> proto::StageUsage {
+    proto::StageUsage {
+        calls: u.calls,
+        cached_calls: u.cached_calls,
+        prompt_tokens: u.prompt_tokens,
+        completion_tokens: u.completion_tokens,
+        cost_usd: u.cost_usd,
+    }
+}
+
+fn to_proto_search(u: SearchUsage) -> proto::SearchUsage {
+    proto::SearchUsage { searches: u.searches, cached_searches: u.cached_searches, cost_usd: u.cost_usd }
+}
+
+fn to_proto_shaping(s: ShapingBreakdown) -> proto::ShapingBreakdown {
+    let component = |c: ShapingComponent| proto::ShapingComponent { flagged: c.flagged as u32, penalty: c.penalty };
+    proto::ShapingBreakdown {
+        duplicate: Some(component(s.duplicate)),
+        off_topic: Some(component(s.off_topic)),
+        density: Some(component(s.density)),
+        effective_supported: s.effective_supported,
+        precision: s.precision,
+        recall: s.recall,
+        f1: s.f1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::reward_service_server::RewardService;
+    use super::*;
+    use crate::test_support::mk_engine;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    fn mk_request(include_details: bool) -> proto::RewardRequest {
+        proto::RewardRequest {
+            group_id: "g0".to_string(),
+            k_median: 4,
+            binary: None,
+            completions: vec![
+                proto::InputRecord { question: Some("Q?".into()), response: "A.".into(), model: None, prompt_source: None },
+                proto::InputRecord { question: Some("Q?".into()), response: "B.".into(), model: None, prompt_source: None },
+            ],
+            advantage: Some(proto::AdvantageConfig { mode: proto::AdvantageMode::ZScore as i32, clip: None, epsilon: None }),
+            include_details,
+        }
+    }
+
+    fn mk_service() -> RewardGrpcService {
+        RewardGrpcService::new(Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO)))
+    }
+
+    #[tokio::test]
+    async fn score_group_mirrors_json_response() {
+        let resp = mk_service().score_group(Request::new(mk_request(true))).await.unwrap().into_inner();
+
+        assert_eq!(resp.rewards.len(), 2);
+        // 1 supported claim, K=4 -> F1 = 0.4
+        assert!((resp.rewards[0] - 0.4).abs() < 1e-4);
+        assert_eq!(resp.advantages.len(), 2);
+        assert_eq!(resp.details.len(), 2);
+        assert_eq!(resp.details[0].supported, 1);
+    }
+
+    #[tokio::test]
+    async fn score_group_stream_ends_with_summary() {
+        let stream = mk_service().score_group_stream(Request::new(mk_request(false))).await.unwrap().into_inner();
+        let events = stream.collect::<Vec<_>>().await;
+
+        assert_eq!(events.len(), 3);
+        for event in &events[..2] {
+            assert!(matches!(
+                event.as_ref().unwrap().event,
+                Some(proto::reward_stream_event::Event::Completion(_))
+            ));
+        }
+        match &events[2].as_ref().unwrap().event {
+            Some(proto::reward_stream_event::Event::Summary(summary)) => assert_eq!(summary.rewards.len(), 2),
+            other => panic!("expected summary, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn score_group_checks_authorization_metadata() {
+        let auth = ApiKeyAuth::new(&[crate::auth::ApiKeyEntry {
+            key: "secret".to_string(),
+            tenant: "team-a".to_string(),
+            qps: None,
+            daily_claim_quota: None,
+            admin: false,
+        }]);
+        let service = mk_service().with_auth(Arc::new(auth));
+
+        let status = service.score_group(Request::new(mk_request(false))).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        assert_eq!(status.metadata().get("x-error-code").unwrap(), "unauthorized");
+
+        let mut request = Request::new(mk_request(false));
+        request.metadata_mut().insert("authorization", "Bearer secret".parse().unwrap());
+        assert_eq!(service.score_group(request).await.unwrap().into_inner().rewards.len(), 2);
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::{bail, Context, Result};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+use veriscore_llm::cache::LlmCache;
+use veriscore_llm::{AnthropicLlm, OpenAiCompatibleLlm, TeiNliModel};
+use veriscore_web::cache::WebCache;
+use veriscore_web::serper::Serper;
+
+use crate::jobs::JobStore;
+
+/// A cheap check that one dependency of the reward server is usable.
+#[async_trait::async_trait]
+pub trait HealthProbe: Send + Sync {
+    async fn probe(&self) -> Result<()>;
+}
+
+/// Lists the backend's models and checks the configured one is served.
+#[async_trait::async_trait]
+impl HealthProbe for OpenAiCompatibleLlm {
+    async fn probe(&self) -> Result<()> {
+        let models = self.list_models().await?;
+        if !models.iter().any(|m| m == self.model()) {
+            bail!("model {:?} is not served (available: {})", self.model(), models.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// Looks the configured model up by id or alias.
+#[async_trait::async_trait]
+impl HealthProbe for AnthropicLlm {
+    async fn probe(&self) -> Result<()> {
+        self.retrieve_model().await.with_context(|| format!("model {:?} is not available", self.model()))?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for TeiNliModel {
+    async fn probe(&self) -> Result<()> {
+        self.health().await
+    }
+}
+
+/// Runs a real search, so wrap it in [`CachedProbe`] to avoid spending credits.
+#[async_trait::async_trait]
+impl HealthProbe for Serper {
+    async fn probe(&self) -> Result<()> {
+        self.search_impl("veriscore readiness probe").await.map(|_| ())
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for LlmCache {
+    async fn probe(&self) -> Result<()> {
+        LlmCache::probe(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for WebCache {
+    async fn probe(&self) -> Result<()> {
+        WebCache::probe(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for JobStore {
+    async fn probe(&self) -> Result<()> {
+        JobStore::probe(self)
+    }
+}
+
+/// Passes when any of several interchangeable backends passes, e.g. the
+/// endpoints behind a `RoutingLlm`.
+pub struct AnyProbe {
+    probes: Vec<(String, Arc<dyn HealthProbe>)>,
+}
+
+impl AnyProbe {
+    pub fn new(probes: Vec<(String, Arc<dyn HealthProbe>)>) -> Self {
+        Self { probes }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for AnyProbe {
+    async fn probe(&self) -> Result<()> {
+        let outcomes = join_all(self.probes.iter().map(|(_, probe)| probe.probe())).await;
+        if outcomes.iter().any(Result::is_ok) {
+            return Ok(());
+        }
+        let errors = self.probes.iter().zip(outcomes).filter_map(|((name, _), outcome)| outcome.err().map(|err| format!("{name}: {err:#}")));
+        bail!("no endpoint is healthy ({})", errors.collect::<Vec<_>>().join("; "))
+    }
+}
+
+/// Reuses the last outcome of an expensive probe for `ttl`.
+pub struct CachedProbe {
+    inner: Arc<dyn HealthProbe>,
+    ttl: Duration,
+    last: Mutex<Option<(Instant, Result<(), String>)>>,
+}
+
+impl CachedProbe {
+    pub fn new(inner: Arc<dyn HealthProbe>, ttl: Duration) -> Self {
+        Self { inner, ttl, last: Mutex::new(None) }
+    }
+
+    fn cached(&self) -> Option<Result<(), String>> {
+        let last = self.last.lock().expect("probe cache poisoned");
+        last.as_ref().filter(|(at, _)| at.elapsed() < self.ttl).map(|(_, outcome)| outcome.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for CachedProbe {
+    async fn probe(&self) -> Result<()> {
+        let outcome = match self.cached() {
+            Some(outcome) => outcome,
+            None => {
+                let outcome = self.inner.probe().await.map_err(|err| err.to_string());
+                *self.last.lock().expect("probe cache poisoned") = Some((Instant::now(), outcome.clone()));
+                outcome
+            }
+        };
+        outcome.map_err(anyhow::Error::msg)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub ok: bool,
+    pub latency_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    /// Set once the server has started shutting down.
+    pub draining: bool,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Runs every registered probe concurrently, each bounded by `timeout`.
+pub struct HealthChecker {
+    probes: Vec<(String, Arc<dyn HealthProbe>)>,
+    timeout: Duration,
+}
+
+impl HealthChecker {
+    pub fn new(timeout: Duration) -> Self {
+        Self { probes: Vec::new(), timeout }
+    }
+
+    pub fn with_probe(mut self, name: impl Into<String>, probe: Arc<dyn HealthProbe>) -> Self {
+        self.probes.push((name.into(), probe));
+        self
+    }
+
+    pub async fn check(&self) -> Vec<DependencyStatus> {
+        join_all(self.probes.iter().map(|(name, probe)| async move {
+            let started = Instant::now();
+            let outcome = match tokio::time::timeout(self.timeout, probe.probe()).await {
+                Ok(result) => result.map_err(|err| format!("{err:#}")),
+                Err(_) => Err(format!("timed out after {:?}", self.timeout)),
+            };
+            DependencyStatus {
+                name: name.clone(),
+                ok: outcome.is_ok(),
+                latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+                error: outcome.err(),
+            }
+        }))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FakeProbe {
+        calls: AtomicUsize,
+        fail: bool,
+        delay: Duration,
+    }
+
+    impl FakeProbe {
+        fn new(fail: bool, delay: Duration) -> Arc<Self> {
+            Arc::new(Self { calls: AtomicUsize::new(0), fail, delay })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HealthProbe for FakeProbe {
+        async fn probe(&self) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            if self.fail {
+                bail!("backend down");
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn check_reports_each_dependency() {
+        let checker = HealthChecker::new(Duration::from_millis(50))
+            .with_probe("extractor", FakeProbe::new(false, Duration::ZERO))
+            .with_probe("serper", FakeProbe::new(true, Duration::ZERO))
+            .with_probe("verifier", FakeProbe::new(false, Duration::from_secs(5)));
+
+        let statuses = checker.check().await;
+
+        assert_eq!(statuses.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["extractor", "serper", "verifier"]);
+        assert!(statuses[0].ok && statuses[0].error.is_none());
+        assert_eq!(statuses[1].error.as_deref(), Some("backend down"));
+        assert!(!statuses[2].ok);
+        assert!(statuses[2].error.as_deref().unwrap().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn cached_probe_reuses_outcome_within_ttl() {
+        let inner = FakeProbe::new(true, Duration::ZERO);
+        let cached = CachedProbe::new(inner.clone(), Duration::from_secs(60));
+
+        assert!(cached.probe().await.is_err());
+        assert!(cached.probe().await.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        let expiring = CachedProbe::new(inner.clone(), Duration::ZERO);
+        expiring.probe().await.unwrap_err();
+        expiring.probe().await.unwrap_err();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn any_probe_needs_one_healthy_endpoint() {
+        let (up, down) = (FakeProbe::new(false, Duration::ZERO), FakeProbe::new(true, Duration::ZERO));
+        let mixed = AnyProbe::new(vec![("replica-0".into(), down.clone()), ("replica-1".into(), up)]);
+        assert!(mixed.probe().await.is_ok());
+
+        let err = AnyProbe::new(vec![("replica-0".into(), down.clone()), ("hosted".into(), down)]).probe().await.unwrap_err();
+        assert_eq!(err.to_string(), "no endpoint is healthy (replica-0: backend down; hosted: backend down)");
+    }
+
+    #[tokio::test]
+    async fn sqlite_probes_detect_closed_caches() {
+        let cache = LlmCache::open(":memory:").unwrap();
+        assert!(HealthProbe::probe(&cache).await.is_ok());
+        cache.close().unwrap();
+        assert!(HealthProbe::probe(&cache).await.is_err());
+    }
+}
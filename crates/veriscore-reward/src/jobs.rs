@@ -0,0 +1,428 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::reward_engine::{RewardEngine, UsageSink};
+use crate::error::RewardError;
+use crate::reward_types::{MultiGroupResponse, RewardRequest, Validate};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "queued" => JobStatus::Queued,
+            "running" => JobStatus::Running,
+            "succeeded" => JobStatus::Succeeded,
+            "failed" => JobStatus::Failed,
+            "cancelled" => JobStatus::Cancelled,
+            other => anyhow::bail!("unknown job status {other:?}"),
+        })
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct JobRequest {
+    #[schema(min_items = 1)]
+    pub groups: Vec<RewardRequest>,
+    #[serde(default)]
+    pub include_details: bool,
+}
+
+impl Validate for JobRequest {
+    fn validate(&self) -> Result<(), RewardError> {
+        if self.groups.is_empty() {
+            return Err(RewardError::InvalidRequest("groups must not be empty".to_string()));
+        }
+        self.groups.iter().try_for_each(Validate::validate)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub groups: usize,
+    pub completions: usize,
+    /// Unix seconds.
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Tenant that submitted the job; `None` when auth was off.
+    #[serde(skip)]
+    pub tenant: Option<String>,
+}
+
+/// SQLite-backed job table; survives server restarts.
+#[derive(Clone)]
+pub struct JobStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl JobStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS reward_jobs (
+                job_id TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                request_json TEXT NOT NULL,
+                result_json TEXT,
+                error TEXT,
+                groups INTEGER NOT NULL,
+                completions INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                tenant TEXT
+            );
+            "#,
+        )?;
+        // job tables created before jobs were scoped to tenants
+        let has_tenant: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('reward_jobs') WHERE name = 'tenant'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_tenant {
+            conn.execute_batch("ALTER TABLE reward_jobs ADD COLUMN tenant TEXT;")?;
+        }
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    pub fn insert(&self, job_id: &str, request: &JobRequest, tenant: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        let now = unix_now();
+        conn.execute(
+            "INSERT INTO reward_jobs(job_id, status, request_json, groups, completions, created_at, updated_at, tenant)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)",
+            params![
+                job_id,
+                JobStatus::Queued.as_str(),
+                serde_json::to_string(request)?,
+                request.groups.len() as i64,
+                request.groups.iter().map(|g| g.completions.len()).sum::<usize>() as i64,
+                now as i64,
+                tenant,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Moves a job to `status` unless it already finished; returns whether it changed.
+    pub fn transition(&self, job_id: &str, status: JobStatus, result_json: Option<&str>, error: Option<&str>) -> Result<bool> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        let changed = conn.execute(
+            "UPDATE reward_jobs SET status = ?2, result_json = COALESCE(?3, result_json), error = ?4, updated_at = ?5
+             WHERE job_id = ?1 AND status IN ('queued', 'running')",
+            params![job_id, status.as_str(), result_json, error, unix_now() as i64],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get(&self, job_id: &str) -> Result<Option<JobInfo>> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        conn.query_row(
+            "SELECT job_id, status, groups, completions, created_at, updated_at, error, tenant FROM reward_jobs WHERE job_id = ?1",
+            params![job_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                ))
+            },
+        )
+        .optional()?
+        .map(|(job_id, status, groups, completions, created_at, updated_at, error, tenant)| {
+            Ok(JobInfo {
+                job_id,
+                status: JobStatus::parse(&status)?,
+                groups: groups as usize,
+                completions: completions as usize,
+                created_at: created_at as u64,
+                updated_at: updated_at as u64,
+                error,
+                tenant,
+            })
+        })
+        .transpose()
+    }
+
+    pub fn result(&self, job_id: &str) -> Result<Option<MultiGroupResponse>> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        let raw: Option<Option<String>> = conn
+            .query_row("SELECT result_json FROM reward_jobs WHERE job_id = ?1", params![job_id], |row| row.get(0))
+            .optional()?;
+        raw.flatten()
+            .map(|json| serde_json::from_str(&json).context("corrupt job result"))
+            .transpose()
+    }
+
+    pub fn request(&self, job_id: &str) -> Result<Option<JobRequest>> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        let raw: Option<String> = conn
+            .query_row("SELECT request_json FROM reward_jobs WHERE job_id = ?1", params![job_id], |row| row.get(0))
+            .optional()?;
+        raw.map(|json| serde_json::from_str(&json).context("corrupt job request")).transpose()
+    }
+
+    /// Checks the database can take a write lock.
+    pub fn probe(&self) -> Result<()> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")?;
+        Ok(())
+    }
+
+    /// Jobs that were queued or running when the process last stopped, oldest first.
+    pub fn unfinished(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().expect("job store poisoned");
+        let mut stmt = conn.prepare(
+            "SELECT job_id FROM reward_jobs WHERE status IN ('queued', 'running') ORDER BY created_at, job_id",
+        )?;
+        let ids = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(ids)
+    }
+}
+
+/// Runs reward jobs in the background on the shared `RewardEngine`.
+pub struct JobManager {
+    store: JobStore,
+    engine: Arc<RewardEngine>,
+    permits: Arc<Semaphore>,
+    running: Mutex<HashMap<String, AbortHandle>>,
+}
+
+impl JobManager {
+    pub fn new(store: JobStore, engine: Arc<RewardEngine>, max_concurrent_jobs: usize) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            engine,
+            permits: Arc::new(Semaphore::new(max_concurrent_jobs.max(1))),
+            running: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Re-queues jobs left unfinished by a previous process. `usage_of` maps a
+    /// job's stored tenant to the sink its claims are charged to, as `submit`
+    /// was given; tenants it does not know are not charged.
+    pub fn resume(self: &Arc<Self>, usage_of: impl Fn(&str) -> Option<Arc<dyn UsageSink>>) -> Result<usize> {
+        let ids = self.store.unfinished()?;
+        for id in &ids {
+            let tenant = self.store.get(id)?.and_then(|info| info.tenant);
+            self.store.transition(id, JobStatus::Queued, None, None)?;
+            let usage = tenant.as_deref().and_then(&usage_of);
+            self.spawn(id.clone(), self.engine_for(usage));
+        }
+        if !ids.is_empty() {
+            info!(jobs = ids.len(), "resumed unfinished reward jobs");
+        }
+        Ok(ids.len())
+    }
+
+    /// Queues a job owned by `tenant`; claims it scores are reported to `usage` when given.
+    pub fn submit(
+        self: &Arc<Self>,
+        request: JobRequest,
+        tenant: Option<&str>,
+        usage: Option<Arc<dyn UsageSink>>,
+    ) -> Result<JobInfo> {
+        request.validate()?;
+        let job_id = new_job_id();
+        self.store.insert(&job_id, &request, tenant)?;
+        self.spawn(job_id.clone(), self.engine_for(usage));
+        self.store.get(&job_id)?.context("job vanished after insert")
+    }
+
+    fn engine_for(&self, usage: Option<Arc<dyn UsageSink>>) -> Arc<RewardEngine> {
+        match usage {
+            Some(sink) => Arc::new(self.engine.with_usage_sink(sink)),
+            None => self.engine.clone(),
+        }
+    }
+
+    pub fn store(&self) -> &JobStore {
+        &self.store
+    }
+
+    pub fn status(&self, job_id: &str) -> Result<Option<JobInfo>> {
+        self.store.get(job_id)
+    }
+
+    pub fn result(&self, job_id: &str) -> Result<Option<MultiGroupResponse>> {
+        self.store.result(job_id)
+    }
+
+    pub fn cancel(&self, job_id: &str) -> Result<Option<JobInfo>> {
+        if self.store.transition(job_id, JobStatus::Cancelled, None, None)? {
+            if let Some(handle) = self.running.lock().expect("job registry poisoned").remove(job_id) {
+                handle.abort();
+            }
+            info!(job_id, "cancelled reward job");
+        }
+        self.store.get(job_id)
+    }
+
+    fn spawn(self: &Arc<Self>, job_id: String, engine: Arc<RewardEngine>) {
+        let this = self.clone();
+        let id = job_id.clone();
+        // hold the registry lock so the task can't deregister before it is registered
+        let mut running = self.running.lock().expect("job registry poisoned");
+        let handle = tokio::spawn(async move {
+            let _permit = this.permits.clone().acquire_owned().await.expect("job semaphore closed");
+            if let Err(err) = this.run(&id, &engine).await {
+                warn!(job_id = %id, error = %err, "reward job failed");
+                let _ = this.store.transition(&id, JobStatus::Failed, None, Some(&err.to_string()));
+            }
+            this.running.lock().expect("job registry poisoned").remove(&id);
+        });
+        running.insert(job_id, handle.abort_handle());
+    }
+
+    async fn run(&self, job_id: &str, engine: &RewardEngine) -> Result<()> {
+        let request = self.store.request(job_id)?.context("job not found")?;
+        if !self.store.transition(job_id, JobStatus::Running, None, None)? {
+            // cancelled while queued
+            return Ok(());
+        }
+        let response = engine.score_multi(request.groups, request.include_details).await?;
+        self.store.transition(job_id, JobStatus::Succeeded, Some(&serde_json::to_string(&response)?), None)?;
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn new_job_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let seed = format!("{}-{}-{}", nanos, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("job-{:x}", md5::compute(seed.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{mk_engine, mk_group};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    async fn wait_terminal(manager: &JobManager, job_id: &str) -> JobInfo {
+        for _ in 0..200 {
+            let info = manager.status(job_id).unwrap().unwrap();
+            if info.status.is_terminal() {
+                return info;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {job_id} did not finish");
+    }
+
+    #[tokio::test]
+    async fn submitted_job_runs_to_completion() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path().join("jobs.sqlite")).unwrap();
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO));
+        let manager = JobManager::new(store, engine, 1);
+
+        let info = manager
+            .submit(JobRequest { groups: vec![mk_group("g0", 4, &["A.", "B."])], include_details: false }, None, None)
+            .unwrap();
+        assert_eq!(info.completions, 2);
+
+        let done = wait_terminal(&manager, &info.job_id).await;
+        assert_eq!(done.status, JobStatus::Succeeded);
+        let result = manager.result(&info.job_id).unwrap().unwrap();
+        assert_eq!(result.groups["g0"].rewards.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_running_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path().join("jobs.sqlite")).unwrap();
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::from_secs(30)));
+        let manager = JobManager::new(store, engine, 1);
+
+        let info = manager
+            .submit(JobRequest { groups: vec![mk_group("g0", 4, &["A."])], include_details: false }, None, None)
+            .unwrap();
+        let cancelled = manager.cancel(&info.job_id).unwrap().unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert!(manager.result(&info.job_id).unwrap().is_none());
+        // a finished job can't be cancelled again or overwritten
+        assert_eq!(manager.cancel(&info.job_id).unwrap().unwrap().status, JobStatus::Cancelled);
+    }
+
+    #[derive(Default)]
+    struct ClaimCounter(AtomicUsize);
+
+    impl UsageSink for ClaimCounter {
+        fn record_claims(&self, claims: usize) {
+            self.0.fetch_add(claims, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn unfinished_jobs_resume_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.sqlite");
+        {
+            let store = JobStore::open(&path).unwrap();
+            let request = JobRequest { groups: vec![mk_group("g0", 4, &["A."])], include_details: true };
+            store.insert("job-left-over", &request, Some("team-a")).unwrap();
+            store.transition("job-left-over", JobStatus::Running, None, None).unwrap();
+        }
+
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO));
+        let manager = JobManager::new(JobStore::open(&path).unwrap(), engine, 1);
+        let charged = Arc::new(ClaimCounter::default());
+        let sink = charged.clone();
+        let resumed = manager.resume(|tenant| (tenant == "team-a").then(|| sink.clone() as Arc<dyn UsageSink>)).unwrap();
+        assert_eq!(resumed, 1);
+
+        let done = wait_terminal(&manager, "job-left-over").await;
+        assert_eq!(done.status, JobStatus::Succeeded);
+        assert!(manager.result("job-left-over").unwrap().unwrap().groups["g0"].details.is_some());
+        // work done after the restart is still charged to the job's tenant
+        assert_eq!(charged.0.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,7 +1,28 @@
+pub mod advantage;
+pub mod auth;
+pub mod error;
+pub mod grpc;
+pub mod health;
+pub mod jobs;
 pub mod reward_api;
 pub mod reward_engine;
+pub mod reward_shaping;
 pub mod reward_types;
+pub mod settings;
 
+#[cfg(test)]
+mod test_support;
+
+pub use advantage::{compute_advantages, AdvantageConfig, AdvantageMode};
+pub use auth::{ApiKeyAuth, RequestLimits};
+pub use error::{ErrorBody, RewardError};
+pub use grpc::RewardGrpcService;
+pub use health::{AnyProbe, CachedProbe, HealthChecker, HealthProbe};
+pub use jobs::{JobManager, JobStore};
 pub use reward_api::build_router;
-pub use reward_engine::RewardEngine;
-pub use reward_types::{RewardRequest, RewardResponse};
+pub use reward_engine::{RewardEngine, UsageSink};
+pub use reward_shaping::{RewardShaper, ShapingConfig};
+pub use reward_types::{
+    MultiGroupRequest, MultiGroupResponse, RewardRequest, RewardResponse, RewardStreamEvent, RunUsage,
+};
+pub use settings::{Budget, BudgetAction, ConfigUpdate, LiveSettings, ReloadableConfig};
@@ -1,45 +1,798 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use axum::{extract::{Query, State}, response::IntoResponse, routing::post, Json, Router};
+use axum::{
+    extract::{DefaultBodyLimit, FromRequest, Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi};
+use veriscore_llm::routing::{EndpointStats, RoutingLlm};
 
-use crate::reward_engine::RewardEngine;
-use crate::reward_types::{RewardRequest, RewardResponse};
+use crate::auth::{ApiKeyAuth, AuthError, RequestLimits, Tenant};
+use crate::error::{ErrorResponse, RewardError};
+use crate::health::{HealthChecker, ReadinessReport};
+use crate::jobs::{JobInfo, JobManager, JobRequest};
+use crate::reward_engine::{RewardEngine, UsageSink};
+use crate::reward_types::{
+    MultiGroupRequest, MultiGroupResponse, RewardRequest, RewardResponse, RewardStreamEvent, UsageReport, Validate,
+};
+use crate::settings::{AdminConfigView, BuildInfo, ConfigUpdate, SettingsSnapshot};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct RewardApiQuery {
+    /// Return per-completion `details` alongside the rewards.
     #[serde(default)]
     pub include_details: bool,
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "veriscore reward API", description = "Factuality rewards for GRPO-style training."),
+    paths(
+        healthz, readyz, reward_batch, reward_groups, reward_stream, submit_job, job_status, job_result, cancel_job,
+        get_admin_config, update_admin_config, get_admin_usage, get_admin_routing,
+    ),
+    modifiers(&BearerAuth),
+    security(("api_key" = []))
+)]
+pub struct ApiDoc;
+
+/// Documents the `--api-keys-file` bearer scheme; servers without it ignore the header.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+/// `Json` that reports malformed bodies as structured errors and then checks
+/// the payload against the constraints in the OpenAPI schema.
+pub struct ValidJson<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = RewardError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await.map_err(|rejection| {
+            match rejection.status() {
+                StatusCode::PAYLOAD_TOO_LARGE => RewardError::PayloadTooLarge(rejection.body_text()),
+                _ => RewardError::InvalidRequest(rejection.body_text()),
+            }
+        })?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+/// Backs `/readyz`; cleared when the server starts draining so load balancers
+/// stop routing new work to it while `/healthz` stays up.
+#[derive(Clone)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicBool::new(true)))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.0.store(ready, Ordering::SeqCst);
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone)]
 pub struct RewardApiState {
     pub engine: Arc<RewardEngine>,
+    /// Background job API; the `/jobs` routes are only mounted when set.
+    pub jobs: Option<Arc<JobManager>>,
+    /// Bearer-token auth for everything but `/healthz`; open when unset.
+    pub auth: Option<Arc<ApiKeyAuth>>,
+    pub limits: RequestLimits,
+    pub readiness: Readiness,
+    /// Dependency probes run by `/readyz`.
+    pub health: Option<Arc<HealthChecker>>,
+    /// Startup settings echoed by `GET /admin/config`.
+    pub startup_config: serde_json::Value,
+    /// Multi-endpoint LLM stages, by stage name, reported by `GET /admin/routing`.
+    pub routing: Vec<(String, Arc<RoutingLlm>)>,
+}
+
+impl RewardApiState {
+    pub fn new(engine: Arc<RewardEngine>) -> Self {
+        Self {
+            engine,
+            jobs: None,
+            auth: None,
+            limits: RequestLimits::default(),
+            readiness: Readiness::new(),
+            health: None,
+            startup_config: serde_json::Value::Null,
+            routing: Vec::new(),
+        }
+    }
+
+    pub fn with_jobs(mut self, jobs: Arc<JobManager>) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    pub fn with_auth(mut self, auth: Arc<ApiKeyAuth>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: RequestLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    pub fn with_health(mut self, health: Arc<HealthChecker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    pub fn with_startup_config(mut self, startup_config: serde_json::Value) -> Self {
+        self.startup_config = startup_config;
+        self
+    }
+
+    pub fn with_routing(mut self, stage: impl Into<String>, llm: Arc<RoutingLlm>) -> Self {
+        self.routing.push((stage.into(), llm));
+        self
+    }
 }
 
 pub fn build_router(state: RewardApiState) -> Router {
+    let mut api = Router::new()
+        .route("/grpo/reward_batch", post(reward_batch))
+        .route("/grpo/reward_groups", post(reward_groups))
+        .route("/grpo/reward_stream", post(reward_stream));
+    if state.jobs.is_some() {
+        api = api
+            .route("/jobs", post(submit_job))
+            .route("/jobs/:job_id", get(job_status).delete(cancel_job))
+            .route("/jobs/:job_id/result", get(job_result));
+    }
+    if let Some(auth) = &state.auth {
+        // admin routes need an authenticated caller, so they only exist with auth on
+        api = api
+            .route("/admin/config", get(get_admin_config).put(update_admin_config))
+            .route("/admin/usage", get(get_admin_usage))
+            .route("/admin/routing", get(get_admin_routing))
+            .route_layer(middleware::from_fn_with_state(auth.clone(), require_api_key));
+    }
+
     Router::new()
         .route("/healthz", post(healthz).get(healthz))
-        .route("/grpo/reward_batch", post(reward_batch))
+        .route("/readyz", get(readyz))
+        .route("/openapi.json", get(openapi_json))
+        .merge(api)
+        .layer(DefaultBodyLimit::max(state.limits.max_body_bytes))
         .with_state(state)
 }
 
+#[utoipa::path(get, path = "/healthz", security(()), responses((status = 200, description = "Server is up")))]
 async fn healthz() -> impl IntoResponse {
     Json(serde_json::json!({"ok": true}))
 }
 
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    security(()),
+    responses(
+        (status = 200, description = "Accepting work", body = ReadinessReport),
+        (status = 503, description = "Draining or a dependency probe failed", body = ReadinessReport),
+    )
+)]
+async fn readyz(State(state): State<RewardApiState>) -> (StatusCode, Json<ReadinessReport>) {
+    let draining = !state.readiness.is_ready();
+    // no point probing backends once the load balancer should be routing away
+    let dependencies = match (&state.health, draining) {
+        (Some(health), false) => health.check().await,
+        _ => Vec::new(),
+    };
+    let ready = !draining && dependencies.iter().all(|d| d.ok);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(ReadinessReport { ready, draining, dependencies }))
+}
+
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn require_api_key(State(auth): State<Arc<ApiKeyAuth>>, mut request: Request, next: Next) -> Response {
+    let authorization = request.headers().get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    match auth.authorize(authorization) {
+        Ok(tenant) => {
+            request.extensions_mut().insert(tenant);
+            next.run(request).await
+        }
+        Err(err) => RewardError::from(err).into_response(),
+    }
+}
+
+/// The shared engine, charging claims to the caller's tenant when auth is on.
+fn engine_for(state: &RewardApiState, tenant: Option<Extension<Arc<Tenant>>>) -> Arc<RewardEngine> {
+    match tenant {
+        Some(Extension(tenant)) => Arc::new(state.engine.with_usage_sink(tenant)),
+        None => state.engine.clone(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/grpo/reward_batch",
+    params(RewardApiQuery),
+    request_body = RewardRequest,
+    responses(
+        (status = 200, body = RewardResponse),
+        (status = "4XX", description = "Invalid, oversized, unauthorized or throttled request", body = ErrorResponse),
+        (status = "5XX", description = "Upstream LLM/search failure or internal error", body = ErrorResponse),
+    )
+)]
 pub async fn reward_batch(
     State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
     Query(query): Query<RewardApiQuery>,
-    Json(request): Json<RewardRequest>,
-) -> Result<Json<RewardResponse>, (axum::http::StatusCode, String)> {
-    state.engine
+    ValidJson(request): ValidJson<RewardRequest>,
+) -> Result<Json<RewardResponse>, RewardError> {
+    state.limits.check([&request])?;
+    engine_for(&state, tenant)
         .score_batch(request, query.include_details)
         .await
         .map(Json)
-        .map_err(internal_error)
 }
 
-fn internal_error(err: anyhow::Error) -> (axum::http::StatusCode, String) {
-    (axum::http::StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+#[utoipa::path(
+    post,
+    path = "/grpo/reward_groups",
+    params(RewardApiQuery),
+    request_body = MultiGroupRequest,
+    responses(
+        (status = 200, body = MultiGroupResponse),
+        (status = "4XX", body = ErrorResponse),
+        (status = "5XX", body = ErrorResponse),
+    )
+)]
+pub async fn reward_groups(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    Query(query): Query<RewardApiQuery>,
+    ValidJson(request): ValidJson<MultiGroupRequest>,
+) -> Result<Json<MultiGroupResponse>, RewardError> {
+    state.limits.check(&request.groups)?;
+    engine_for(&state, tenant)
+        .score_multi(request.groups, query.include_details)
+        .await
+        .map(Json)
+}
+
+/// Server-Sent Events variant of `reward_batch`: one `completion` event per
+/// finished completion, then a `summary` (or `error`) event.
+#[utoipa::path(
+    post,
+    path = "/grpo/reward_stream",
+    params(RewardApiQuery),
+    request_body = RewardRequest,
+    responses(
+        (status = 200, description = "SSE stream; each event's data is the payload of one variant",
+            content_type = "text/event-stream", body = RewardStreamEvent),
+        (status = "4XX", body = ErrorResponse),
+    )
+)]
+pub async fn reward_stream(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    Query(query): Query<RewardApiQuery>,
+    ValidJson(request): ValidJson<RewardRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, RewardError> {
+    state.limits.check([&request])?;
+    let events = engine_for(&state, tenant).score_stream(request, query.include_details).map(|event| {
+        let sse = Event::default().event(event.name());
+        match event {
+            RewardStreamEvent::Completion(c) => sse.json_data(c),
+            RewardStreamEvent::Summary(summary) => sse.json_data(summary),
+            RewardStreamEvent::Error(body) => sse.json_data(body),
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn job_manager(state: &RewardApiState) -> Result<&Arc<JobManager>, RewardError> {
+    state.jobs.as_ref().ok_or(RewardError::JobsDisabled)
+}
+
+fn job_not_found(job_id: &str) -> RewardError {
+    RewardError::JobNotFound(job_id.to_string())
+}
+
+/// The job if the caller may see it: tenants only see their own jobs, admins
+/// and unauthenticated servers see all. Other tenants' jobs look missing.
+fn visible_job(jobs: &JobManager, tenant: &Option<Extension<Arc<Tenant>>>, job_id: &str) -> Result<JobInfo, RewardError> {
+    let info = jobs.status(job_id)?.ok_or_else(|| job_not_found(job_id))?;
+    match tenant {
+        Some(Extension(tenant)) if !tenant.admin && info.tenant.as_deref() != Some(tenant.name.as_str()) => {
+            Err(job_not_found(job_id))
+        }
+        _ => Ok(info),
+    }
+}
+
+/// Jobs are meant for large sweeps, so only the per-completion length limit applies.
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    request_body = JobRequest,
+    responses((status = 202, body = JobInfo), (status = "4XX", body = ErrorResponse))
+)]
+pub async fn submit_job(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    ValidJson(request): ValidJson<JobRequest>,
+) -> Result<(StatusCode, Json<JobInfo>), RewardError> {
+    state.limits.check_response_lengths(&request.groups)?;
+    let owner = tenant.as_ref().map(|Extension(t)| t.name.clone());
+    let usage = tenant.map(|Extension(t)| t as Arc<dyn UsageSink>);
+    job_manager(&state)?
+        .submit(request, owner.as_deref(), usage)
+        .map(|info| (StatusCode::ACCEPTED, Json(info)))
+        .map_err(RewardError::from_anyhow)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    params(("job_id" = String, Path)),
+    responses((status = 200, body = JobInfo), (status = 404, body = ErrorResponse))
+)]
+pub async fn job_status(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobInfo>, RewardError> {
+    visible_job(job_manager(&state)?, &tenant, &job_id).map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}/result",
+    params(("job_id" = String, Path)),
+    responses(
+        (status = 200, body = MultiGroupResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Job has not succeeded", body = ErrorResponse),
+    )
+)]
+pub async fn job_result(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<MultiGroupResponse>, RewardError> {
+    let jobs = job_manager(&state)?;
+    let info = visible_job(jobs, &tenant, &job_id)?;
+    match jobs.result(&job_id)? {
+        Some(result) => Ok(Json(result)),
+        None => Err(RewardError::JobNotReady { job_id, status: info.status.as_str().to_string() }),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/jobs/{job_id}",
+    params(("job_id" = String, Path)),
+    responses((status = 200, body = JobInfo), (status = 404, body = ErrorResponse))
+)]
+pub async fn cancel_job(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobInfo>, RewardError> {
+    let jobs = job_manager(&state)?;
+    visible_job(jobs, &tenant, &job_id)?;
+    jobs.cancel(&job_id)?.map(Json).ok_or_else(|| job_not_found(&job_id))
+}
+
+fn require_admin(tenant: Option<Extension<Arc<Tenant>>>) -> Result<Arc<Tenant>, RewardError> {
+    let Extension(tenant) = tenant.ok_or(AuthError::MissingToken)?;
+    tenant.require_admin()?;
+    Ok(tenant)
+}
+
+fn admin_view(state: &RewardApiState, snapshot: &SettingsSnapshot) -> AdminConfigView {
+    AdminConfigView {
+        build: BuildInfo::current(),
+        startup: state.startup_config.clone(),
+        version: snapshot.version,
+        reloadable: snapshot.config.clone(),
+    }
+}
+
+/// Effective configuration: build info, startup settings and the reloadable settings.
+#[utoipa::path(
+    get,
+    path = "/admin/config",
+    responses(
+        (status = 200, body = AdminConfigView),
+        (status = "4XX", description = "Missing key or not an admin key", body = ErrorResponse),
+    )
+)]
+pub async fn get_admin_config(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+) -> Result<Json<AdminConfigView>, RewardError> {
+    require_admin(tenant)?;
+    Ok(Json(admin_view(&state, &state.engine.settings().current())))
+}
+
+/// LLM calls and tokens per pipeline stage since the server started.
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    responses(
+        (status = 200, body = UsageReport),
+        (status = "4XX", description = "Missing key or not an admin key", body = ErrorResponse),
+    )
+)]
+pub async fn get_admin_usage(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+) -> Result<Json<UsageReport>, RewardError> {
+    require_admin(tenant)?;
+    Ok(Json(state.engine.usage_meter().totals().into()))
+}
+
+/// Per-endpoint routing counters and circuit states of each multi-endpoint stage.
+#[utoipa::path(
+    get,
+    path = "/admin/routing",
+    responses(
+        (status = 200, body = BTreeMap<String, Vec<EndpointStats>>),
+        (status = "4XX", description = "Missing key or not an admin key", body = ErrorResponse),
+    )
+)]
+pub async fn get_admin_routing(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+) -> Result<Json<BTreeMap<String, Vec<EndpointStats>>>, RewardError> {
+    require_admin(tenant)?;
+    Ok(Json(state.routing.iter().map(|(stage, llm)| (stage.clone(), llm.stats())).collect()))
+}
+
+/// Swaps prompt templates, shaping and retrieval settings in one step.
+/// Requests already running finish with the settings they started with.
+#[utoipa::path(
+    put,
+    path = "/admin/config",
+    request_body = ConfigUpdate,
+    responses(
+        (status = 200, body = AdminConfigView),
+        (status = "4XX", description = "Invalid settings, missing key or not an admin key", body = ErrorResponse),
+    )
+)]
+pub async fn update_admin_config(
+    State(state): State<RewardApiState>,
+    tenant: Option<Extension<Arc<Tenant>>>,
+    ValidJson(update): ValidJson<ConfigUpdate>,
+) -> Result<Json<AdminConfigView>, RewardError> {
+    let tenant = require_admin(tenant)?;
+    let (previous, next) = state.engine.settings().update(update)?;
+    let changed = [
+        ("prompts", previous.config.prompts != next.config.prompts),
+        ("shaping", previous.config.shaping != next.config.shaping),
+        ("retrieval", previous.config.retrieval != next.config.retrieval),
+        ("soft_rewards", previous.config.soft_rewards != next.config.soft_rewards),
+        ("budget", previous.config.budget != next.config.budget),
+    ]
+    .into_iter()
+    .filter_map(|(section, changed)| changed.then_some(section))
+    .collect::<Vec<_>>();
+    tracing::info!(
+        tenant = %tenant.name,
+        from_version = previous.version,
+        to_version = next.version,
+        ?changed,
+        config = %serde_json::to_string(&next.config).unwrap_or_default(),
+        "reloaded runtime config"
+    );
+    Ok(Json(admin_view(&state, &next)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::ApiKeyEntry;
+    use crate::jobs::{JobStatus, JobStore};
+    use crate::reward_types::{MAX_GROUP_ID_CHARS, MAX_QUESTION_CHARS, MAX_TAG_CHARS};
+    use crate::test_support::{mk_engine, mk_group, FakeVerifier};
+    use axum::body::Body;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn mk_router(qps: Option<u32>) -> Router {
+        let auth = ApiKeyAuth::new(&[
+            ApiKeyEntry { key: "secret".to_string(), tenant: "team-a".to_string(), qps, daily_claim_quota: None, admin: false },
+            ApiKeyEntry { key: "root".to_string(), tenant: "ops".to_string(), qps: None, daily_claim_quota: None, admin: true },
+        ]);
+        let state = RewardApiState::new(Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO)))
+            .with_auth(Arc::new(auth))
+            .with_limits(RequestLimits { max_body_bytes: 4096, max_completions: 2, max_response_chars: 100 })
+            .with_routing("verifier", Arc::new(RoutingLlm::new(vec![("replica-0".to_string(), Arc::new(FakeVerifier))])));
+        build_router(state)
+    }
+
+    fn post(body: &RewardRequest, key: Option<&str>) -> Request {
+        let mut builder = Request::post("/grpo/reward_batch").header(header::CONTENT_TYPE, "application/json");
+        if let Some(key) = key {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {key}"));
+        }
+        builder.body(Body::from(serde_json::to_vec(body).unwrap())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reward_batch_requires_api_key() {
+        let router = mk_router(None);
+        let group = mk_group("g0", 4, &["A."]);
+
+        let resp = router.clone().oneshot(post(&group, None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().contains_key(header::WWW_AUTHENTICATE));
+
+        let resp = router.clone().oneshot(post(&group, Some("wrong"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = router.oneshot(post(&group, Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn healthz_stays_open() {
+        let resp = mk_router(None)
+            .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn readyz_flips_while_draining() {
+        let readiness = Readiness::new();
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO));
+        let router = build_router(RewardApiState::new(engine).with_readiness(readiness.clone()));
+        let get = || Request::get("/readyz").body(Body::empty()).unwrap();
+
+        assert_eq!(router.clone().oneshot(get()).await.unwrap().status(), StatusCode::OK);
+        readiness.set_ready(false);
+        assert_eq!(router.clone().oneshot(get()).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        let health = router.oneshot(Request::get("/healthz").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn readyz_reports_failing_dependencies() {
+        let cache = Arc::new(veriscore_llm::cache::LlmCache::open(":memory:").unwrap());
+        let health = HealthChecker::new(Duration::from_secs(1)).with_probe("llm_cache", cache.clone());
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO));
+        let router = build_router(RewardApiState::new(engine).with_health(Arc::new(health)));
+        let get = || Request::get("/readyz").body(Body::empty()).unwrap();
+
+        let resp = router.clone().oneshot(get()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        cache.close().unwrap();
+        let resp = router.oneshot(get()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let report: ReadinessReport = serde_json::from_slice(&body).unwrap();
+        assert!(!report.ready && !report.draining);
+        assert_eq!(report.dependencies[0].name, "llm_cache");
+        assert!(report.dependencies[0].error.as_deref().unwrap().contains("closed"));
+    }
+
+    #[tokio::test]
+    async fn oversized_requests_get_413() {
+        let router = mk_router(None);
+
+        let resp = router.clone().oneshot(post(&mk_group("g0", 4, &["A.", "B.", "C."]), Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let huge = "x".repeat(8192);
+        let resp = router.oneshot(post(&mk_group("g0", 4, &[huge.as_str()]), Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_get_structured_400() {
+        let resp = mk_router(None).oneshot(post(&mk_group("g0", 0, &["A."]), Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "invalid_request");
+        assert_eq!(body["error"]["retryable"], false);
+        assert!(body["error"]["message"].as_str().unwrap().contains("k_median"));
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_get_structured_400() {
+        let request = Request::post("/grpo/reward_batch")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::from(r#"{"group_id": "g0", "completions": []}"#))
+            .unwrap();
+        let resp = mk_router(None).oneshot(request).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.error.code, "invalid_request");
+        assert!(body.error.message.contains("k_median"));
+    }
+
+    #[tokio::test]
+    async fn openapi_document_is_public_and_matches_validation() {
+        let resp = mk_router(None)
+            .oneshot(Request::get("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert!(doc["paths"]["/grpo/reward_batch"]["post"].is_object());
+        assert!(doc["paths"]["/jobs/{job_id}/result"]["get"].is_object());
+        let schemas = &doc["components"]["schemas"];
+        let request = &schemas["RewardRequest"]["properties"];
+        assert_eq!(request["k_median"]["minimum"], 1);
+        assert_eq!(request["completions"]["minItems"], 1);
+        assert_eq!(request["group_id"]["maxLength"], MAX_GROUP_ID_CHARS);
+        let record = &schemas["InputRecord"]["properties"];
+        assert_eq!(record["question"]["maxLength"], MAX_QUESTION_CHARS);
+        assert_eq!(record["model"]["maxLength"], MAX_TAG_CHARS);
+        assert_eq!(record["prompt_source"]["maxLength"], MAX_TAG_CHARS);
+        assert!(schemas["ErrorResponse"].is_object());
+    }
+
+    #[tokio::test]
+    async fn admin_config_requires_admin_key_and_swaps_settings() {
+        let router = mk_router(None);
+        let admin = |method: &str, key: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri("/admin/config")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {key}"))
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let resp = router.clone().oneshot(admin("GET", "secret", "")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = router.clone().oneshot(admin("GET", "root", "")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: AdminConfigView = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view.version, 1);
+        assert_eq!(view.build.version, env!("CARGO_PKG_VERSION"));
+
+        let resp = router.clone().oneshot(admin("PUT", "root", r#"{"prompts": {"extraction_user": "oops"}}"#)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let update = r#"{"retrieval": {"top_k": 3, "concurrency": 4}, "shaping": {"density": {"max_claims_per_sentence": 2, "weight": 0.5}}}"#;
+        let resp = router.clone().oneshot(admin("PUT", "root", update)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: AdminConfigView = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view.version, 2);
+        assert_eq!(view.reloadable.retrieval.unwrap().top_k, 3);
+        assert_eq!(view.reloadable.shaping.density.unwrap().max_claims_per_sentence, 2);
+
+        // scoring keeps working on the new settings
+        let resp = router.clone().oneshot(post(&mk_group("g0", 4, &["A."]), Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let usage = Request::get("/admin/usage").header(header::AUTHORIZATION, "Bearer root").body(Body::empty()).unwrap();
+        let resp = router.clone().oneshot(usage).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let report: UsageReport = serde_json::from_slice(&body).unwrap();
+        assert!(report.runs > 0);
+
+        let routing = Request::get("/admin/routing").header(header::AUTHORIZATION, "Bearer root").body(Body::empty()).unwrap();
+        let resp = router.oneshot(routing).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let stats: BTreeMap<String, Vec<EndpointStats>> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["verifier"][0].name, "replica-0");
+    }
+
+    #[tokio::test]
+    async fn jobs_are_scoped_to_the_submitting_tenant() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::from_secs(30)));
+        let jobs = JobManager::new(JobStore::open(dir.path().join("jobs.sqlite")).unwrap(), engine.clone(), 1);
+        let key = |key: &str, tenant: &str, admin| ApiKeyEntry {
+            key: key.to_string(),
+            tenant: tenant.to_string(),
+            qps: None,
+            daily_claim_quota: None,
+            admin,
+        };
+        let auth = ApiKeyAuth::new(&[key("key-a", "team-a", false), key("key-b", "team-b", false), key("root", "ops", true)]);
+        let router = build_router(RewardApiState::new(engine).with_auth(Arc::new(auth)).with_jobs(jobs));
+        let call = |method: &str, uri: &str, key: &str, body: Vec<u8>| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {key}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let request = JobRequest { groups: vec![mk_group("g0", 4, &["A."])], include_details: false };
+        let resp = router.clone().oneshot(call("POST", "/jobs", "key-a", serde_json::to_vec(&request).unwrap())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let job_id = serde_json::from_slice::<JobInfo>(&body).unwrap().job_id;
+
+        // another tenant can neither see, read nor cancel it
+        for (method, uri) in [("GET", format!("/jobs/{job_id}")), ("GET", format!("/jobs/{job_id}/result")), ("DELETE", format!("/jobs/{job_id}"))] {
+            let resp = router.clone().oneshot(call(method, &uri, "key-b", Vec::new())).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{method} {uri}");
+        }
+
+        let resp = router.clone().oneshot(call("GET", &format!("/jobs/{job_id}"), "key-a", Vec::new())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router.clone().oneshot(call("GET", &format!("/jobs/{job_id}"), "root", Vec::new())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router.oneshot(call("DELETE", &format!("/jobs/{job_id}"), "key-a", Vec::new())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<JobInfo>(&body).unwrap().status, JobStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_key_gets_429() {
+        let router = mk_router(Some(1));
+        let group = mk_group("g0", 4, &["A."]);
+
+        let resp = router.clone().oneshot(post(&group, Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router.oneshot(post(&group, Some("secret"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().contains_key(header::RETRY_AFTER));
+    }
 }
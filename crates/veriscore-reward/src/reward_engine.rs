@@ -1,42 +1,562 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use veriscore_runtime::pipeline::StatelessPipeline;
+use tokio::sync::mpsc;
+use veriscore_core::error::CoreError;
+use veriscore_core::scoring::PerResponseScore;
+use veriscore_core::types::InputRecord;
+use veriscore_runtime::metrics::UsageMeter;
+use veriscore_runtime::pipeline::{PartialUsage, PipelineSettings, StatelessPipeline};
 
-use crate::reward_types::{RewardDetail, RewardRequest, RewardResponse};
+use crate::advantage::compute_advantages;
+use crate::error::RewardError;
+use crate::reward_shaping::{RewardShaper, ShapingBreakdown};
+use crate::settings::{BudgetAction, LiveSettings, ReloadableConfig, SettingsSnapshot};
+use crate::reward_types::{
+    CompletionReward, MultiGroupResponse, RecordUsage, RewardDetail, RewardRequest, RewardResponse, RewardStreamEvent,
+    RunUsage, Validate,
+};
+
+/// One pipeline run's result; `None` when a `degrade` budget ran out first.
+type Scored = Option<(PerResponseScore, Option<ShapingBreakdown>, RecordUsage)>;
+
+/// Receives usage as completions are scored, e.g. to charge tenant quotas.
+pub trait UsageSink: Send + Sync {
+    fn record_claims(&self, claims: usize);
+}
 
 #[derive(Clone)]
 pub struct RewardEngine {
     pipeline: Arc<StatelessPipeline>,
+    settings: Arc<LiveSettings>,
+    usage: Option<Arc<dyn UsageSink>>,
+    meter: Arc<UsageMeter>,
+}
+
+/// Identical completions scored with the same settings share one pipeline run.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CompletionKey {
+    binary: bool,
+    k_median: usize,
+    question: Option<String>,
+    response: String,
+}
+
+impl CompletionKey {
+    fn new(request: &RewardRequest, record: &InputRecord) -> Self {
+        Self {
+            binary: request.binary,
+            k_median: request.k_median,
+            question: record.question.clone(),
+            response: record.response.clone(),
+        }
+    }
 }
 
 impl RewardEngine {
     pub fn new(pipeline: Arc<StatelessPipeline>) -> Self {
-        Self { pipeline }
-    }
-
-    pub async fn score_batch(&self, request: RewardRequest, include_details: bool) -> Result<RewardResponse> {
-        let mut rewards = Vec::with_capacity(request.completions.len());
-        let mut details = Vec::new();
-
-        for record in request.completions.iter() {
-            let (_verification, score) = self.pipeline
-                .verify_and_score(record, request.binary, request.k_median)
-                .await?;
-            rewards.push(score.f1);
-            if include_details {
-                details.push(RewardDetail {
-                    supported: score.supported,
-                    total: score.total,
-                    precision: score.precision,
-                    recall: score.recall,
-                    f1: score.f1,
-                });
+        Self { pipeline, settings: Arc::new(LiveSettings::default()), usage: None, meter: Arc::default() }
+    }
+
+    /// Scores with `shaper`'s config and judge, keeping the current prompts and retrieval.
+    pub fn with_shaper(mut self, shaper: RewardShaper) -> Self {
+        let config = ReloadableConfig { shaping: shaper.config().clone(), ..self.settings.current().config.clone() };
+        self.settings = Arc::new(LiveSettings::with_judge(config, shaper.judge()));
+        self
+    }
+
+    pub fn with_settings(mut self, settings: Arc<LiveSettings>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Shared with every copy of this engine, so a reload applies to all of them.
+    pub fn settings(&self) -> &Arc<LiveSettings> {
+        &self.settings
+    }
+
+    /// LLM usage of every pipeline run, shared with every copy of this engine.
+    pub fn usage_meter(&self) -> &Arc<UsageMeter> {
+        &self.meter
+    }
+
+    /// Meters into `meter`, e.g. one that also collects hedged calls' losing attempts.
+    pub fn with_usage_meter(mut self, meter: Arc<UsageMeter>) -> Self {
+        self.meter = meter;
+        self
+    }
+
+    /// A cheap copy of this engine that reports usage to `sink`.
+    pub fn with_usage_sink(&self, sink: Arc<dyn UsageSink>) -> Self {
+        Self { usage: Some(sink), ..self.clone() }
+    }
+
+    pub async fn score_batch(&self, request: RewardRequest, include_details: bool) -> Result<RewardResponse, RewardError> {
+        let (mut out, usage) = self.score_groups(vec![request], include_details).await?;
+        let response = out.pop().expect("one response per group");
+        Ok(RewardResponse { usage: Some(usage), ..response })
+    }
+
+    /// Scores several prompt groups in one pass, keyed by `group_id`.
+    pub async fn score_multi(&self, groups: Vec<RewardRequest>, include_details: bool) -> Result<MultiGroupResponse, RewardError> {
+        let mut seen = HashSet::new();
+        for group in &groups {
+            if !seen.insert(group.group_id.as_str()) {
+                return Err(RewardError::InvalidRequest(format!(
+                    "duplicate group_id {:?} in multi-group request",
+                    group.group_id
+                )));
             }
         }
+        let ids = groups.iter().map(|g| g.group_id.clone()).collect::<Vec<_>>();
+        let (responses, usage) = self.score_groups(groups, include_details).await?;
+        Ok(MultiGroupResponse { groups: ids.into_iter().zip(responses).collect::<BTreeMap<_, _>>(), usage: Some(usage) })
+    }
 
+    /// Runs every distinct completion across all groups concurrently (one at a
+    /// time under a budget), so they share the micro-batchers, caches and
+    /// budget, then reassembles per-group responses.
+    async fn score_groups(
+        &self,
+        groups: Vec<RewardRequest>,
+        include_details: bool,
+    ) -> Result<(Vec<RewardResponse>, RunUsage), RewardError> {
+        for group in &groups {
+            group.validate()?;
+        }
+        let settings = self.settings.current();
+        let pipeline = settings.run_pipeline();
+        let mut unique: Vec<(CompletionKey, &InputRecord)> = Vec::new();
+        let mut index: HashMap<CompletionKey, usize> = HashMap::new();
+        for group in &groups {
+            for record in &group.completions {
+                let key = CompletionKey::new(group, record);
+                if !index.contains_key(&key) {
+                    index.insert(key.clone(), unique.len());
+                    unique.push((key, record));
+                }
+            }
+        }
+
+        let runs = unique.iter().map(|(key, record)| self.score_one(&settings, &pipeline, record, key.binary, key.k_median));
+        let scored = stream::iter(runs.collect::<Vec<_>>())
+            .buffered(in_flight(&pipeline))
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut usage = RunUsage::default();
+        for result in &scored {
+            usage.add(result.as_ref().map(|(_, _, u)| u));
+        }
+
+        let mut out = Vec::with_capacity(groups.len());
+        for group in &groups {
+            let results = group.completions.iter().map(|record| &scored[index[&CompletionKey::new(group, record)]]).collect::<Vec<_>>();
+            let fill = fill_reward(results.iter().filter_map(|r| r.as_ref().map(|(score, shaping, _)| reward_of(score, shaping))));
+            let rewards = results
+                .iter()
+                .map(|r| r.as_ref().map_or(fill, |(score, shaping, _)| reward_of(score, shaping)))
+                .collect::<Vec<_>>();
+            let details = include_details.then(|| results.iter().map(|r| detail_of(r)).collect());
+
+            let advantages = group.advantage.as_ref().map(|cfg| compute_advantages(&rewards, cfg));
+            out.push(RewardResponse { rewards, advantages, details, usage: None });
+        }
+        Ok((out, usage))
+    }
+
+    /// Streams one event per completion as soon as its pipeline finishes, then
+    /// a summary carrying the full `RewardResponse` (including advantages).
+    pub fn score_stream(self: Arc<Self>, request: RewardRequest, include_details: bool) -> impl Stream<Item = RewardStreamEvent> + Send {
+        let (tx, rx) = mpsc::channel(request.completions.len().max(1) + 1);
+        tokio::spawn(async move {
+            let event = match self.stream_completions(&request, include_details, &tx).await {
+                Ok(response) => RewardStreamEvent::Summary(response),
+                Err(err) => RewardStreamEvent::Error(RewardError::from_anyhow(err).body()),
+            };
+            let _ = tx.send(event).await;
+        });
+        stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|event| (event, rx)) })
+    }
+
+    async fn stream_completions(
+        &self,
+        request: &RewardRequest,
+        include_details: bool,
+        tx: &mpsc::Sender<RewardStreamEvent>,
+    ) -> Result<RewardResponse> {
+        request.validate()?;
+        let settings = self.settings.current();
+        let pipeline = settings.run_pipeline();
+        let mut positions: Vec<Vec<usize>> = Vec::new();
+        let mut index: HashMap<CompletionKey, usize> = HashMap::new();
+        let mut unique = Vec::new();
+        for (i, record) in request.completions.iter().enumerate() {
+            let key = CompletionKey::new(request, record);
+            match index.get(&key) {
+                Some(&u) => positions[u].push(i),
+                None => {
+                    index.insert(key, unique.len());
+                    positions.push(vec![i]);
+                    unique.push(record);
+                }
+            }
+        }
+
+        let runs = unique.iter().enumerate().map(|(u, record)| {
+            let (settings, pipeline) = (&settings, &pipeline);
+            async move { (u, self.score_one(settings, pipeline, record, request.binary, request.k_median).await) }
+        });
+        let mut pending = stream::iter(runs.collect::<Vec<_>>()).buffer_unordered(in_flight(&pipeline));
+
+        let mut rewards = vec![0.0; request.completions.len()];
+        let mut details: Vec<Option<RewardDetail>> = vec![None; request.completions.len()];
+        let mut usage = RunUsage::default();
+        // completions skipped by the budget get the group mean, known only at the end
+        let mut skipped = Vec::new();
+        let mut emit = |index: usize, reward: f32, detail: Option<RewardDetail>| {
+            rewards[index] = reward;
+            details[index] = detail.clone();
+            tx.send(RewardStreamEvent::Completion(CompletionReward { index, reward, detail }))
+        };
+        let mut scored_rewards = Vec::new();
+        while let Some((u, result)) = pending.next().await {
+            let result = result?;
+            usage.add(result.as_ref().map(|(_, _, u)| u));
+            let Some((score, shaping, _)) = &result else {
+                skipped.push(u);
+                continue;
+            };
+            let reward = reward_of(score, shaping);
+            scored_rewards.push(reward);
+            for &i in &positions[u] {
+                if emit(i, reward, include_details.then(|| detail_of(&result))).await.is_err() {
+                    bail!("reward stream closed by client");
+                }
+            }
+        }
+        let fill = fill_reward(scored_rewards.into_iter());
+        for u in skipped {
+            for &i in &positions[u] {
+                if emit(i, fill, include_details.then(|| detail_of(&None))).await.is_err() {
+                    bail!("reward stream closed by client");
+                }
+            }
+        }
+
+        let advantages = request.advantage.as_ref().map(|cfg| compute_advantages(&rewards, cfg));
         Ok(RewardResponse {
             rewards,
-            details: include_details.then_some(details),
+            advantages,
+            details: include_details.then(|| details.into_iter().flatten().collect()),
+            usage: Some(usage),
         })
     }
+
+    async fn score_one(
+        &self,
+        settings: &SettingsSnapshot,
+        pipeline: &PipelineSettings,
+        record: &InputRecord,
+        binary: bool,
+        k_median: usize,
+    ) -> Result<Scored> {
+        let (verification, score) = match self.pipeline.verify_and_score_with(record, binary, k_median, pipeline).await {
+            Err(err) if matches!(CoreError::find(&err), Some(CoreError::BudgetExceeded(_))) => {
+                // stages that ran before the budget stopped this record were paid for
+                if let Some(partial) = PartialUsage::find(&err) {
+                    self.meter.record(&[("extraction", partial.extraction)], partial.retrieval);
+                }
+                if settings.config.budget.on_exceeded == BudgetAction::Degrade {
+                    return Ok(None);
+                }
+                return Err(err);
+            }
+            result => result?,
+        };
+        let usage = RecordUsage::of(&verification);
+        self.meter.record(&[("extraction", Some(usage.extraction)), ("verification", usage.verification)], usage.retrieval);
+        if let Some(usage) = &self.usage {
+            usage.record_claims(verification.claim_verification_result.len());
+        }
+        let shaping = match &settings.shaper {
+            Some(shaper) => Some(shaper.shape(&verification, k_median).await?),
+            None => None,
+        };
+        Ok(Some((score, shaping, usage)))
+    }
+}
+
+/// Completions scored at once. A budget is checked before each stage against
+/// what earlier stages were charged, so under one completions run one at a
+/// time; otherwise every completion would pass its first checks at $0 and a
+/// run could overshoot its cap by one stage per completion.
+fn in_flight(pipeline: &PipelineSettings) -> usize {
+    if pipeline.budget.is_some() { 1 } else { usize::MAX }
+}
+
+fn reward_of(score: &PerResponseScore, shaping: &Option<ShapingBreakdown>) -> f32 {
+    shaping.as_ref().map(|s| s.f1).unwrap_or(score.f1)
+}
+
+/// Reward for completions the budget left unscored: the mean of the scored
+/// ones, so group-relative advantages treat them as average.
+fn fill_reward(scored: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = scored.fold((0.0, 0usize), |(sum, count), r| (sum + r, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f32 }
+}
+
+fn detail_of(scored: &Scored) -> RewardDetail {
+    let Some((score, shaping, usage)) = scored else {
+        return RewardDetail::budget_exhausted();
+    };
+    RewardDetail {
+        supported: score.supported,
+        total: score.total,
+        precision: score.precision,
+        recall: score.recall,
+        f1: score.f1,
+        shaping: shaping.clone(),
+        usage: Some(*usage),
+        budget_exhausted: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{Budget, ConfigUpdate};
+    use crate::test_support::{mk_engine, mk_group, FakeEvidence, FakeExtractor, FakeVerifier};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use veriscore_core::extraction::LlmClaimExtractor;
+    use veriscore_core::prompts::PromptTemplates;
+    use veriscore_core::stages::ClaimVerifier;
+    use veriscore_core::types::{ClaimVerification, EvidenceRecord, VerificationLabel, VerificationRecord};
+    use veriscore_llm::traits::{Completion, Llm};
+    use veriscore_llm::usage::TokenUsage;
+
+    /// Rule-based verifier: a claim is supported only when it has evidence
+    /// whose snippet contains `needle`.
+    struct SnippetRule {
+        needle: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl ClaimVerifier for SnippetRule {
+        async fn verify(&self, evidence: EvidenceRecord, _binary: bool, _prompts: &PromptTemplates) -> anyhow::Result<VerificationRecord> {
+            let results = evidence
+                .claim_snippets_dict
+                .iter()
+                .map(|(claim, hits)| ClaimVerification {
+                    claim: claim.clone(),
+                    search_results: hits.clone(),
+                    verification_result: if hits.iter().any(|h| h.snippet.contains(self.needle)) {
+                        VerificationLabel::Supported
+                    } else {
+                        VerificationLabel::Unsupported
+                    },
+                    confidence: None,
+                })
+                .collect();
+            Ok(VerificationRecord { evidence, claim_verification_result: results, verification_usage: None })
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_claim_verifier_drives_rewards() {
+        let engine_with = |needle| {
+            RewardEngine::new(Arc::new(StatelessPipeline {
+                extractor: Arc::new(LlmClaimExtractor::new(Arc::new(FakeExtractor {
+                    calls: Arc::new(AtomicUsize::new(0)),
+                    delay: Duration::ZERO,
+                }))),
+                verifier: Arc::new(SnippetRule { needle }),
+                evidence: Arc::new(FakeEvidence),
+            }))
+        };
+
+        let out = engine_with("s").score_batch(mk_group("g0", 1, &["A."]), false).await.unwrap();
+        assert!((out.rewards[0] - 1.0).abs() < 1e-4);
+        let out = engine_with("nowhere").score_batch(mk_group("g0", 1, &["A."]), false).await.unwrap();
+        assert_eq!(out.rewards[0], 0.0);
+    }
+
+    /// Extracts one claim per window for 100 tokens, after `delay`.
+    struct MeteredExtractor {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl Llm for MeteredExtractor {
+        async fn chat_many(&self, prompts: Vec<Vec<async_openai::types::ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<String>> {
+            tokio::time::sleep(self.delay).await;
+            Ok(prompts.iter().map(|_| r#"["Alpha claim"]"#.to_string()).collect())
+        }
+
+        async fn chat_many_with_usage(
+            &self,
+            prompts: Vec<Vec<async_openai::types::ChatCompletionRequestMessage>>,
+        ) -> anyhow::Result<Vec<Completion>> {
+            let usage = TokenUsage { prompt_tokens: 90, completion_tokens: 10 };
+            let texts = self.chat_many(prompts).await?;
+            Ok(texts.into_iter().map(|text| Completion { usage: Some(usage), ..Completion::text(text) }).collect())
+        }
+    }
+
+    fn budgeted_engine(budget: Budget) -> RewardEngine {
+        budgeted_engine_with_delay(budget, Duration::ZERO)
+    }
+
+    fn budgeted_engine_with_delay(budget: Budget, delay: Duration) -> RewardEngine {
+        let engine = RewardEngine::new(Arc::new(StatelessPipeline::from_llms(
+            Arc::new(MeteredExtractor { delay }),
+            Arc::new(FakeVerifier),
+            Arc::new(FakeEvidence),
+        )));
+        engine.settings().update(ConfigUpdate { budget: Some(budget), ..Default::default() }).unwrap();
+        engine
+    }
+
+    #[tokio::test]
+    async fn exhausted_budget_aborts_the_request() {
+        let engine = budgeted_engine(Budget { max_tokens: Some(150), ..Default::default() });
+
+        let err = engine.score_batch(mk_group("g0", 4, &["A.", "B.", "C."]), false).await.unwrap_err();
+        assert_eq!(err.code(), "budget_exceeded");
+        assert_eq!(err.status(), axum::http::StatusCode::PAYMENT_REQUIRED);
+
+        // each request gets a fresh budget
+        let out = engine.score_batch(mk_group("g0", 4, &["A."]), false).await.unwrap();
+        assert_eq!(out.usage.unwrap().extraction.total_tokens(), 100);
+    }
+
+    #[tokio::test]
+    async fn degraded_budget_fills_skipped_completions_with_the_group_mean() {
+        let engine = budgeted_engine(Budget {
+            max_tokens: Some(150),
+            on_exceeded: BudgetAction::Degrade,
+            ..Default::default()
+        });
+
+        let out = engine.score_batch(mk_group("g0", 4, &["A.", "B.", "C."]), true).await.unwrap();
+
+        // the first completion spends 100 tokens; the second crosses the cap
+        // in extraction and the third never starts
+        let details = out.details.unwrap();
+        assert_eq!(details.iter().filter(|d| d.budget_exhausted).count(), 2);
+        assert!(out.rewards.iter().all(|r| (r - 0.4).abs() < 1e-4));
+        let usage = out.usage.unwrap();
+        assert_eq!((usage.scored, usage.skipped), (1, 2));
+        assert_eq!(usage.extraction.total_tokens(), 100);
+    }
+
+    #[tokio::test]
+    async fn budget_cap_holds_when_stages_yield() {
+        let budget = Budget { max_tokens: Some(250), on_exceeded: BudgetAction::Degrade, ..Default::default() };
+        let completions = ["A.", "B.", "C.", "D.", "E."];
+
+        // every extraction sleeps, so concurrent completions would all pass
+        // their first check at zero spend
+        let engine = budgeted_engine_with_delay(budget.clone(), Duration::from_millis(20));
+        let usage = engine.score_batch(mk_group("g0", 4, &completions), false).await.unwrap().usage.unwrap();
+        assert_eq!((usage.scored, usage.skipped), (2, 3));
+        assert_eq!(usage.extraction.total_tokens(), 200);
+        // the third completion's extraction crossed the cap and is still metered
+        let totals = engine.usage_meter().totals();
+        assert_eq!(totals.stages["extraction"].total_tokens(), 300);
+
+        let engine = Arc::new(budgeted_engine_with_delay(budget, Duration::from_millis(20)));
+        let events = engine.clone().score_stream(mk_group("g0", 4, &completions), true).collect::<Vec<_>>().await;
+        let Some(RewardStreamEvent::Summary(summary)) = events.last() else { panic!("expected a summary, got {events:?}") };
+        let usage = summary.usage.unwrap();
+        assert_eq!((usage.scored, usage.skipped), (2, 3));
+        assert_eq!(engine.usage_meter().totals().stages["extraction"].total_tokens(), 300);
+    }
+
+    #[tokio::test]
+    async fn score_multi_keys_responses_by_group_and_dedups_completions() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let engine = mk_engine(calls.clone(), Duration::ZERO);
+
+        let out = engine
+            .score_multi(
+                vec![
+                    mk_group("g0", 4, &["Same text.", "Same text.", "Other text."]),
+                    mk_group("g1", 4, &["Same text."]),
+                    mk_group("g2", 1, &["Same text."]),
+                ],
+                true,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(out.groups.len(), 3);
+        assert_eq!(out.groups["g0"].rewards.len(), 3);
+        assert_eq!(out.groups["g0"].details.as_ref().unwrap().len(), 3);
+        // 1 supported claim, K=4 -> F1 = 0.4; K=1 -> F1 = 1.0
+        assert!((out.groups["g1"].rewards[0] - 0.4).abs() < 1e-4);
+        assert!((out.groups["g2"].rewards[0] - 1.0).abs() < 1e-4);
+        // "Same text." at K=4 is shared by g0 and g1; K=1 is a separate run
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn score_multi_rejects_duplicate_group_ids() {
+        let engine = mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO);
+        let err = engine
+            .score_multi(vec![mk_group("g0", 4, &["A."]), mk_group("g0", 4, &["B."])], false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RewardError::InvalidRequest(msg) if msg.contains("duplicate group_id")));
+    }
+
+    #[tokio::test]
+    async fn invalid_groups_are_rejected_before_scoring() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let engine = Arc::new(mk_engine(calls.clone(), Duration::ZERO));
+
+        let err = engine.score_batch(mk_group("g0", 0, &["A."]), false).await.unwrap_err();
+        assert!(err.to_string().contains("k_median"));
+        let err = engine.score_multi(vec![mk_group("g0", 4, &["A."]), mk_group("g1", 4, &[])], false).await.unwrap_err();
+        assert_eq!(err.code(), "invalid_request");
+
+        let events = engine.clone().score_stream(mk_group("g0", 0, &["A."]), false).collect::<Vec<_>>().await;
+        match &events[..] {
+            [RewardStreamEvent::Error(body)] => assert_eq!(body.code, "invalid_request"),
+            other => panic!("expected a single error event, got {other:?}"),
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn score_stream_emits_each_completion_then_summary() {
+        let engine = Arc::new(mk_engine(Arc::new(AtomicUsize::new(0)), Duration::ZERO));
+        let mut request = mk_group("g0", 4, &["A.", "B.", "A."]);
+        request.advantage = Some(Default::default());
+
+        let events = engine.score_stream(request, true).collect::<Vec<_>>().await;
+
+        assert_eq!(events.len(), 4);
+        let mut indices = events[..3]
+            .iter()
+            .map(|e| match e {
+                RewardStreamEvent::Completion(c) => {
+                    assert!(c.detail.is_some());
+                    c.index
+                }
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1, 2]);
+        match &events[3] {
+            RewardStreamEvent::Summary(summary) => {
+                assert_eq!(summary.rewards.len(), 3);
+                assert_eq!(summary.advantages.as_ref().unwrap().len(), 3);
+                assert_eq!(summary.details.as_ref().unwrap().len(), 3);
+            }
+            other => panic!("expected summary, got {other:?}"),
+        }
+    }
 }
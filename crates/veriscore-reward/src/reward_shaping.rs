@@ -0,0 +1,485 @@
+use anyhow::Result;
+use async_openai::types::{
+    ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestUserMessageArgs,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use veriscore_core::types::{VerificationLabel, VerificationRecord};
+use veriscore_llm::traits::Llm;
+
+use crate::error::RewardError;
+
+/// Optional reward-shaping modules applied on top of the VeriScore F1.
+///
+/// Every enabled component flags claims that look like reward padding. A
+/// flagged supported claim only contributes `1 - weight` to the supported
+/// count, so padding a completion with duplicates, off-topic facts or dense
+/// claim lists no longer raises precision or recall@K.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ShapingConfig {
+    #[serde(default)]
+    pub duplicate: Option<DuplicateConfig>,
+    #[serde(default)]
+    pub relevance: Option<RelevanceConfig>,
+    #[serde(default)]
+    pub density: Option<DensityConfig>,
+}
+
+impl ShapingConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.duplicate.is_some() || self.relevance.is_some() || self.density.is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), RewardError> {
+        let weights = [
+            ("duplicate", self.duplicate.as_ref().map(|c| c.weight)),
+            ("relevance", self.relevance.as_ref().map(|c| c.weight)),
+            ("density", self.density.as_ref().map(|c| c.weight)),
+        ];
+        for (component, weight) in weights {
+            if let Some(weight) = weight.filter(|w| !(0.0..=1.0).contains(w)) {
+                return Err(RewardError::InvalidRequest(format!("shaping.{component}.weight must be in [0, 1], got {weight}")));
+            }
+        }
+        if let Some(threshold) = self.duplicate.as_ref().map(|c| c.similarity_threshold).filter(|t| !(0.0..=1.0).contains(t)) {
+            return Err(RewardError::InvalidRequest(format!(
+                "shaping.duplicate.similarity_threshold must be in [0, 1], got {threshold}"
+            )));
+        }
+        if self.density.as_ref().is_some_and(|c| c.max_claims_per_sentence == 0) {
+            return Err(RewardError::InvalidRequest("shaping.density.max_claims_per_sentence must be at least 1".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateConfig {
+    /// Token Jaccard similarity at or above which a claim counts as a repeat.
+    pub similarity_threshold: f32,
+    pub weight: f32,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        Self { similarity_threshold: 0.8, weight: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelevanceConfig {
+    pub weight: f32,
+}
+
+impl Default for RelevanceConfig {
+    fn default() -> Self {
+        Self { weight: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DensityConfig {
+    pub max_claims_per_sentence: usize,
+    pub weight: f32,
+}
+
+impl Default for DensityConfig {
+    fn default() -> Self {
+        Self { max_claims_per_sentence: 3, weight: 1.0 }
+    }
+}
+
+/// Per-component outcome reported in `RewardDetail`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ShapingComponent {
+    /// Number of claims flagged by this component.
+    pub flagged: usize,
+    /// F1 lost when only this component is applied.
+    pub penalty: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ShapingBreakdown {
+    pub duplicate: ShapingComponent,
+    pub off_topic: ShapingComponent,
+    pub density: ShapingComponent,
+    pub effective_supported: f32,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+}
+
+/// Decides which claims are relevant to the question that prompted them.
+#[async_trait::async_trait]
+pub trait RelevanceJudge: Send + Sync {
+    async fn judge(&self, question: &str, claims: &[String]) -> Result<Vec<bool>>;
+}
+
+/// Cheap judge: a claim is relevant when enough of its content words occur in the question.
+#[derive(Debug, Clone)]
+pub struct LexicalRelevance {
+    pub min_overlap: f32,
+}
+
+impl Default for LexicalRelevance {
+    fn default() -> Self {
+        Self { min_overlap: 0.1 }
+    }
+}
+
+#[async_trait::async_trait]
+impl RelevanceJudge for LexicalRelevance {
+    async fn judge(&self, question: &str, claims: &[String]) -> Result<Vec<bool>> {
+        let q = content_tokens(question);
+        if q.is_empty() {
+            return Ok(vec![true; claims.len()]);
+        }
+        Ok(claims
+            .iter()
+            .map(|claim| {
+                let c = content_tokens(claim);
+                if c.is_empty() {
+                    return false;
+                }
+                let overlap = c.intersection(&q).count() as f32 / c.len() as f32;
+                overlap >= self.min_overlap
+            })
+            .collect())
+    }
+}
+
+/// Asks an LLM whether each claim helps answer the question.
+#[derive(Clone)]
+pub struct LlmRelevanceJudge {
+    llm: Arc<dyn Llm>,
+}
+
+impl LlmRelevanceJudge {
+    pub fn new(llm: Arc<dyn Llm>) -> Self {
+        Self { llm }
+    }
+}
+
+fn build_relevance_prompt(question: &str, claim: &str) -> Vec<ChatCompletionRequestMessage> {
+    let sys = ChatCompletionRequestSystemMessageArgs::default()
+        .content("You judge whether a factual claim is relevant to answering a question. Return JSON: {\"relevant\": true | false}")
+        .build().unwrap().into();
+    let usr = ChatCompletionRequestUserMessageArgs::default()
+        .content(format!("Question:\n{question}\n\nClaim:\n{claim}"))
+        .build().unwrap().into();
+    vec![sys, usr]
+}
+
+#[async_trait::async_trait]
+impl RelevanceJudge for LlmRelevanceJudge {
+    async fn judge(&self, question: &str, claims: &[String]) -> Result<Vec<bool>> {
+        let prompts = claims.iter().map(|c| build_relevance_prompt(question, c)).collect::<Vec<_>>();
+        let outs = self.llm.chat_many(prompts).await?;
+        // unparseable answers are treated as relevant so a flaky judge never penalizes
+        Ok(outs
+            .iter()
+            .map(|out| {
+                serde_json::from_str::<serde_json::Value>(out.trim())
+                    .ok()
+                    .and_then(|v| v.get("relevant").and_then(|r| r.as_bool()))
+                    .unwrap_or(true)
+            })
+            .collect())
+    }
+}
+
+pub struct RewardShaper {
+    config: ShapingConfig,
+    judge: Arc<dyn RelevanceJudge>,
+}
+
+impl RewardShaper {
+    pub fn new(config: ShapingConfig) -> Self {
+        Self { config, judge: Arc::new(LexicalRelevance::default()) }
+    }
+
+    pub fn with_judge(mut self, judge: Arc<dyn RelevanceJudge>) -> Self {
+        self.judge = judge;
+        self
+    }
+
+    pub fn config(&self) -> &ShapingConfig {
+        &self.config
+    }
+
+    pub fn judge(&self) -> Arc<dyn RelevanceJudge> {
+        self.judge.clone()
+    }
+
+    pub async fn shape(&self, vr: &VerificationRecord, k: usize) -> Result<ShapingBreakdown> {
+        let claims = vr.claim_verification_result.iter().map(|c| c.claim.clone()).collect::<Vec<_>>();
+        let supported = vr.claim_verification_result
+            .iter()
+            .map(|c| matches!(c.verification_result, VerificationLabel::Supported))
+            .collect::<Vec<_>>();
+        let total = claims.len();
+
+        let duplicate = match &self.config.duplicate {
+            Some(cfg) => (flag_duplicates(&claims, &supported, cfg.similarity_threshold), cfg.weight),
+            None => (vec![false; total], 0.0),
+        };
+        let question = vr.evidence.claims.input.question.as_deref();
+        let off_topic = match (&self.config.relevance, question) {
+            (Some(cfg), Some(q)) if total > 0 => {
+                let relevant = self.judge.judge(q, &claims).await?;
+                let flags = (0..total).map(|i| !relevant.get(i).copied().unwrap_or(true)).collect();
+                (flags, cfg.weight)
+            }
+            _ => (vec![false; total], 0.0),
+        };
+        let density = match &self.config.density {
+            Some(cfg) => (flag_dense(&vr.evidence.claims.claim_list, total, cfg.max_claims_per_sentence), cfg.weight),
+            None => (vec![false; total], 0.0),
+        };
+
+        let (_, _, base_f1) = score_effective(&supported, &[], total, k);
+        let component = |(flags, weight): &(Vec<bool>, f32)| {
+            let (_, _, f1) = score_effective(&supported, &[(flags.as_slice(), *weight)], total, k);
+            ShapingComponent { flagged: flags.iter().filter(|f| **f).count(), penalty: base_f1 - f1 }
+        };
+        let discounts = [
+            (duplicate.0.as_slice(), duplicate.1),
+            (off_topic.0.as_slice(), off_topic.1),
+            (density.0.as_slice(), density.1),
+        ];
+        let effective_supported = effective_supported(&supported, &discounts);
+        let (precision, recall, f1) = score_effective(&supported, &discounts, total, k);
+
+        Ok(ShapingBreakdown {
+            duplicate: component(&duplicate),
+            off_topic: component(&off_topic),
+            density: component(&density),
+            effective_supported,
+            precision,
+            recall,
+            f1,
+        })
+    }
+}
+
+fn effective_supported(supported: &[bool], discounts: &[(&[bool], f32)]) -> f32 {
+    supported
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| **s)
+        .map(|(i, _)| {
+            discounts
+                .iter()
+                .filter(|(flags, _)| flags.get(i).copied().unwrap_or(false))
+                .fold(1.0f32, |acc, (_, weight)| acc * (1.0 - weight.clamp(0.0, 1.0)))
+        })
+        .sum()
+}
+
+// mirrors `score_response`, but over a fractional supported count
+fn score_effective(supported: &[bool], discounts: &[(&[bool], f32)], total: usize, k: usize) -> (f32, f32, f32) {
+    let eff = effective_supported(supported, discounts);
+    let precision = eff / total.max(1) as f32;
+    let recall = (eff / k.max(1) as f32).min(1.0);
+    let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
+    (precision, recall, f1)
+}
+
+/// Flags every claim that is a near-duplicate of an unflagged one. Supported
+/// claims are kept first, in order, so a correct restatement of an earlier
+/// unsupported claim is not the one penalized.
+fn flag_duplicates(claims: &[String], supported: &[bool], threshold: f32) -> Vec<bool> {
+    let tokens = claims.iter().map(|c| word_tokens(c)).collect::<Vec<_>>();
+    let mut order = (0..claims.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| !supported.get(i).copied().unwrap_or(false));
+    let mut kept: Vec<usize> = Vec::new();
+    let mut flags = vec![false; claims.len()];
+    for i in order {
+        let dup = kept.iter().any(|&j| jaccard(&tokens[i], &tokens[j]) >= threshold);
+        if !dup {
+            kept.push(i);
+        }
+        flags[i] = dup;
+    }
+    flags
+}
+
+/// Flags claims beyond the per-sentence cap, using the per-window layout in `claim_list`.
+fn flag_dense(claim_list: &[Vec<String>], total: usize, max_per_sentence: usize) -> Vec<bool> {
+    let flags = claim_list
+        .iter()
+        .flat_map(|window| (0..window.len()).map(move |i| i >= max_per_sentence))
+        .collect::<Vec<_>>();
+    // verification results no longer line up with the windows; don't guess
+    if flags.len() != total {
+        return vec![false; total];
+    }
+    flags
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(b).count() as f32 / a.union(b).count() as f32
+}
+
+fn word_tokens(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "are", "was", "were", "for", "with", "that", "this", "from", "what", "which",
+    "who", "whom", "how", "why", "when", "where", "does", "did", "has", "have", "had", "its",
+    "his", "her", "their", "about", "into", "than", "then", "also", "can", "could", "would",
+    "should", "been", "being", "not", "but",
+];
+
+fn content_tokens(text: &str) -> HashSet<String> {
+    word_tokens(text)
+        .into_iter()
+        .filter(|w| w.chars().count() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use veriscore_core::types::{
+        ClaimVerification, EvidenceRecord, ExtractedClaimsRecord, InputRecord,
+    };
+
+    fn mk_record(question: Option<&str>, claim_list: Vec<Vec<&str>>, supported: &[bool]) -> VerificationRecord {
+        let claim_list = claim_list
+            .into_iter()
+            .map(|w| w.into_iter().map(str::to_string).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let all_claims = claim_list.iter().flatten().cloned().collect::<Vec<_>>();
+        let claim_verification_result = all_claims
+            .iter()
+            .zip(supported)
+            .map(|(claim, s)| ClaimVerification {
+                claim: claim.clone(),
+                search_results: vec![],
+                verification_result: if *s { VerificationLabel::Supported } else { VerificationLabel::Unsupported },
+                confidence: None,
+            })
+            .collect();
+
+        VerificationRecord {
+            evidence: EvidenceRecord {
+                claims: ExtractedClaimsRecord {
+                    input: InputRecord {
+                        question: question.map(str::to_string),
+                        response: "response".to_string(),
+                        model: None,
+                        prompt_source: None,
+                    },
+                    prompt_tok_cnt: None,
+                    response_tok_cnt: None,
+                    abstained: false,
+                    claim_list,
+                    all_claims,
+                    extraction_usage: None,
+                },
+                claim_snippets_dict: vec![],
+                retrieval_usage: None,
+            },
+            claim_verification_result,
+            verification_usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicates_do_not_count_twice() {
+        let vr = mk_record(
+            None,
+            vec![vec!["Paris is the capital of France."], vec!["Paris is the capital of France"], vec!["The Seine flows through Paris."]],
+            &[true, true, true],
+        );
+        let shaper = RewardShaper::new(ShapingConfig { duplicate: Some(DuplicateConfig::default()), ..Default::default() });
+        let out = shaper.shape(&vr, 3).await.unwrap();
+
+        assert_eq!(out.duplicate.flagged, 1);
+        assert!(out.duplicate.penalty > 0.0);
+        assert!((out.effective_supported - 2.0).abs() < 1e-6);
+        assert_eq!(out.off_topic.flagged, 0);
+        assert_eq!(out.density.flagged, 0);
+    }
+
+    #[tokio::test]
+    async fn supported_restatement_of_an_unsupported_claim_is_kept() {
+        let vr = mk_record(
+            None,
+            vec![vec!["Paris is the capital of France."], vec!["Paris is the capital of France"]],
+            &[false, true],
+        );
+        let shaper = RewardShaper::new(ShapingConfig { duplicate: Some(DuplicateConfig::default()), ..Default::default() });
+        let out = shaper.shape(&vr, 2).await.unwrap();
+
+        // the unsupported first instance is the duplicate, so nothing is lost
+        assert_eq!(out.duplicate.flagged, 1);
+        assert_eq!(out.duplicate.penalty, 0.0);
+        assert!((out.effective_supported - 1.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn off_topic_claims_are_penalized_lexically() {
+        let vr = mk_record(
+            Some("What is the capital of France?"),
+            vec![vec!["Paris is the capital of France.", "Bananas are rich in potassium."]],
+            &[true, true],
+        );
+        let shaper = RewardShaper::new(ShapingConfig { relevance: Some(RelevanceConfig::default()), ..Default::default() });
+        let out = shaper.shape(&vr, 2).await.unwrap();
+
+        assert_eq!(out.off_topic.flagged, 1);
+        assert!((out.effective_supported - 1.0).abs() < 1e-6);
+        assert!((out.precision - 0.5).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn relevance_is_skipped_without_question() {
+        let vr = mk_record(None, vec![vec!["Bananas are rich in potassium."]], &[true]);
+        let shaper = RewardShaper::new(ShapingConfig { relevance: Some(RelevanceConfig::default()), ..Default::default() });
+        let out = shaper.shape(&vr, 1).await.unwrap();
+
+        assert_eq!(out.off_topic.flagged, 0);
+        assert!((out.f1 - 1.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn density_caps_claims_per_sentence() {
+        let vr = mk_record(
+            None,
+            vec![vec!["A one.", "B two.", "C three."], vec!["D four."]],
+            &[true, true, true, true],
+        );
+        let shaper = RewardShaper::new(ShapingConfig {
+            density: Some(DensityConfig { max_claims_per_sentence: 2, weight: 0.5 }),
+            ..Default::default()
+        });
+        let out = shaper.shape(&vr, 4).await.unwrap();
+
+        assert_eq!(out.density.flagged, 1);
+        assert!((out.effective_supported - 3.5).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn unsupported_claims_carry_no_penalty() {
+        let vr = mk_record(None, vec![vec!["Same claim here."], vec!["Same claim here."]], &[false, false]);
+        let shaper = RewardShaper::new(ShapingConfig { duplicate: Some(DuplicateConfig::default()), ..Default::default() });
+        let out = shaper.shape(&vr, 2).await.unwrap();
+
+        assert_eq!(out.duplicate.flagged, 1);
+        assert!((out.duplicate.penalty - 0.0).abs() < 1e-6);
+        assert!((out.f1 - 0.0).abs() < 1e-6);
+    }
+}
@@ -1,29 +1,252 @@
 use serde::{Deserialize, Serialize};
-use veriscore_core::types::InputRecord;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+use veriscore_core::types::{InputRecord, SearchUsage, StageUsage, VerificationRecord};
+use veriscore_runtime::metrics::UsageTotals;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+use crate::advantage::AdvantageConfig;
+use crate::error::{ErrorBody, RewardError};
+use crate::reward_shaping::ShapingBreakdown;
+
+/// Length bounds enforced by [`Validate`]; they match the `maxLength` values
+/// in the OpenAPI document.
+pub const MAX_GROUP_ID_CHARS: usize = 256;
+pub const MAX_QUESTION_CHARS: usize = 16_384;
+pub const MAX_TAG_CHARS: usize = 256;
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct RewardRequest {
+    #[schema(min_length = 1, max_length = 256)]
     pub group_id: String,
+    /// Claims needed for full recall.
+    #[schema(minimum = 1)]
     pub k_median: usize,
     #[serde(default = "default_binary")]
+    #[schema(default = true)]
     pub binary: bool,
+    #[schema(min_items = 1)]
     pub completions: Vec<InputRecord>,
+    /// When set, group-relative advantages are returned alongside the raw rewards.
+    #[serde(default)]
+    pub advantage: Option<AdvantageConfig>,
 }
 
 fn default_binary() -> bool { true }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Checks the constraints the OpenAPI schema declares but serde can't enforce.
+pub trait Validate {
+    fn validate(&self) -> Result<(), RewardError>;
+}
+
+impl Validate for RewardRequest {
+    fn validate(&self) -> Result<(), RewardError> {
+        let invalid = |msg: String| Err(RewardError::InvalidRequest(msg));
+        let id_chars = self.group_id.chars().count();
+        if id_chars == 0 || id_chars > MAX_GROUP_ID_CHARS {
+            return invalid(format!("group_id must be 1 to {MAX_GROUP_ID_CHARS} characters, got {id_chars}"));
+        }
+        if self.k_median == 0 {
+            return invalid(format!("group {:?}: k_median must be at least 1", self.group_id));
+        }
+        if self.completions.is_empty() {
+            return invalid(format!("group {:?} has no completions", self.group_id));
+        }
+        for (i, c) in self.completions.iter().enumerate() {
+            let fields = [
+                ("question", c.question.as_deref(), MAX_QUESTION_CHARS),
+                ("model", c.model.as_deref(), MAX_TAG_CHARS),
+                ("prompt_source", c.prompt_source.as_deref(), MAX_TAG_CHARS),
+            ];
+            for (name, value, max) in fields {
+                let chars = value.map_or(0, |v| v.chars().count());
+                if chars > max {
+                    return invalid(format!(
+                        "completion {i} of group {:?}: {name} has {chars} characters; the limit is {max}",
+                        self.group_id
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct RewardDetail {
     pub supported: usize,
     pub total: usize,
     pub precision: f32,
     pub recall: f32,
     pub f1: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shaping: Option<ShapingBreakdown>,
+    /// LLM usage of the pipeline run behind this completion. Identical
+    /// completions in a request share one run and report the same usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RecordUsage>,
+    /// Not scored because the run's budget ran out; the reward is the mean
+    /// of the group's scored completions.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub budget_exhausted: bool,
+}
+
+impl RewardDetail {
+    pub(crate) fn budget_exhausted() -> Self {
+        Self {
+            supported: 0,
+            total: 0,
+            precision: 0.0,
+            recall: 0.0,
+            f1: 0.0,
+            shaping: None,
+            usage: None,
+            budget_exhausted: true,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct RecordUsage {
+    pub extraction: StageUsage,
+    /// `null` when claims were verified without an LLM (NLI).
+    pub verification: Option<StageUsage>,
+    /// `null` when the evidence provider does not report its searches.
+    #[serde(default)]
+    pub retrieval: Option<SearchUsage>,
+    /// USD across all stages, per the server's cost model.
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+impl RecordUsage {
+    pub fn of(record: &VerificationRecord) -> Self {
+        let extraction = record.evidence.claims.extraction_usage.unwrap_or_default();
+        let verification = record.verification_usage;
+        let retrieval = record.evidence.retrieval_usage;
+        let cost_usd = extraction.cost_usd
+            + verification.map_or(0.0, |u| u.cost_usd)
+            + retrieval.map_or(0.0, |u| u.cost_usd);
+        Self { extraction, verification, retrieval, cost_usd }
+    }
+}
+
+/// What one run spent, summed over its distinct completions. A skipped
+/// completion's stages that finished before the budget ran out count toward
+/// the budget but are not itemized here.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct RunUsage {
+    /// Distinct completions that went through the pipeline.
+    pub scored: usize,
+    /// Distinct completions left unscored by a `degrade` budget.
+    pub skipped: usize,
+    pub extraction: StageUsage,
+    pub verification: StageUsage,
+    pub retrieval: SearchUsage,
+    pub cost_usd: f64,
+}
+
+impl RunUsage {
+    /// `None` stands for a completion skipped by the budget.
+    pub fn add(&mut self, record: Option<&RecordUsage>) {
+        let Some(record) = record else {
+            self.skipped += 1;
+            return;
+        };
+        self.scored += 1;
+        self.extraction.merge(&record.extraction);
+        if let Some(verification) = &record.verification {
+            self.verification.merge(verification);
+        }
+        if let Some(retrieval) = &record.retrieval {
+            self.retrieval.merge(retrieval);
+        }
+        self.cost_usd += record.cost_usd;
+    }
+}
+
+/// Response of `GET /admin/usage`: LLM and search usage since the server started.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema)]
+pub struct UsageReport {
+    /// Pipeline runs, i.e. distinct completions scored.
+    pub runs: u64,
+    /// Usage per stage (`extraction`, `verification`).
+    pub stages: BTreeMap<String, StageUsage>,
+    pub retrieval: SearchUsage,
+    pub cost_usd: f64,
+}
+
+impl From<UsageTotals> for UsageReport {
+    fn from(totals: UsageTotals) -> Self {
+        Self {
+            runs: totals.runs,
+            cost_usd: totals.cost_usd(),
+            retrieval: totals.retrieval,
+            stages: totals.stages.into_iter().map(|(stage, usage)| (stage.to_string(), usage)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct RewardResponse {
     pub rewards: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advantages: Option<Vec<f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<Vec<RewardDetail>>,
+    /// Spend of the whole call; unset on the groups of a multi-group response,
+    /// which report it once at the top level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RunUsage>,
+}
+
+/// Several prompt groups scored in one round-trip, e.g. a whole training step.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct MultiGroupRequest {
+    #[schema(min_items = 1)]
+    pub groups: Vec<RewardRequest>,
+}
+
+impl Validate for MultiGroupRequest {
+    fn validate(&self) -> Result<(), RewardError> {
+        if self.groups.is_empty() {
+            return Err(RewardError::InvalidRequest("groups must not be empty".to_string()));
+        }
+        self.groups.iter().try_for_each(Validate::validate)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct MultiGroupResponse {
+    /// Responses keyed by `group_id`.
+    pub groups: BTreeMap<String, RewardResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RunUsage>,
+}
+
+/// One finished completion of a streamed `reward_batch`.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct CompletionReward {
+    pub index: usize,
+    pub reward: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<RewardDetail>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardStreamEvent {
+    Completion(CompletionReward),
+    Summary(RewardResponse),
+    Error(ErrorBody),
+}
+
+impl RewardStreamEvent {
+    /// SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RewardStreamEvent::Completion(_) => "completion",
+            RewardStreamEvent::Summary(_) => "summary",
+            RewardStreamEvent::Error(_) => "error",
+        }
+    }
 }
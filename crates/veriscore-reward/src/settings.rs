@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use utoipa::ToSchema;
+use veriscore_core::prompts::PromptTemplates;
+use veriscore_runtime::cost::BudgetTracker;
+use veriscore_runtime::pipeline::PipelineSettings;
+use veriscore_web::RetrievalParams;
+
+use crate::error::RewardError;
+use crate::reward_shaping::{LexicalRelevance, RelevanceJudge, RewardShaper, ShapingConfig};
+use crate::reward_types::Validate;
+
+/// Upper bound on `retrieval.top_k`; Serper returns at most 100 organic results.
+pub const MAX_RETRIEVAL_TOP_K: usize = 100;
+
+/// Build details reported by `GET /admin/config`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Set from `VERISCORE_GIT_SHA` at compile time, when available.
+    pub git_sha: Option<String>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: option_env!("VERISCORE_GIT_SHA").map(str::to_string),
+        }
+    }
+}
+
+/// Response of the `/admin/config` routes.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdminConfigView {
+    pub build: BuildInfo,
+    /// Startup settings of the server process, secrets omitted. Changing
+    /// these still needs a restart.
+    #[schema(value_type = Object)]
+    pub startup: serde_json::Value,
+    /// Reload counter; 1 until the first `PUT /admin/config`.
+    pub version: u64,
+    pub reloadable: ReloadableConfig,
+}
+
+/// Settings that can be replaced while the server is running.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReloadableConfig {
+    pub prompts: PromptTemplates,
+    pub shaping: ShapingConfig,
+    /// `null` keeps the evidence provider's startup settings.
+    pub retrieval: Option<RetrievalParams>,
+    /// Weight each claim by its probability of support when scoring.
+    #[serde(default)]
+    pub soft_rewards: bool,
+    #[serde(default)]
+    pub budget: Budget,
+}
+
+/// Spending caps for one run: a reward request, a multi-group call or a job.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Budget {
+    /// USD, priced with the server's cost model.
+    #[serde(default)]
+    pub max_usd: Option<f64>,
+    /// Prompt plus completion tokens that missed the LLM cache.
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub on_exceeded: BudgetAction,
+}
+
+/// What a run does once its budget is spent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetAction {
+    /// Fail the run with `budget_exceeded`.
+    #[default]
+    Abort,
+    /// Give completions that did not fit the mean reward of the scored ones
+    /// in their group, so their advantage is zero, and mark them in `details`.
+    Degrade,
+}
+
+impl Budget {
+    pub fn is_enabled(&self) -> bool {
+        self.max_usd.is_some() || self.max_tokens.is_some()
+    }
+
+    /// A fresh tracker for one run, or `None` without caps.
+    fn tracker(&self) -> Option<Arc<BudgetTracker>> {
+        self.is_enabled().then(|| Arc::new(BudgetTracker::new(self.max_usd, self.max_tokens)))
+    }
+}
+
+/// Body of `PUT /admin/config`. Each section that is present replaces the
+/// current one whole; absent sections are left alone.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct ConfigUpdate {
+    #[serde(default)]
+    pub prompts: Option<PromptTemplates>,
+    #[serde(default)]
+    pub shaping: Option<ShapingConfig>,
+    #[serde(default)]
+    pub retrieval: Option<RetrievalParams>,
+    #[serde(default)]
+    pub soft_rewards: Option<bool>,
+    #[serde(default)]
+    pub budget: Option<Budget>,
+}
+
+impl Validate for ConfigUpdate {
+    fn validate(&self) -> Result<(), RewardError> {
+        if let Some(prompts) = &self.prompts {
+            prompts.validate()?;
+        }
+        if let Some(shaping) = &self.shaping {
+            shaping.validate()?;
+        }
+        if let Some(retrieval) = &self.retrieval {
+            if retrieval.top_k == 0 || retrieval.top_k > MAX_RETRIEVAL_TOP_K {
+                return Err(RewardError::InvalidRequest(format!(
+                    "retrieval.top_k must be 1 to {MAX_RETRIEVAL_TOP_K}, got {}",
+                    retrieval.top_k
+                )));
+            }
+            if retrieval.concurrency == 0 {
+                return Err(RewardError::InvalidRequest("retrieval.concurrency must be at least 1".to_string()));
+            }
+        }
+        if let Some(max_usd) = self.budget.as_ref().and_then(|b| b.max_usd) {
+            if !(max_usd.is_finite() && max_usd >= 0.0) {
+                return Err(RewardError::InvalidRequest(format!("budget.max_usd must be a non-negative number, got {max_usd}")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One generation of the reloadable settings. Requests take a snapshot when
+/// they start and keep it until they finish, so a reload never changes the
+/// prompts or shaping halfway through a group.
+pub struct SettingsSnapshot {
+    /// Starts at 1 and increases with every reload.
+    pub version: u64,
+    pub config: ReloadableConfig,
+    pub(crate) pipeline: PipelineSettings,
+    pub(crate) shaper: Option<RewardShaper>,
+}
+
+impl std::fmt::Debug for SettingsSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SettingsSnapshot")
+            .field("version", &self.version)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SettingsSnapshot {
+    fn new(version: u64, config: ReloadableConfig, judge: &Arc<dyn RelevanceJudge>) -> Self {
+        let pipeline = PipelineSettings {
+            prompts: config.prompts.clone(),
+            retrieval: config.retrieval.clone(),
+            soft_rewards: config.soft_rewards,
+            budget: None,
+        };
+        let shaper = config.shaping.is_enabled().then(|| RewardShaper::new(config.shaping.clone()).with_judge(judge.clone()));
+        Self { version, config, pipeline, shaper }
+    }
+
+    /// Pipeline settings for one run, with a fresh tracker when a budget is set.
+    pub(crate) fn run_pipeline(&self) -> PipelineSettings {
+        PipelineSettings { budget: self.config.budget.tracker(), ..self.pipeline.clone() }
+    }
+}
+
+/// The current [`SettingsSnapshot`], swapped atomically on reload.
+pub struct LiveSettings {
+    current: RwLock<Arc<SettingsSnapshot>>,
+    judge: Arc<dyn RelevanceJudge>,
+}
+
+impl LiveSettings {
+    pub fn new(config: ReloadableConfig) -> Self {
+        Self::with_judge(config, Arc::new(LexicalRelevance::default()))
+    }
+
+    /// `judge` backs the relevance component of every shaping config loaded later.
+    pub fn with_judge(config: ReloadableConfig, judge: Arc<dyn RelevanceJudge>) -> Self {
+        let current = RwLock::new(Arc::new(SettingsSnapshot::new(1, config, &judge)));
+        Self { current, judge }
+    }
+
+    pub fn current(&self) -> Arc<SettingsSnapshot> {
+        self.current.read().expect("live settings poisoned").clone()
+    }
+
+    /// Applies `update` on top of the current settings and returns the
+    /// previous and new snapshots. Concurrent updates are serialized.
+    pub fn update(&self, update: ConfigUpdate) -> Result<(Arc<SettingsSnapshot>, Arc<SettingsSnapshot>), RewardError> {
+        update.validate()?;
+        let mut current = self.current.write().expect("live settings poisoned");
+        let mut config = current.config.clone();
+        if let Some(prompts) = update.prompts {
+            config.prompts = prompts;
+        }
+        if let Some(shaping) = update.shaping {
+            config.shaping = shaping;
+        }
+        if let Some(retrieval) = update.retrieval {
+            config.retrieval = Some(retrieval);
+        }
+        if let Some(soft_rewards) = update.soft_rewards {
+            config.soft_rewards = soft_rewards;
+        }
+        if let Some(budget) = update.budget {
+            config.budget = budget;
+        }
+        let next = Arc::new(SettingsSnapshot::new(current.version + 1, config, &self.judge));
+        let previous = std::mem::replace(&mut *current, next.clone());
+        Ok((previous, next))
+    }
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self::new(ReloadableConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reward_shaping::DuplicateConfig;
+
+    #[test]
+    fn update_replaces_given_sections_and_keeps_old_snapshots_intact() {
+        let live = LiveSettings::default();
+        let before = live.current();
+        assert_eq!(before.version, 1);
+        assert!(before.shaper.is_none());
+
+        let (previous, next) = live
+            .update(ConfigUpdate {
+                shaping: Some(ShapingConfig { duplicate: Some(DuplicateConfig::default()), ..Default::default() }),
+                retrieval: Some(RetrievalParams { top_k: 4, concurrency: 8 }),
+                soft_rewards: Some(true),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(previous.version, 1);
+        assert_eq!(next.version, 2);
+        assert!(next.shaper.is_some());
+        assert_eq!(next.pipeline.retrieval, Some(RetrievalParams { top_k: 4, concurrency: 8 }));
+        assert!(next.pipeline.soft_rewards && !before.pipeline.soft_rewards);
+        assert_eq!(next.config.prompts, PromptTemplates::default());
+        // a request that started before the reload still sees the old settings
+        assert!(before.shaper.is_none() && before.config.retrieval.is_none());
+        assert_eq!(live.current().version, 2);
+    }
+
+    #[test]
+    fn invalid_updates_leave_settings_unchanged() {
+        let live = LiveSettings::default();
+        let bad_prompts = PromptTemplates { extraction_user: "no window".into(), ..Default::default() };
+
+        let err = live.update(ConfigUpdate { prompts: Some(bad_prompts), ..Default::default() }).unwrap_err();
+        assert_eq!(err.code(), "invalid_request");
+        let err = live
+            .update(ConfigUpdate { retrieval: Some(RetrievalParams { top_k: 0, concurrency: 1 }), ..Default::default() })
+            .unwrap_err();
+        assert!(err.to_string().contains("top_k"));
+        let err = live
+            .update(ConfigUpdate { budget: Some(Budget { max_usd: Some(-1.0), ..Default::default() }), ..Default::default() })
+            .unwrap_err();
+        assert!(err.to_string().contains("max_usd"));
+        assert_eq!(live.current().version, 1);
+    }
+}
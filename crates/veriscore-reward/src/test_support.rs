@@ -0,0 +1,74 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use veriscore_core::types::{EvidenceItem, InputRecord};
+use veriscore_runtime::pipeline::StatelessPipeline;
+use veriscore_web::web_evidence::EvidenceProvider;
+
+use crate::reward_engine::RewardEngine;
+use crate::reward_types::RewardRequest;
+
+/// Extracts one claim per window and counts `chat_many` calls.
+pub struct FakeExtractor {
+    pub calls: Arc<AtomicUsize>,
+    pub delay: Duration,
+}
+
+#[async_trait::async_trait]
+impl veriscore_llm::traits::Llm for FakeExtractor {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<String>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        Ok(prompts.iter().map(|_| r#"["Alpha claim"]"#.to_string()).collect())
+    }
+}
+
+pub struct FakeVerifier;
+
+#[async_trait::async_trait]
+impl veriscore_llm::traits::Llm for FakeVerifier {
+    async fn chat_many(&self, prompts: Vec<Vec<ChatCompletionRequestMessage>>) -> anyhow::Result<Vec<String>> {
+        Ok(prompts.iter().map(|_| r#"{"label":"supported"}"#.to_string()).collect())
+    }
+}
+
+pub struct FakeEvidence;
+
+#[async_trait::async_trait]
+impl EvidenceProvider for FakeEvidence {
+    async fn fetch_evidence_for_claims(&self, claims: &[String]) -> anyhow::Result<Vec<(String, Vec<EvidenceItem>)>> {
+        Ok(claims
+            .iter()
+            .map(|c| (c.clone(), vec![EvidenceItem { title: "t".into(), snippet: "s".into(), link: "l".into() }]))
+            .collect())
+    }
+}
+
+pub fn mk_engine(calls: Arc<AtomicUsize>, delay: Duration) -> RewardEngine {
+    RewardEngine::new(Arc::new(StatelessPipeline::from_llms(
+        Arc::new(FakeExtractor { calls, delay }),
+        Arc::new(FakeVerifier),
+        Arc::new(FakeEvidence),
+    )))
+}
+
+pub fn mk_group(group_id: &str, k_median: usize, responses: &[&str]) -> RewardRequest {
+    RewardRequest {
+        group_id: group_id.to_string(),
+        k_median,
+        binary: true,
+        completions: responses
+            .iter()
+            .map(|r| InputRecord {
+                question: Some("Q?".to_string()),
+                response: r.to_string(),
+                model: None,
+                prompt_source: None,
+            })
+            .collect(),
+        advantage: None,
+    }
+}
@@ -1,38 +1,217 @@
+use std::future::IntoFuture;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
 use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::EnvFilter;
-use veriscore_llm::{BatchedLlm, MicroBatchConfig, OpenAiCompatibleLlm};
+use veriscore_common::hedge::{HedgeConfig, HedgeDelay};
+use veriscore_core::types::StageUsage;
+use veriscore_core::{ClaimVerifier, LlmClaimExtractor, LlmClaimVerifier, NliClaimVerifier, NliVerifierConfig, VotingVerifier};
+use veriscore_llm::{
+    AnthropicLlm, ApproxTokenCounter, ArrayBatchLlm, BatchStore, BatchedLlm, CandleNliModel, CircuitConfig, HedgedLlm, Llm, LoserSink, MicroBatchConfig,
+    NliModel, OpenAiBatchLlm, OpenAiCompatibleLlm, RecordingLlm, ReplayLlm, RoutingLlm, RoutingPolicy, SamplingParams, TeiNliModel,
+    TokenCounter, TokenizerCounter,
+};
 use veriscore_llm::cache::LlmCache;
-use veriscore_reward::{build_router, RewardEngine};
-use veriscore_reward::reward_api::RewardApiState;
+use veriscore_llm::openai::OPENAI_API_BASE;
+use veriscore_reward::{
+    build_router, AnyProbe, ApiKeyAuth, Budget, BudgetAction, CachedProbe, HealthChecker, HealthProbe, JobManager, JobStore, LiveSettings,
+    ReloadableConfig, RequestLimits, RewardEngine, RewardGrpcService, UsageSink,
+};
+use veriscore_reward::reward_api::{Readiness, RewardApiState};
+use veriscore_reward::reward_shaping::{
+    DensityConfig, DuplicateConfig, LexicalRelevance, LlmRelevanceJudge, RelevanceConfig, RelevanceJudge, ShapingConfig,
+};
+use veriscore_runtime::cost::CostModel;
+use veriscore_runtime::metrics::UsageMeter;
 use veriscore_runtime::pipeline::StatelessPipeline;
 use veriscore_web::cache::WebCache;
-use veriscore_web::serper::Serper;
-use veriscore_web::WebEvidenceProvider;
+use veriscore_web::serper::{HedgedSearcher, Searcher, Serper};
+use veriscore_web::{RecordingSearcher, ReplaySearcher, WebEvidenceProvider};
 
-#[derive(Debug, Parser)]
+/// Serialized (secrets skipped) as the `startup` section of `GET /admin/config`.
+#[derive(Debug, Parser, Serialize)]
 #[command(name = "veriscore-rewardd")]
 struct Args {
     #[arg(long, default_value = "0.0.0.0:8088")]
     listen: String,
 
+    /// Also serve the gRPC `RewardService` on this address.
+    #[arg(long)]
+    grpc_listen: Option<String>,
+
     #[arg(long, env = "OPENAI_BASE_URL")]
     openai_base_url: Option<String>,
 
     #[arg(long, env = "OPENAI_API_KEY")]
+    #[serde(skip)]
     openai_api_key: Option<String>,
 
+    #[arg(long, env = "ANTHROPIC_BASE_URL")]
+    anthropic_base_url: Option<String>,
+
+    #[arg(long, env = "ANTHROPIC_API_KEY")]
+    #[serde(skip)]
+    anthropic_api_key: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = LlmBackend::Openai)]
+    extract_backend: LlmBackend,
+
     #[arg(long, env = "EXTRACT_MODEL", default_value = "llama-3.3-70b-instruct")]
     extract_model: String,
 
+    #[arg(long, value_enum, default_value_t = LlmBackend::Openai)]
+    verify_backend: LlmBackend,
+
     #[arg(long, env = "VERIFY_MODEL", default_value = "llama-3.3-70b-instruct")]
     verify_model: String,
 
+    /// Path of the array batch endpoint, under the OpenAI base URL.
+    #[arg(long, default_value = "/batch")]
+    array_batch_path: String,
+
+    /// Prompts per array batch request; larger micro-batches are split.
+    #[arg(long, default_value_t = 64)]
+    array_batch_max_size: usize,
+
+    /// SQLite file recording submitted OpenAI batches, so a restart resumes them.
+    #[arg(long, default_value = "./openai_batches.sqlite")]
+    openai_batch_db: String,
+
+    #[arg(long, default_value_t = 30)]
+    openai_batch_poll_secs: u64,
+
+    /// Prompts per OpenAI batch. An `openai-batch` stage collects prompts for
+    /// up to `--openai-batch-wait-secs` instead of `--max-batch-wait-ms`.
+    #[arg(long, default_value_t = 10_000)]
+    openai_batch_max_size: usize,
+
+    #[arg(long, default_value_t = 60)]
+    openai_batch_wait_secs: u64,
+
+    /// More OpenAI-compatible servers (e.g. vLLM replicas) serving the same
+    /// models as `--openai-base-url`; OpenAI-backed stages spread their calls
+    /// over all of them.
+    #[arg(long, value_delimiter = ',')]
+    openai_replica_urls: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = RoutingPolicyArg::RoundRobin)]
+    routing_policy: RoutingPolicyArg,
+
+    /// Extraction model used while every primary endpoint is failing.
+    #[arg(long)]
+    extract_fallback_model: Option<String>,
+
+    /// Verification model used while every primary endpoint is failing.
+    #[arg(long)]
+    verify_fallback_model: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = LlmBackend::Openai)]
+    fallback_backend: LlmBackend,
+
+    /// Base URL of the fallback backend; defaults to that backend's `--*-base-url`.
+    #[arg(long)]
+    fallback_base_url: Option<String>,
+
+    /// Consecutive failures that take an endpoint out of rotation.
+    #[arg(long, default_value_t = 5)]
+    circuit_failures: u32,
+
+    /// How long a failing endpoint stays out of rotation before a trial call.
+    #[arg(long, default_value_t = 30)]
+    circuit_open_secs: u64,
+
+    /// Sampling settings for both stages; unset ones use the backend's default
+    /// (for Anthropic, `--max-tokens` falls back to 1024).
+    #[arg(long)]
+    temperature: Option<f32>,
+
+    #[arg(long)]
+    top_p: Option<f32>,
+
+    #[arg(long)]
+    max_tokens: Option<u32>,
+
+    /// Verifier samples per claim, drawn with seeds 0..N and combined by
+    /// majority vote. Only useful with `--temperature` above 0.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    verify_samples: u32,
+
+    /// Extra verifier models (on `--verify-backend`) that vote alongside `--verify-model`.
+    #[arg(long, value_delimiter = ',')]
+    vote_models: Vec<String>,
+
+    /// Request token logprobs from the verifier and keep the probability of
+    /// the `supported` label per claim (OpenAI-compatible backends only).
+    #[arg(long)]
+    verify_logprobs: bool,
+
+    /// Pack up to this many claims into one verification prompt; answers that
+    /// do not line up with the claims are retried one claim per prompt.
+    /// Values above 1 disable `--verify-logprobs`.
+    #[arg(long, default_value_t = 1)]
+    verify_claims_per_prompt: usize,
+
+    /// Start with soft rewards: claims count by their probability of support
+    /// (vote share or label logprob) instead of 0/1.
+    #[arg(long)]
+    soft_rewards: bool,
+
+    /// Estimate token counts from text length when an OpenAI-compatible
+    /// server does not report `usage`.
+    #[arg(long)]
+    approx_token_counts: bool,
+
+    /// Count tokens with this Hugging Face `tokenizer.json` when an
+    /// OpenAI-compatible server does not report `usage`.
+    #[arg(long, conflicts_with = "approx_token_counts")]
+    tokenizer: Option<String>,
+
+    /// JSON prices used to report cost and enforce `--budget-usd`:
+    /// `{"models": {"<model>": {"input_per_mtok", "output_per_mtok"}}, "search_usd"}`.
+    #[arg(long)]
+    cost_model: Option<String>,
+
+    /// Stop scoring a request or job once it has spent this many USD.
+    #[arg(long)]
+    budget_usd: Option<f64>,
+
+    /// Stop scoring a request or job once it has spent this many uncached tokens.
+    #[arg(long)]
+    budget_tokens: Option<u64>,
+
+    /// When a budget runs out, give the remaining completions the group's
+    /// mean reward instead of failing the request.
+    #[arg(long)]
+    budget_degrade: bool,
+
+    /// Verify claims in-process with the NLI cross-encoder checkpoint in this
+    /// directory (`config.json`, `tokenizer.json`, `model.safetensors`)
+    /// instead of prompting the verifier LLM.
+    #[arg(long, env = "NLI_MODEL_DIR", conflicts_with = "nli_url")]
+    nli_model_dir: Option<String>,
+
+    /// Like `--nli-model-dir`, but with the cross-encoder served by
+    /// text-embeddings-inference at this URL.
+    #[arg(long, env = "NLI_URL")]
+    nli_url: Option<String>,
+
+    #[arg(long, default_value_t = 0.5)]
+    nli_entailment_threshold: f32,
+
+    /// Pairs per forward pass or `/predict` call; for TEI keep it at or below
+    /// the server's `--max-client-batch-size`.
+    #[arg(long, default_value_t = 32)]
+    nli_max_batch: usize,
+
     #[arg(long, env = "SERPER_API_KEY")]
+    #[serde(skip)]
     serper_api_key: String,
 
     #[arg(long, default_value_t = 64)]
@@ -44,6 +223,27 @@ struct Args {
     #[arg(long, default_value_t = 8)]
     serper_top_k: usize,
 
+    /// Duplicate extraction and verification calls still running after this
+    /// long (`400ms`, `2s`) or this latency percentile (`p95`) and keep the
+    /// first answer.
+    #[arg(long, value_parser = parse_hedge_delay)]
+    llm_hedge_after: Option<String>,
+
+    /// Extra LLM requests hedging may add, as a fraction of calls per stage.
+    #[arg(long, default_value_t = 0.05, value_parser = HedgeConfig::parse_max_extra_load)]
+    llm_hedge_max_extra: f64,
+
+    /// Like `--llm-hedge-after`, for web searches.
+    #[arg(long, value_parser = parse_hedge_delay)]
+    search_hedge_after: Option<String>,
+
+    #[arg(long, default_value_t = 0.05, value_parser = HedgeConfig::parse_max_extra_load)]
+    search_hedge_max_extra: f64,
+
+    /// Hedged duplicates in flight at once, per backend.
+    #[arg(long, default_value_t = 8)]
+    hedge_max_in_flight: usize,
+
     #[arg(long, default_value_t = 32)]
     max_batch_size: usize,
 
@@ -55,6 +255,143 @@ struct Args {
 
     #[arg(long, default_value = "./web_cache.sqlite")]
     web_cache_db: String,
+
+    /// SQLite file backing the asynchronous `/jobs` API.
+    #[arg(long, default_value = "./reward_jobs.sqlite")]
+    jobs_db: String,
+
+    #[arg(long, default_value_t = 1)]
+    max_concurrent_jobs: usize,
+
+    /// JSON file of API keys (`{"keys": [{"key", "tenant", "qps", "daily_claim_quota"}]}`);
+    /// when set, every route but `/healthz` requires `Authorization: Bearer <key>`,
+    /// and keys with `"admin": true` may use `/admin/config`.
+    #[arg(long, env = "REWARD_API_KEYS_FILE")]
+    api_keys_file: Option<String>,
+
+    #[arg(long, default_value_t = 16 * 1024 * 1024)]
+    max_body_bytes: usize,
+
+    /// Completions per request, summed over all groups.
+    #[arg(long, default_value_t = 4096)]
+    max_completions: usize,
+
+    #[arg(long, default_value_t = 32_768)]
+    max_response_chars: usize,
+
+    /// Discount near-duplicate claims within a completion.
+    #[arg(long)]
+    penalize_duplicates: bool,
+
+    #[arg(long, default_value_t = 0.8)]
+    duplicate_similarity: f32,
+
+    /// Discount claims that are irrelevant to the question.
+    #[arg(long)]
+    penalize_off_topic: bool,
+
+    #[arg(long, value_enum, default_value_t = RelevanceJudgeKind::Lexical)]
+    relevance_judge: RelevanceJudgeKind,
+
+    /// Discount claims beyond this many per sentence.
+    #[arg(long)]
+    max_claims_per_sentence: Option<usize>,
+
+    /// Record every extraction, verification and search request with its
+    /// response to `extractor.jsonl`, `verifier.jsonl` and `search.jsonl` here.
+    #[arg(long, conflicts_with = "replay_fixtures")]
+    record_fixtures: Option<String>,
+
+    /// Answer extraction, verification and search from fixtures recorded
+    /// with `--record-fixtures`; unrecorded requests fail.
+    #[arg(long)]
+    replay_fixtures: Option<String>,
+
+    /// After SIGTERM/SIGINT, how long to let in-flight requests and queued
+    /// LLM batches finish before exiting anyway.
+    #[arg(long, default_value_t = 30)]
+    drain_timeout_secs: u64,
+
+    /// Per-dependency timeout for the `/readyz` probes.
+    #[arg(long, default_value_t = 2000)]
+    probe_timeout_ms: u64,
+
+    /// The Serper probe spends a search credit, so its result is reused this long.
+    #[arg(long, default_value_t = 300)]
+    serper_probe_ttl_secs: u64,
+}
+
+impl Args {
+    fn sampling(&self) -> SamplingParams {
+        SamplingParams {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            ..Default::default()
+        }
+    }
+
+    fn hedge(&self, after: Option<&String>, max_extra_load: f64) -> Option<HedgeConfig> {
+        Some(HedgeConfig {
+            delay: after?.parse().expect("validated by clap"),
+            max_extra_load,
+            max_in_flight: self.hedge_max_in_flight,
+        })
+    }
+
+    /// Every micro-batch sent to the OpenAI Batch API becomes its own batch,
+    /// so that backend collects far larger ones.
+    fn micro_batch(&self, backend: LlmBackend) -> MicroBatchConfig {
+        match backend {
+            LlmBackend::OpenaiBatch => MicroBatchConfig {
+                max_batch_size: self.openai_batch_max_size,
+                max_wait: Duration::from_secs(self.openai_batch_wait_secs),
+                queue_capacity: self.openai_batch_max_size.max(4096),
+            },
+            _ => MicroBatchConfig {
+                max_batch_size: self.max_batch_size,
+                max_wait: Duration::from_millis(self.max_batch_wait_ms),
+                queue_capacity: 4096,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum LlmBackend {
+    /// Any OpenAI-compatible chat completions server (vLLM, Matrix, OpenAI).
+    Openai,
+    /// The Anthropic Messages API.
+    Anthropic,
+    /// An OpenAI-compatible server that also takes arrays of requests at
+    /// `--array-batch-path`; each micro-batch is posted as one request.
+    #[serde(rename = "array-batch")]
+    ArrayBatch,
+    /// The OpenAI Batch API: cheaper, but answers can take hours, so only
+    /// for offline `/jobs` runs.
+    #[serde(rename = "openai-batch")]
+    OpenaiBatch,
+}
+
+fn parse_hedge_delay(s: &str) -> Result<String> {
+    s.parse::<HedgeDelay>()?;
+    Ok(s.to_string())
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum RoutingPolicyArg {
+    RoundRobin,
+    /// Prefer the endpoint with the fewest calls in flight.
+    LeastOutstanding,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum RelevanceJudgeKind {
+    Lexical,
+    Llm,
 }
 
 #[tokio::main]
@@ -64,61 +401,503 @@ async fn main() -> Result<()> {
         .init();
 
     let args = Args::parse();
+    let startup_config = serde_json::to_value(&args)?;
 
+    let cost = args.cost_model.as_deref().map(CostModel::from_file).transpose()?.unwrap_or_default();
     let llm_cache = Arc::new(LlmCache::open(&args.llm_cache_db)?);
+    let token_counter: Option<Arc<dyn TokenCounter>> = match &args.tokenizer {
+        Some(path) => Some(Arc::new(TokenizerCounter::from_file(path)?)),
+        None if args.approx_token_counts => Some(Arc::new(ApproxTokenCounter::default())),
+        None => None,
+    };
+    // the losing attempts of hedged prompts are metered under their stage
+    let meter = Arc::new(UsageMeter::default());
+    let deps = LlmDeps {
+        cache: llm_cache.clone(),
+        cost,
+        token_counter,
+        hedge: args.hedge(args.llm_hedge_after.as_ref(), args.llm_hedge_max_extra),
+        meter: meter.clone(),
+    };
     let web_cache = Arc::new(WebCache::open(&args.web_cache_db)?);
 
-    let extract_raw = Arc::new(OpenAiCompatibleLlm::new(
-        args.extract_model,
-        args.openai_base_url.clone(),
-        args.openai_api_key.clone(),
-        args.llm_concurrency,
-        Some(llm_cache.clone()),
-    ));
-    let verify_raw = Arc::new(OpenAiCompatibleLlm::new(
-        args.verify_model,
-        args.openai_base_url,
-        args.openai_api_key,
-        args.llm_concurrency,
-        Some(llm_cache),
-    ));
-
-    let extract_llm = Arc::new(BatchedLlm::spawn(
-        extract_raw,
-        MicroBatchConfig {
-            max_batch_size: args.max_batch_size,
-            max_wait: std::time::Duration::from_millis(args.max_batch_wait_ms),
-            queue_capacity: 4096,
-        },
-    ));
-    let verify_llm = Arc::new(BatchedLlm::spawn(
-        verify_raw,
-        MicroBatchConfig {
-            max_batch_size: args.max_batch_size,
-            max_wait: std::time::Duration::from_millis(args.max_batch_wait_ms),
-            queue_capacity: 4096,
-        },
-    ));
+    let extract = build_stage_llm(
+        StageKind::Extraction,
+        args.extract_backend,
+        &args.extract_model,
+        args.extract_fallback_model.as_deref(),
+        args.sampling(),
+        &args,
+        &deps,
+    )?;
+    let verify = build_stage_llm(
+        StageKind::Verification,
+        args.verify_backend,
+        &args.verify_model,
+        args.verify_fallback_model.as_deref(),
+        args.sampling(),
+        &args,
+        &deps,
+    )?;
+    let (extract_llm, verify_llm) = (extract.llm.clone(), verify.llm.clone());
 
     let serper_http = reqwest::Client::new();
-    let serper = Arc::new(Serper::new(serper_http, args.serper_api_key, args.serper_top_k));
-    let evidence = Arc::new(WebEvidenceProvider::new(
-        serper,
-        args.serper_top_k,
-        args.search_concurrency,
-        Some(web_cache),
-    ));
+    let serper = Arc::new(Serper::new(serper_http, args.serper_api_key.clone(), args.serper_top_k));
+    let mut searcher: Arc<dyn Searcher> = match args.hedge(args.search_hedge_after.as_ref(), args.search_hedge_max_extra) {
+        Some(config) => Arc::new(HedgedSearcher::new(serper.clone(), config)),
+        None => serper.clone(),
+    };
+    // fixtures bypass the web cache, whose hits would never reach the recorder
+    let fixtures = args.record_fixtures.is_some() || args.replay_fixtures.is_some();
+    if let Some(dir) = &args.record_fixtures {
+        searcher = Arc::new(RecordingSearcher::new(searcher, format!("{dir}/search.jsonl"))?);
+    }
+    if let Some(dir) = &args.replay_fixtures {
+        searcher = Arc::new(ReplaySearcher::from_file(format!("{dir}/search.jsonl"))?);
+    }
+    let evidence = Arc::new(
+        WebEvidenceProvider::new(
+            searcher,
+            args.serper_top_k,
+            args.search_concurrency,
+            (!fixtures).then(|| web_cache.clone()),
+        )
+            .with_search_price(deps.cost.search_usd),
+    );
+
+    let shaping = ShapingConfig {
+        duplicate: args.penalize_duplicates.then(|| DuplicateConfig {
+            similarity_threshold: args.duplicate_similarity,
+            ..Default::default()
+        }),
+        relevance: args.penalize_off_topic.then(RelevanceConfig::default),
+        density: args.max_claims_per_sentence.map(|max| DensityConfig {
+            max_claims_per_sentence: max,
+            ..Default::default()
+        }),
+    };
+    let budget = Budget {
+        max_usd: args.budget_usd,
+        max_tokens: args.budget_tokens,
+        on_exceeded: if args.budget_degrade { BudgetAction::Degrade } else { BudgetAction::Abort },
+    };
+    if budget.max_usd.is_some() && args.cost_model.is_none() {
+        tracing::warn!("--budget-usd without --cost-model: every call is priced at $0");
+    }
+    // prompts, shaping and retrieval start from the flags and can be swapped via /admin/config
+    let judge: Arc<dyn RelevanceJudge> = match args.relevance_judge {
+        RelevanceJudgeKind::Lexical => Arc::new(LexicalRelevance::default()),
+        RelevanceJudgeKind::Llm => Arc::new(LlmRelevanceJudge::new(verify_llm.clone())),
+    };
+    let settings = LiveSettings::with_judge(
+        ReloadableConfig { shaping, soft_rewards: args.soft_rewards, budget, ..Default::default() },
+        judge,
+    );
 
+    let tei_nli = args.nli_url.as_ref().map(|url| Arc::new(TeiNliModel::new(url.clone(), args.nli_max_batch)));
+    let nli: Option<Arc<dyn NliModel>> = match &args.nli_model_dir {
+        Some(dir) => {
+            let model = CandleNliModel::load(dir).with_context(|| format!("failed to load NLI model from {dir}"))?;
+            tracing::info!(dir, "verifying claims with an in-process NLI model");
+            Some(Arc::new(model.with_max_batch(args.nli_max_batch)))
+        }
+        None => tei_nli.clone().map(|model| model as Arc<dyn NliModel>),
+    };
+    let voting = args.verify_samples > 1 || !args.vote_models.is_empty();
+    if voting && nli.is_some() {
+        anyhow::bail!("--verify-samples and --vote-models apply to LLM verification and cannot be combined with NLI verification");
+    }
+    let llm_verifier = |llm: Arc<BatchedLlm>| {
+        LlmClaimVerifier::new(llm)
+            .with_logprobs(args.verify_logprobs)
+            .with_claims_per_prompt(args.verify_claims_per_prompt)
+    };
+    // each voter gets the verifier's whole stack, with its own micro-batcher,
+    // hedging budget, circuits and backend concurrency limit
+    let mut voters: Vec<(String, StageLlm)> = Vec::new();
+    // seeds of one model share its endpoints, so each voter model is probed once
+    let mut voter_probes = Vec::new();
+    let verifier: Arc<dyn ClaimVerifier> = match &nli {
+        Some(model) => Arc::new(NliClaimVerifier::new(
+            model.clone(),
+            NliVerifierConfig { entailment_threshold: args.nli_entailment_threshold, ..Default::default() },
+        )),
+        None if voting => {
+            let mut members: Vec<Arc<dyn ClaimVerifier>> = Vec::new();
+            for model in std::iter::once(&args.verify_model).chain(&args.vote_models) {
+                for seed in 0..args.verify_samples {
+                    let sampling = SamplingParams {
+                        seed: (args.verify_samples > 1).then_some(i64::from(seed)),
+                        ..args.sampling()
+                    };
+                    let voter = build_stage_llm(
+                        StageKind::Verification,
+                        args.verify_backend,
+                        model,
+                        args.verify_fallback_model.as_deref(),
+                        sampling,
+                        &args,
+                        &deps,
+                    )?;
+                    members.push(Arc::new(llm_verifier(voter.llm.clone())));
+                    if seed == 0 {
+                        voter_probes.push((format!("voter {model}"), voter.probe.clone()));
+                    }
+                    voters.push((format!("voter {model} seed {seed}"), voter));
+                }
+            }
+            tracing::info!(voters = members.len(), "verifying claims by majority vote");
+            Arc::new(VotingVerifier::new(members)?)
+        }
+        None => Arc::new(llm_verifier(verify_llm.clone())),
+    };
     let pipeline = Arc::new(StatelessPipeline {
-        extractor: extract_llm,
-        verifier: verify_llm,
+        extractor: Arc::new(LlmClaimExtractor::new(extract_llm.clone())),
+        verifier,
         evidence,
     });
-    let engine = Arc::new(RewardEngine::new(pipeline));
-    let router = build_router(RewardApiState { engine }).layer(TraceLayer::new_for_http());
+    let engine = Arc::new(RewardEngine::new(pipeline).with_settings(Arc::new(settings)).with_usage_meter(meter));
+    let auth = args.api_keys_file.as_deref().map(ApiKeyAuth::from_file).transpose()?.map(Arc::new);
+    let jobs = JobManager::new(JobStore::open(&args.jobs_db)?, engine.clone(), args.max_concurrent_jobs);
+    jobs.resume(|tenant| Some(auth.as_ref()?.tenant(tenant)? as Arc<dyn UsageSink>))?;
+    let limits = RequestLimits {
+        max_body_bytes: args.max_body_bytes,
+        max_completions: args.max_completions,
+        max_response_chars: args.max_response_chars,
+    };
+
+    // replayed runs need neither the LLM backends nor Serper
+    let replaying = args.replay_fixtures.is_some();
+    let mut health = HealthChecker::new(Duration::from_millis(args.probe_timeout_ms));
+    if !replaying {
+        health = health.with_probe("extractor", extract.probe.clone()).with_probe(
+            "serper",
+            Arc::new(CachedProbe::new(serper, Duration::from_secs(args.serper_probe_ttl_secs))),
+        );
+    }
+    // with NLI verification the verifier LLM only backs the LLM relevance judge
+    if !replaying && (nli.is_none() || matches!(args.relevance_judge, RelevanceJudgeKind::Llm)) {
+        health = health.with_probe("verifier", verify.probe.clone());
+    }
+    if !replaying {
+        for (name, probe) in voter_probes {
+            health = health.with_probe(name, probe);
+        }
+    }
+    // an in-process model is loaded at startup, so only a TEI server needs probing
+    if let Some(model) = tei_nli {
+        health = health.with_probe("nli", model);
+    }
+    let health = health
+        .with_probe("llm_cache", llm_cache.clone())
+        .with_probe("web_cache", web_cache.clone())
+        .with_probe("jobs_db", Arc::new(jobs.store().clone()));
+
+    let readiness = Readiness::new();
+    let mut state = RewardApiState::new(engine.clone())
+        .with_jobs(jobs)
+        .with_limits(limits.clone())
+        .with_readiness(readiness.clone())
+        .with_health(Arc::new(health))
+        .with_startup_config(startup_config);
+    let mut grpc = RewardGrpcService::new(engine.clone()).with_limits(limits);
+    if let Some(auth) = auth {
+        state = state.with_auth(auth.clone());
+        grpc = grpc.with_auth(auth);
+    }
+    let stages = [("extractor", &extract), ("verifier", &verify)].into_iter().chain(voters.iter().map(|(name, voter)| (name.as_str(), voter)));
+    for (stage, llm) in stages {
+        if let Some(routing) = &llm.routing {
+            state = state.with_routing(stage, routing.clone());
+        }
+    }
+    let router = build_router(state).layer(TraceLayer::new_for_http());
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if let Err(err) = shutdown_signal().await {
+                tracing::error!(error = %err, "failed to listen for shutdown signals");
+                return;
+            }
+            tracing::info!("shutdown signal received; draining");
+            readiness.set_ready(false);
+            shutdown.cancel();
+        }
+    });
+
+    let grpc_server = args.grpc_listen.as_ref().map(|grpc_listen| -> Result<_> {
+        let addr = grpc_listen.parse()?;
+        tracing::info!(listen = %grpc_listen, "starting gRPC RewardService");
+        let server = tonic::transport::Server::builder()
+            .add_service(grpc.into_server())
+            .serve_with_shutdown(addr, shutdown.clone().cancelled_owned());
+        Ok(tokio::spawn(async move {
+            if let Err(err) = server.await {
+                tracing::error!(error = %err, "gRPC server stopped");
+            }
+        }))
+    }).transpose()?;
 
     let listener = TcpListener::bind(&args.listen).await?;
     tracing::info!(listen = %args.listen, "starting veriscore-rewardd");
-    axum::serve(listener, router).await?;
+    let http_server = axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+        .into_future();
+
+    // servers stop accepting on the signal and finish in-flight requests; the
+    // drain deadline starts counting from the signal, not from startup
+    let drain_timeout = Duration::from_secs(args.drain_timeout_secs);
+    let drained = async {
+        http_server.await?;
+        if let Some(grpc_server) = grpc_server {
+            grpc_server.await?;
+        }
+        extract_llm.shutdown().await;
+        verify_llm.shutdown().await;
+        for (_, voter) in &voters {
+            voter.llm.shutdown().await;
+        }
+        anyhow::Ok(())
+    };
+    tokio::select! {
+        result = drained => result?,
+        _ = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(drain_timeout).await;
+        } => tracing::warn!(timeout_secs = args.drain_timeout_secs, "drain deadline exceeded; dropping in-flight work"),
+    }
+
+    llm_cache.close()?;
+    web_cache.close()?;
+    tracing::info!("veriscore-rewardd stopped");
     Ok(())
 }
+
+/// What every LLM backend and stage is built with.
+struct LlmDeps {
+    cache: Arc<LlmCache>,
+    cost: CostModel,
+    /// Fills in usage for OpenAI-compatible servers that leave it out.
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    /// Each stage hedges against its own load limit.
+    hedge: Option<HedgeConfig>,
+    meter: Arc<UsageMeter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StageKind {
+    Extraction,
+    Verification,
+}
+
+impl StageKind {
+    /// Names the stage's fixture file.
+    fn role(self) -> &'static str {
+        match self {
+            StageKind::Extraction => "extractor",
+            StageKind::Verification => "verifier",
+        }
+    }
+
+    /// The stage's key in `GET /admin/usage`.
+    fn usage_key(self) -> &'static str {
+        match self {
+            StageKind::Extraction => "extraction",
+            StageKind::Verification => "verification",
+        }
+    }
+}
+
+/// A stage's micro-batched LLM, its readiness probe, and its [`RoutingLlm`]
+/// for `/admin/routing` when it is routed.
+struct StageLlm {
+    llm: Arc<BatchedLlm>,
+    probe: Arc<dyn HealthProbe>,
+    routing: Option<Arc<RoutingLlm>>,
+}
+
+/// Builds a stage's whole LLM stack: the routed backend, fixture recording or
+/// replay, hedging and the micro-batcher. The extractor, the verifier and
+/// every voter get one each.
+fn build_stage_llm(
+    kind: StageKind,
+    backend: LlmBackend,
+    model: &str,
+    fallback_model: Option<&str>,
+    sampling: SamplingParams,
+    args: &Args,
+    deps: &LlmDeps,
+) -> Result<StageLlm> {
+    let (mut llm, probe, routing) = build_stage(backend, model, fallback_model, &sampling, args, deps)?;
+    // fixtures are keyed by model and sampling, so voters share the stage's file
+    if let Some(dir) = &args.record_fixtures {
+        llm = Arc::new(RecordingLlm::new(llm, model, sampling.clone(), format!("{dir}/{}.jsonl", kind.role()))?);
+    }
+    if let Some(dir) = &args.replay_fixtures {
+        llm = Arc::new(ReplayLlm::from_file(format!("{dir}/{}.jsonl", kind.role()), model, sampling)?);
+    }
+    match &deps.hedge {
+        // prompts are hedged one at a time, which would break up provider batches
+        Some(config) if !matches!(backend, LlmBackend::ArrayBatch | LlmBackend::OpenaiBatch) => {
+            let (meter, stage) = (deps.meter.clone(), kind.usage_key());
+            let sink: LoserSink = Arc::new(move |c| meter.record_stage(stage, &StageUsage::of(&[c])));
+            llm = Arc::new(HedgedLlm::new(llm, config.clone()).with_loser_sink(sink));
+        }
+        _ => {}
+    }
+    Ok(StageLlm { llm: Arc::new(BatchedLlm::spawn(llm, args.micro_batch(backend))), probe, routing })
+}
+
+type Stage = (Arc<dyn Llm>, Arc<dyn HealthProbe>, Option<Arc<RoutingLlm>>);
+
+/// A stage's backend and readiness probe. With replicas or a fallback model
+/// the backend is a [`RoutingLlm`] over all of them, also returned for
+/// `/admin/routing`, and the stage is ready while any endpoint is.
+fn build_stage(
+    backend: LlmBackend,
+    model: &str,
+    fallback_model: Option<&str>,
+    sampling: &SamplingParams,
+    args: &Args,
+    deps: &LlmDeps,
+) -> Result<Stage> {
+    let replicas: &[String] = match backend {
+        LlmBackend::Openai | LlmBackend::ArrayBatch => &args.openai_replica_urls,
+        LlmBackend::Anthropic | LlmBackend::OpenaiBatch => &[],
+    };
+    if replicas.is_empty() && fallback_model.is_none() {
+        let (llm, probe) = build_llm(backend, model, sampling.clone(), None, args, deps)?;
+        return Ok((llm, probe, None));
+    }
+
+    let mut probes = Vec::new();
+    let mut endpoint = |backend, model: &str, base_url: Option<&String>| -> Result<(String, Arc<dyn Llm>)> {
+        let (llm, probe) = build_llm(backend, model, sampling.clone(), base_url.cloned(), args, deps)?;
+        let name = match base_url {
+            Some(url) => format!("{model}@{url}"),
+            None => model.to_string(),
+        };
+        probes.push((name.clone(), probe));
+        Ok((name, llm))
+    };
+    let mut primary = vec![endpoint(backend, model, None)?];
+    for url in replicas {
+        primary.push(endpoint(backend, model, Some(url))?);
+    }
+    let mut routing = RoutingLlm::new(primary)
+        .with_policy(match args.routing_policy {
+            RoutingPolicyArg::RoundRobin => RoutingPolicy::RoundRobin,
+            RoutingPolicyArg::LeastOutstanding => RoutingPolicy::LeastOutstanding,
+        })
+        .with_circuit(CircuitConfig {
+            failure_threshold: args.circuit_failures.max(1),
+            open_for: Duration::from_secs(args.circuit_open_secs),
+        });
+    if let Some(fallback) = fallback_model {
+        routing = routing.with_fallback_tier(vec![endpoint(args.fallback_backend, fallback, args.fallback_base_url.as_ref())?]);
+    }
+    let routing = Arc::new(routing);
+    Ok((routing.clone(), Arc::new(AnyProbe::new(probes)), Some(routing)))
+}
+
+/// One backend endpoint, also returned as its readiness probe. `base_url`
+/// overrides the backend's `--*-base-url`.
+fn build_llm(
+    backend: LlmBackend,
+    model: &str,
+    sampling: SamplingParams,
+    base_url: Option<String>,
+    args: &Args,
+    deps: &LlmDeps,
+) -> Result<(Arc<dyn Llm>, Arc<dyn HealthProbe>)> {
+    let LlmDeps { cache, cost, token_counter, .. } = deps;
+    let price = cost.model(model);
+    if price.is_none() && !cost.models.is_empty() {
+        tracing::warn!(model, "no price for model in --cost-model; its calls count as $0");
+    }
+    Ok(match backend {
+        LlmBackend::Openai => {
+            let mut llm = OpenAiCompatibleLlm::new(
+                model,
+                base_url.or_else(|| args.openai_base_url.clone()),
+                args.openai_api_key.clone(),
+                args.llm_concurrency,
+                Some(cache.clone()),
+            )
+            .with_sampling(sampling);
+            if let Some(counter) = token_counter {
+                llm = llm.with_token_counter(counter.clone());
+            }
+            if let Some(price) = price {
+                llm = llm.with_price(price);
+            }
+            let llm = Arc::new(llm);
+            (llm.clone(), llm)
+        }
+        LlmBackend::ArrayBatch => {
+            let base_url = base_url.or_else(|| args.openai_base_url.clone()).unwrap_or_else(|| OPENAI_API_BASE.to_string());
+            let batch_url = format!("{}/{}", base_url.trim_end_matches('/'), args.array_batch_path.trim_start_matches('/'));
+            let mut llm = ArrayBatchLlm::new(model, batch_url, args.openai_api_key.clone(), args.llm_concurrency, Some(cache.clone()))
+                .with_max_batch_size(args.array_batch_max_size)
+                .with_sampling(sampling);
+            if let Some(counter) = token_counter {
+                llm = llm.with_token_counter(counter.clone());
+            }
+            if let Some(price) = price {
+                llm = llm.with_price(price);
+            }
+            // the batch endpoint has no model listing; probe the server's /models
+            let probe = OpenAiCompatibleLlm::new(model, Some(base_url), args.openai_api_key.clone(), 1, None);
+            (Arc::new(llm), Arc::new(probe))
+        }
+        LlmBackend::OpenaiBatch => {
+            let store = BatchStore::open(&args.openai_batch_db)?;
+            let pending = store.pending_batches()?;
+            if !pending.is_empty() {
+                tracing::info!(model, batches = ?pending, "OpenAI batches from an earlier run are resumed when their prompts come up again");
+            }
+            let base_url = base_url.or_else(|| args.openai_base_url.clone());
+            let mut llm = OpenAiBatchLlm::new(model, base_url.clone(), args.openai_api_key.clone(), store, Some(cache.clone()))
+                .with_poll_interval(Duration::from_secs(args.openai_batch_poll_secs))
+                .with_sampling(sampling);
+            if let Some(counter) = token_counter {
+                llm = llm.with_token_counter(counter.clone());
+            }
+            if let Some(price) = price {
+                llm = llm.with_price(price);
+            }
+            let probe = OpenAiCompatibleLlm::new(model, base_url, args.openai_api_key.clone(), 1, None);
+            (Arc::new(llm), Arc::new(probe))
+        }
+        LlmBackend::Anthropic => {
+            let api_key = args
+                .anthropic_api_key
+                .clone()
+                .context("--anthropic-api-key (or ANTHROPIC_API_KEY) is required for the anthropic backend")?;
+            let mut llm =
+                AnthropicLlm::new(
+                    model,
+                    base_url.or_else(|| args.anthropic_base_url.clone()),
+                    api_key,
+                    args.llm_concurrency,
+                    Some(cache.clone()),
+                )
+                    .with_sampling(sampling);
+            if let Some(price) = price {
+                llm = llm.with_price(price);
+            }
+            let llm = Arc::new(llm);
+            (llm.clone(), llm)
+        }
+    })
+}
+
+async fn shutdown_signal() -> std::io::Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = sigterm.recv() => Ok(()),
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use veriscore_core::error::CoreError;
+use veriscore_core::types::{SearchUsage, StageUsage};
+use veriscore_llm::usage::ModelPrice;
+
+/// What LLM tokens and web searches cost, keyed by model id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostModel {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelPrice>,
+    /// USD per search that misses the web cache.
+    #[serde(default)]
+    pub search_usd: f64,
+}
+
+impl CostModel {
+    /// Loads `{"models": {"<model>": {"input_per_mtok", "output_per_mtok"}, ...}, "search_usd"}`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let raw = std::fs::read_to_string(path.as_ref())
+            .with_context(|| format!("failed to read cost model {}", path.as_ref().display()))?;
+        serde_json::from_str(&raw).context("invalid cost model")
+    }
+
+    pub fn model(&self, model: &str) -> Option<ModelPrice> {
+        self.models.get(model).copied()
+    }
+}
+
+/// Dollars and tokens charged to a [`BudgetTracker`] so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spend {
+    pub usd: f64,
+    pub tokens: u64,
+}
+
+/// Spending caps for one run (a reward request or a batch job). The pipeline
+/// checks the caps before each stage and the reward engine runs budgeted
+/// completions one at a time, so a run ends at most one stage over budget.
+#[derive(Debug)]
+pub struct BudgetTracker {
+    max_usd: Option<f64>,
+    max_tokens: Option<u64>,
+    spent: Mutex<Spend>,
+}
+
+impl BudgetTracker {
+    pub fn new(max_usd: Option<f64>, max_tokens: Option<u64>) -> Self {
+        Self { max_usd, max_tokens, spent: Mutex::new(Spend::default()) }
+    }
+
+    pub fn charge_llm(&self, usage: &StageUsage) {
+        let mut spent = self.spent.lock().expect("budget poisoned");
+        spent.usd += usage.cost_usd;
+        spent.tokens += usage.total_tokens();
+    }
+
+    pub fn charge_search(&self, usage: &SearchUsage) {
+        self.spent.lock().expect("budget poisoned").usd += usage.cost_usd;
+    }
+
+    pub fn spent(&self) -> Spend {
+        *self.spent.lock().expect("budget poisoned")
+    }
+
+    /// Fails once either cap has been reached.
+    pub fn check(&self) -> Result<(), CoreError> {
+        let spent = self.spent();
+        if let Some(max) = self.max_usd.filter(|max| spent.usd >= *max) {
+            return Err(CoreError::BudgetExceeded(format!("spent ${:.4} of ${max:.4}", spent.usd)));
+        }
+        if let Some(max) = self.max_tokens.filter(|max| spent.tokens >= *max) {
+            return Err(CoreError::BudgetExceeded(format!("spent {} of {max} tokens", spent.tokens)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_trips_on_either_cap() {
+        let llm = StageUsage { calls: 1, prompt_tokens: 600, completion_tokens: 100, cost_usd: 0.02, ..Default::default() };
+        let search = SearchUsage { searches: 3, cached_searches: 0, cost_usd: 0.003 };
+
+        let dollars = BudgetTracker::new(Some(0.05), None);
+        for _ in 0..2 {
+            dollars.charge_llm(&llm);
+            dollars.charge_search(&search);
+        }
+        assert!(dollars.check().is_ok());
+        dollars.charge_llm(&llm);
+        assert!(matches!(dollars.check(), Err(CoreError::BudgetExceeded(msg)) if msg == "spent $0.0660 of $0.0500"));
+
+        let tokens = BudgetTracker::new(None, Some(1000));
+        tokens.charge_llm(&llm);
+        assert!(tokens.check().is_ok());
+        tokens.charge_llm(&llm);
+        assert_eq!(tokens.spent().tokens, 1400);
+        assert!(matches!(tokens.check(), Err(CoreError::BudgetExceeded(msg)) if msg.contains("1400 of 1000 tokens")));
+    }
+}
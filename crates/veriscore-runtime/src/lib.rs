@@ -1,6 +1,8 @@
 pub mod config;
+pub mod cost;
 pub mod metrics;
 pub mod pipeline;
 
 pub use config::RuntimeConfig;
-pub use pipeline::StatelessPipeline;
+pub use cost::{BudgetTracker, CostModel, Spend};
+pub use pipeline::{PartialUsage, PipelineSettings, StatelessPipeline};
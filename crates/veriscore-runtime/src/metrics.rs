@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
+use veriscore_core::types::{SearchUsage, StageUsage};
 
 #[derive(Debug, Clone)]
 pub struct StageTiming {
@@ -20,3 +23,50 @@ impl StageTimer {
         StageTiming { name: self.name, elapsed: self.start.elapsed() }
     }
 }
+
+/// LLM usage summed per pipeline stage since the process started.
+#[derive(Debug, Default)]
+pub struct UsageMeter {
+    totals: Mutex<UsageTotals>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    /// Pipeline runs recorded.
+    pub runs: u64,
+    pub stages: BTreeMap<&'static str, StageUsage>,
+    pub retrieval: SearchUsage,
+}
+
+impl UsageTotals {
+    /// USD across every stage and search.
+    pub fn cost_usd(&self) -> f64 {
+        self.stages.values().map(|u| u.cost_usd).sum::<f64>() + self.retrieval.cost_usd
+    }
+}
+
+impl UsageMeter {
+    /// Adds one pipeline run; stages that did not call an LLM pass `None`.
+    pub fn record(&self, stages: &[(&'static str, Option<StageUsage>)], retrieval: Option<SearchUsage>) {
+        let mut totals = self.totals.lock().expect("usage meter poisoned");
+        totals.runs += 1;
+        if let Some(retrieval) = retrieval {
+            totals.retrieval.merge(&retrieval);
+        }
+        for (stage, usage) in stages {
+            if let Some(usage) = usage {
+                totals.stages.entry(stage).or_default().merge(usage);
+            }
+        }
+    }
+
+    /// Adds LLM calls made outside any run, such as the slower attempt of a
+    /// hedged call, without counting a run.
+    pub fn record_stage(&self, stage: &'static str, usage: &StageUsage) {
+        self.totals.lock().expect("usage meter poisoned").stages.entry(stage).or_default().merge(usage);
+    }
+
+    pub fn totals(&self) -> UsageTotals {
+        self.totals.lock().expect("usage meter poisoned").clone()
+    }
+}
@@ -1,33 +1,209 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::sync::Arc;
-use veriscore_core::extraction::extract_record;
-use veriscore_core::scoring::{score_response, PerResponseScore};
-use veriscore_core::types::{InputRecord, VerificationRecord};
-use veriscore_core::verification::verify_record;
+use veriscore_core::extraction::LlmClaimExtractor;
+use veriscore_core::prompts::PromptTemplates;
+use veriscore_core::scoring::{score_response, score_response_soft, PerResponseScore};
+use veriscore_core::stages::{ClaimExtractor, ClaimVerifier};
+use veriscore_core::types::{InputRecord, SearchUsage, StageUsage, VerificationRecord};
+use veriscore_core::verification::LlmClaimVerifier;
 use veriscore_llm::traits::Llm;
-use veriscore_web::web_evidence::EvidenceProvider;
+use veriscore_web::web_evidence::{EvidenceProvider, RetrievalParams};
+
+use crate::cost::BudgetTracker;
+
+/// Per-call pipeline settings that may change between requests.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineSettings {
+    pub prompts: PromptTemplates,
+    /// `None` keeps the evidence provider's own settings.
+    pub retrieval: Option<RetrievalParams>,
+    /// Score claims by their expected support instead of 0/1; only differs
+    /// from hard scoring when the verifier sets a confidence (voting or logprobs).
+    pub soft_rewards: bool,
+    /// Charged with every stage's usage; a stage does not start once it is spent.
+    pub budget: Option<Arc<BudgetTracker>>,
+}
+
+/// Context on a `BudgetExceeded` error raised after some of the record's
+/// stages already ran: what those stages were charged.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PartialUsage {
+    pub extraction: Option<StageUsage>,
+    pub retrieval: Option<SearchUsage>,
+}
+
+impl PartialUsage {
+    pub fn find(err: &anyhow::Error) -> Option<&PartialUsage> {
+        err.downcast_ref::<PartialUsage>()
+    }
+}
+
+impl std::fmt::Display for PartialUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("budget ran out partway through the record")
+    }
+}
 
 pub struct StatelessPipeline {
-    pub extractor: Arc<dyn Llm>,
-    pub verifier: Arc<dyn Llm>,
+    pub extractor: Arc<dyn ClaimExtractor>,
+    pub verifier: Arc<dyn ClaimVerifier>,
     pub evidence: Arc<dyn EvidenceProvider>,
 }
 
 impl StatelessPipeline {
+    /// The default stages: LLM extraction and LLM verification.
+    pub fn from_llms(extractor: Arc<dyn Llm>, verifier: Arc<dyn Llm>, evidence: Arc<dyn EvidenceProvider>) -> Self {
+        Self {
+            extractor: Arc::new(LlmClaimExtractor::new(extractor)),
+            verifier: Arc::new(LlmClaimVerifier::new(verifier)),
+            evidence,
+        }
+    }
+
     pub async fn verify_and_score(
         &self,
         record: &InputRecord,
         binary: bool,
         k_median: usize,
     ) -> Result<(VerificationRecord, PerResponseScore)> {
-        let extracted = extract_record(self.extractor.as_ref(), record).await?;
-        let evidence_rows = self.evidence.fetch_evidence_for_claims(&extracted.all_claims).await?;
+        self.verify_and_score_with(record, binary, k_median, &PipelineSettings::default()).await
+    }
+
+    pub async fn verify_and_score_with(
+        &self,
+        record: &InputRecord,
+        binary: bool,
+        k_median: usize,
+        settings: &PipelineSettings,
+    ) -> Result<(VerificationRecord, PerResponseScore)> {
+        let budget = settings.budget.as_deref();
+        let check_budget = || budget.map_or(Ok(()), BudgetTracker::check);
+
+        check_budget()?;
+        let extracted = self.extractor.extract(record, &settings.prompts).await?;
+        if let (Some(budget), Some(usage)) = (budget, &extracted.extraction_usage) {
+            budget.charge_llm(usage);
+        }
+        let mut partial = PartialUsage { extraction: extracted.extraction_usage, retrieval: None };
+        check_budget().context(partial)?;
+        let (evidence_rows, retrieval_usage) =
+            self.evidence.fetch_evidence_with_usage(&extracted.all_claims, settings.retrieval.as_ref()).await?;
+        if let (Some(budget), Some(usage)) = (budget, &retrieval_usage) {
+            budget.charge_search(usage);
+        }
+        partial.retrieval = retrieval_usage;
         let evidence_record = veriscore_core::types::EvidenceRecord {
             claims: extracted,
             claim_snippets_dict: evidence_rows,
+            retrieval_usage,
+        };
+        check_budget().context(partial)?;
+        let verification = self.verifier.verify(evidence_record, binary, &settings.prompts).await?;
+        if let (Some(budget), Some(usage)) = (budget, &verification.verification_usage) {
+            budget.charge_llm(usage);
+        }
+        let score = if settings.soft_rewards {
+            score_response_soft(&verification, k_median)
+        } else {
+            score_response(&verification, k_median)
         };
-        let verification = verify_record(self.verifier.as_ref(), evidence_record, binary, 1).await?;
-        let score = score_response(&verification, k_median);
         Ok((verification, score))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use veriscore_core::types::VerificationLabel;
+    use veriscore_llm::{Llm, RecordingLlm, ReplayLlm, SamplingParams};
+    use veriscore_web::{RecordingSearcher, ReplaySearcher, WebEvidenceProvider};
+
+    /// Replays fixtures in the format `RecordingLlm` and `RecordingSearcher`
+    /// write. The `eiffel` ones are synthetic: written by hand in that format
+    /// rather than recorded from live backends, so their responses and usage
+    /// numbers are illustrative. A change to the prompts or segmentation
+    /// misses them; record real ones with `--record-fixtures` when that
+    /// change is intended.
+    fn replayed(name: &str) -> StatelessPipeline {
+        replayed_from(&format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR")))
+    }
+
+    fn replayed_from(dir: &str) -> StatelessPipeline {
+        let searcher = ReplaySearcher::from_file(format!("{dir}/search.jsonl")).unwrap();
+        let llm = |file: &str| Arc::new(ReplayLlm::from_file(format!("{dir}/{file}"), "gpt-4o-mini", SamplingParams::default()).unwrap());
+        StatelessPipeline::from_llms(
+            llm("extractor.jsonl"),
+            llm("verifier.jsonl"),
+            Arc::new(WebEvidenceProvider::new(Arc::new(searcher), 2, 8, None)),
+        )
+    }
+
+    fn eiffel_record() -> InputRecord {
+        InputRecord {
+            question: Some("Who built the Eiffel Tower and when?".into()),
+            response: "The Eiffel Tower was designed by the engineering firm of Gustave Eiffel. It was completed in 1899. It stands in Paris."
+                .into(),
+            model: None,
+            prompt_source: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn replayed_synthetic_fixtures_score_as_expected() {
+        let record = eiffel_record();
+        let (verification, score) = replayed("eiffel").verify_and_score(&record, true, 3).await.unwrap();
+
+        let labels: Vec<_> = verification
+            .claim_verification_result
+            .iter()
+            .map(|c| (c.claim.as_str(), matches!(c.verification_result, VerificationLabel::Supported)))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                ("The Eiffel Tower was designed by the engineering firm of Gustave Eiffel.", true),
+                ("The Eiffel Tower was completed in 1899.", false),
+                ("The Eiffel Tower stands in Paris.", true),
+            ]
+        );
+        assert_eq!((score.supported, score.total), (2, 3));
+        assert!((score.f1 - 2.0 / 3.0).abs() < 1e-6);
+        let usage = verification.verification_usage.unwrap();
+        assert_eq!((usage.calls, usage.prompt_tokens), (3, 1965));
+
+        let unrecorded = InputRecord { response: "The Eiffel Tower is 330 metres tall.".into(), ..record };
+        let Err(err) = replayed("eiffel").verify_and_score(&unrecorded, true, 3).await else {
+            panic!("an unrecorded response must not be scored");
+        };
+        assert!(err.to_string().contains("no recorded response"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn recorded_run_replays_the_same() {
+        let dir = std::env::temp_dir().join(format!("veriscore-runtime-record-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let out = dir.to_str().unwrap();
+
+        // the synthetic fixtures stand in for live backends
+        let live = format!("{}/fixtures/eiffel", env!("CARGO_MANIFEST_DIR"));
+        let backend = |file: &str| -> Arc<dyn Llm> {
+            Arc::new(ReplayLlm::from_file(format!("{live}/{file}"), "gpt-4o-mini", SamplingParams::default()).unwrap())
+        };
+        let recorder = |file: &str| {
+            Arc::new(RecordingLlm::new(backend(file), "gpt-4o-mini", SamplingParams::default(), format!("{out}/{file}")).unwrap())
+        };
+        let searcher = RecordingSearcher::new(Arc::new(ReplaySearcher::from_file(format!("{live}/search.jsonl")).unwrap()), format!("{out}/search.jsonl"))
+            .unwrap();
+        let recording = StatelessPipeline::from_llms(
+            recorder("extractor.jsonl"),
+            recorder("verifier.jsonl"),
+            Arc::new(WebEvidenceProvider::new(Arc::new(searcher), 2, 8, None)),
+        );
+        let (recorded, recorded_score) = recording.verify_and_score(&eiffel_record(), true, 3).await.unwrap();
+
+        let (replayed, replayed_score) = replayed_from(out).verify_and_score(&eiffel_record(), true, 3).await.unwrap();
+        assert_eq!(serde_json::to_value(&replayed).unwrap(), serde_json::to_value(&recorded).unwrap());
+        assert_eq!((replayed_score.supported, replayed_score.total), (recorded_score.supported, recorded_score.total));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -5,7 +5,8 @@ use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct WebCache {
-    conn: Arc<Mutex<Connection>>,
+    // `None` once closed; later lookups miss and writes are dropped
+    conn: Arc<Mutex<Option<Connection>>>,
 }
 
 impl WebCache {
@@ -19,15 +20,18 @@ impl WebCache {
             );
             "#,
         )?;
-        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+        Ok(Self { conn: Arc::new(Mutex::new(Some(conn))) })
     }
 
     pub fn make_key(query: &str, top_k: usize) -> String {
-        format!("{}:{}", top_k, format!("{:x}", md5::compute(query.as_bytes())))
+        format!("{}:{:x}", top_k, md5::compute(query.as_bytes()))
     }
 
     pub fn get(&self, key: &str) -> Result<Option<String>> {
-        let conn = self.conn.lock().expect("web cache poisoned");
+        let guard = self.conn.lock().expect("web cache poisoned");
+        let Some(conn) = guard.as_ref() else {
+            return Ok(None);
+        };
         let mut stmt = conn.prepare("SELECT response_json FROM web_cache WHERE cache_key = ?1")?;
         let mut rows = stmt.query(params![key])?;
         if let Some(row) = rows.next()? {
@@ -38,11 +42,32 @@ impl WebCache {
     }
 
     pub fn put(&self, key: &str, response_json: &str) -> Result<()> {
-        let conn = self.conn.lock().expect("web cache poisoned");
+        let guard = self.conn.lock().expect("web cache poisoned");
+        let Some(conn) = guard.as_ref() else {
+            return Ok(());
+        };
         conn.execute(
             "INSERT OR REPLACE INTO web_cache(cache_key, response_json) VALUES(?1, ?2)",
             params![key, response_json],
         )?;
         Ok(())
     }
+
+    /// Checks the database is open and can take a write lock.
+    pub fn probe(&self) -> Result<()> {
+        let guard = self.conn.lock().expect("web cache poisoned");
+        let conn = guard.as_ref().ok_or_else(|| anyhow::anyhow!("web cache is closed"))?;
+        conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;")?;
+        Ok(())
+    }
+
+    /// Waits for any in-progress write and closes the connection. Shared by
+    /// every clone of this cache.
+    pub fn close(&self) -> Result<()> {
+        let conn = self.conn.lock().expect("web cache poisoned").take();
+        if let Some(conn) = conn {
+            conn.close().map_err(|(_, err)| err)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,62 @@
+use reqwest::StatusCode;
+
+/// Failures from a web search backend. Like `LlmError`, these ride inside the
+/// `anyhow` chain returned by `Searcher`; recover them with [`SearchError::find`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SearchError {
+    #[error("search backend rate limited the request: {0}")]
+    RateLimited(String),
+    #[error("search backend quota exhausted: {0}")]
+    QuotaExhausted(String),
+    #[error("search backend rejected the API key: {0}")]
+    Unauthorized(String),
+    #[error("search backend unavailable: {0}")]
+    Unavailable(String),
+    #[error("search backend rejected the request: {0}")]
+    Rejected(String),
+    #[error("search backend returned a malformed response: {0}")]
+    BadResponse(String),
+}
+
+impl SearchError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, SearchError::RateLimited(_) | SearchError::Unavailable(_) | SearchError::BadResponse(_))
+    }
+
+    pub fn find(err: &anyhow::Error) -> Option<&SearchError> {
+        err.chain().find_map(|e| e.downcast_ref::<SearchError>())
+    }
+
+    /// Classifies a non-success HTTP response. Serper reports exhausted credits
+    /// as a 400 with a "credits" message rather than a dedicated status.
+    pub fn from_status(status: StatusCode, body: &str) -> Self {
+        let message = format!("{status}: {}", body.trim());
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => SearchError::RateLimited(message),
+            StatusCode::PAYMENT_REQUIRED => SearchError::QuotaExhausted(message),
+            _ if body.to_ascii_lowercase().contains("credits") => SearchError::QuotaExhausted(message),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => SearchError::Unauthorized(message),
+            s if s.is_server_error() => SearchError::Unavailable(message),
+            _ => SearchError::Rejected(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_http_failures() {
+        assert!(matches!(SearchError::from_status(StatusCode::TOO_MANY_REQUESTS, ""), SearchError::RateLimited(_)));
+        assert!(matches!(
+            SearchError::from_status(StatusCode::BAD_REQUEST, r#"{"message":"Not enough credits"}"#),
+            SearchError::QuotaExhausted(_)
+        ));
+        assert!(matches!(SearchError::from_status(StatusCode::FORBIDDEN, "bad key"), SearchError::Unauthorized(_)));
+        let down = SearchError::from_status(StatusCode::BAD_GATEWAY, "");
+        assert!(matches!(down, SearchError::Unavailable(_)));
+        assert!(down.is_retryable());
+        assert!(!SearchError::from_status(StatusCode::BAD_REQUEST, "bad query").is_retryable());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::serper::{Searcher, SerperItem};
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use veriscore_common::fixtures::{FixtureWriter, Fixtures};
+
+/// What a search is recorded under; `top_k` is `None` for the backend's default.
+#[derive(Serialize)]
+struct SearchRequest<'a> {
+    query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<usize>,
+}
+
+/// Passes searches through to another [`Searcher`] and records each query
+/// and its results, to be served later by [`ReplaySearcher`].
+pub struct RecordingSearcher {
+    inner: Arc<dyn Searcher>,
+    writer: FixtureWriter,
+}
+
+impl RecordingSearcher {
+    pub fn new(inner: Arc<dyn Searcher>, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { inner, writer: FixtureWriter::create(path)? })
+    }
+}
+
+#[async_trait::async_trait]
+impl Searcher for RecordingSearcher {
+    async fn search(&self, query: &str) -> Result<Vec<SerperItem>> {
+        let items = self.inner.search(query).await?;
+        self.writer.record(&SearchRequest { query, top_k: None }, &items)?;
+        Ok(items)
+    }
+
+    async fn search_top_k(&self, query: &str, top_k: usize) -> Result<Vec<SerperItem>> {
+        let items = self.inner.search_top_k(query, top_k).await?;
+        self.writer.record(&SearchRequest { query, top_k: Some(top_k) }, &items)?;
+        Ok(items)
+    }
+}
+
+/// Serves results recorded by [`RecordingSearcher`]; an unrecorded query
+/// fails the search.
+pub struct ReplaySearcher {
+    fixtures: Fixtures<Vec<SerperItem>>,
+}
+
+impl ReplaySearcher {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { fixtures: Fixtures::load(path)? })
+    }
+}
+
+#[async_trait::async_trait]
+impl Searcher for ReplaySearcher {
+    async fn search(&self, query: &str) -> Result<Vec<SerperItem>> {
+        self.fixtures.get(&SearchRequest { query, top_k: None })
+    }
+
+    async fn search_top_k(&self, query: &str, top_k: usize) -> Result<Vec<SerperItem>> {
+        self.fixtures.get(&SearchRequest { query, top_k: Some(top_k) })
+    }
+}
@@ -1,5 +1,9 @@
 pub mod cache;
+pub mod error;
+pub mod fixtures;
 pub mod serper;
 pub mod web_evidence;
 
-pub use web_evidence::{EvidenceProvider, WebEvidenceProvider};
+pub use error::SearchError;
+pub use fixtures::{RecordingSearcher, ReplaySearcher};
+pub use web_evidence::{EvidenceProvider, RetrievalParams, WebEvidenceProvider};
@@ -1,7 +1,11 @@
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use veriscore_common::hedge::{HedgeConfig, HedgeStats, Hedger};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::error::SearchError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerperItem {
     pub title: String,
     pub link: String,
@@ -17,6 +21,11 @@ struct SerperResponse {
 #[async_trait::async_trait]
 pub trait Searcher: Send + Sync {
     async fn search(&self, query: &str) -> Result<Vec<SerperItem>>;
+
+    /// Asks for `top_k` results; backends without a result-count knob ignore it.
+    async fn search_top_k(&self, query: &str, _top_k: usize) -> Result<Vec<SerperItem>> {
+        self.search(query).await
+    }
 }
 
 #[derive(Clone)]
@@ -36,23 +45,31 @@ impl Serper {
     }
 
     pub async fn search_impl(&self, query: &str) -> Result<Vec<SerperItem>> {
+        self.search_num(query, self.default_top_k).await
+    }
+
+    async fn search_num(&self, query: &str, num: usize) -> Result<Vec<SerperItem>> {
         let response = self.http
             .post("https://google.serper.dev/search")
             .header("X-API-KEY", &self.api_key)
             .json(&serde_json::json!({
                 "q": query,
-                "num": self.default_top_k,
+                "num": num,
             }))
             .send()
             .await
-            .context("failed to call Serper")?
-            .error_for_status()
-            .context("Serper returned non-success status")?;
+            .map_err(|e| SearchError::Unavailable(format!("failed to call Serper: {e}")))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(SearchError::from_status(status, &body).into());
+        }
 
         let parsed: SerperResponse = response
             .json()
             .await
-            .context("failed to decode Serper response")?;
+            .map_err(|e| SearchError::BadResponse(format!("failed to decode Serper response: {e}")))?;
 
         Ok(parsed.organic)
     }
@@ -63,4 +80,72 @@ impl Searcher for Serper {
     async fn search(&self, query: &str) -> Result<Vec<SerperItem>> {
         self.search_impl(query).await
     }
-}
\ No newline at end of file
+
+    async fn search_top_k(&self, query: &str, top_k: usize) -> Result<Vec<SerperItem>> {
+        self.search_num(query, top_k).await
+    }
+}
+
+/// A [`Searcher`] whose slow searches are hedged. Duplicates reach the
+/// search API, so they are billed but not counted in `SearchUsage`.
+pub struct HedgedSearcher {
+    inner: Arc<dyn Searcher>,
+    hedger: Hedger,
+}
+
+impl HedgedSearcher {
+    pub fn new(inner: Arc<dyn Searcher>, config: HedgeConfig) -> Self {
+        Self { inner, hedger: Hedger::new(config) }
+    }
+
+    pub fn stats(&self) -> HedgeStats {
+        self.hedger.stats()
+    }
+}
+
+#[async_trait::async_trait]
+impl Searcher for HedgedSearcher {
+    async fn search(&self, query: &str) -> Result<Vec<SerperItem>> {
+        self.hedger.run(|| self.inner.search(query)).await
+    }
+
+    async fn search_top_k(&self, query: &str, top_k: usize) -> Result<Vec<SerperItem>> {
+        self.hedger.run(|| self.inner.search_top_k(query, top_k)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+    use veriscore_common::hedge::HedgeDelay;
+
+    /// The first search stalls; later ones answer at once.
+    struct Stalling {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Searcher for Stalling {
+        async fn search(&self, query: &str) -> Result<Vec<SerperItem>> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            Ok(vec![SerperItem { title: query.into(), link: String::new(), snippet: String::new() }])
+        }
+    }
+
+    #[tokio::test]
+    async fn stalled_search_is_answered_by_the_hedge() {
+        let searcher = HedgedSearcher::new(
+            Arc::new(Stalling { calls: AtomicUsize::new(0) }),
+            HedgeConfig { delay: HedgeDelay::Fixed(Duration::from_millis(20)), max_extra_load: 1.0, max_in_flight: 1 },
+        );
+        let started = Instant::now();
+        let items = searcher.search("q").await.unwrap();
+        assert_eq!(items[0].title, "q");
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(searcher.stats().hedge_wins, 1);
+    }
+}
@@ -1,63 +1,135 @@
 use crate::cache::WebCache;
 use crate::serper::{SerperItem};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::task::JoinSet;
-use veriscore_core::types::EvidenceItem;
+use veriscore_core::types::{EvidenceItem, SearchUsage};
 
 #[async_trait::async_trait]
 pub trait EvidenceProvider: Send + Sync {
     async fn fetch_evidence_for_claims(&self, claims: &[String]) -> Result<Vec<(String, Vec<EvidenceItem>)>>;
+
+    /// Like `fetch_evidence_for_claims`, but with per-call retrieval settings.
+    /// Providers without tunable retrieval ignore `params`.
+    async fn fetch_evidence_with(&self, claims: &[String], _params: &RetrievalParams) -> Result<Vec<(String, Vec<EvidenceItem>)>> {
+        self.fetch_evidence_for_claims(claims).await
+    }
+
+    /// Like `fetch_evidence_with` (or `fetch_evidence_for_claims` when
+    /// `params` is `None`), plus the searches it ran. Providers that do not
+    /// track searches report `None`.
+    async fn fetch_evidence_with_usage(
+        &self,
+        claims: &[String],
+        params: Option<&RetrievalParams>,
+    ) -> Result<(Vec<(String, Vec<EvidenceItem>)>, Option<SearchUsage>)> {
+        let rows = match params {
+            Some(params) => self.fetch_evidence_with(claims, params).await?,
+            None => self.fetch_evidence_for_claims(claims).await?,
+        };
+        Ok((rows, None))
+    }
+}
+
+/// How many snippets to keep per claim and how many searches to run at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RetrievalParams {
+    pub top_k: usize,
+    pub concurrency: usize,
 }
 
 #[derive(Clone)]
 pub struct WebEvidenceProvider {
     serper: Arc<dyn crate::serper::Searcher>,
-    top_k: usize,
-    concurrency: usize,
+    params: RetrievalParams,
     cache: Option<Arc<WebCache>>,
+    search_price: f64,
 }
 
 impl WebEvidenceProvider {
     pub fn new(serper: Arc<dyn crate::serper::Searcher>, top_k: usize, concurrency: usize, cache: Option<Arc<WebCache>>) -> Self {
-        Self { serper, top_k, concurrency: concurrency.max(1), cache }
+        Self { serper, params: RetrievalParams { top_k, concurrency }, cache, search_price: 0.0 }
+    }
+
+    /// USD charged per search that misses the cache.
+    pub fn with_search_price(mut self, usd: f64) -> Self {
+        self.search_price = usd;
+        self
+    }
+
+    /// The settings used when a caller does not pass its own.
+    pub fn params(&self) -> &RetrievalParams {
+        &self.params
     }
 }
 
 #[async_trait::async_trait]
 impl EvidenceProvider for WebEvidenceProvider {
     async fn fetch_evidence_for_claims(&self, claims: &[String]) -> Result<Vec<(String, Vec<EvidenceItem>)>> {
+        self.fetch_evidence_with(claims, &self.params).await
+    }
+
+    async fn fetch_evidence_with(&self, claims: &[String], params: &RetrievalParams) -> Result<Vec<(String, Vec<EvidenceItem>)>> {
+        Ok(self.fetch(claims, params).await?.0)
+    }
+
+    async fn fetch_evidence_with_usage(
+        &self,
+        claims: &[String],
+        params: Option<&RetrievalParams>,
+    ) -> Result<(Vec<(String, Vec<EvidenceItem>)>, Option<SearchUsage>)> {
+        let (rows, usage) = self.fetch(claims, params.unwrap_or(&self.params)).await?;
+        Ok((rows, Some(usage)))
+    }
+}
+
+impl WebEvidenceProvider {
+    async fn fetch(&self, claims: &[String], params: &RetrievalParams) -> Result<(Vec<(String, Vec<EvidenceItem>)>, SearchUsage)> {
+        let mut out = Vec::with_capacity(claims.len());
+        let mut usage = SearchUsage::default();
+        let mut finish = |result: Result<Result<(String, Vec<EvidenceItem>, bool)>, tokio::task::JoinError>| -> Result<()> {
+            let (claim, items, cached) = result??;
+            usage.searches += 1;
+            usage.cached_searches += u32::from(cached);
+            out.push((claim, items));
+            Ok(())
+        };
+
         let mut join_set = JoinSet::new();
         for claim in claims.iter().cloned() {
             let serper = self.serper.clone();
             let cache = self.cache.clone();
-            let top_k = self.top_k;
+            let top_k = params.top_k;
             join_set.spawn(async move {
                 let cache_key = cache.as_ref().map(|_| WebCache::make_key(&claim, top_k));
                 if let (Some(cache), Some(key)) = (cache.as_ref(), cache_key.as_deref()) {
                     if let Some(hit) = cache.get(key)? {
                         let items: Vec<EvidenceItem> = serde_json::from_str(&hit)?;
-                        return Ok::<_, anyhow::Error>((claim, items));
+                        return Ok::<_, anyhow::Error>((claim, items, true));
                     }
                 }
-                let raw = serper.search(&claim).await?;
+                let raw = serper.search_top_k(&claim, top_k).await?;
                 let items = raw.into_iter().map(to_evidence_item).take(top_k).collect::<Vec<_>>();
                 if let (Some(cache), Some(key)) = (cache.as_ref(), cache_key.as_deref()) {
                     cache.put(key, &serde_json::to_string(&items)?)?;
                 }
-                Ok::<_, anyhow::Error>((claim, items))
+                Ok::<_, anyhow::Error>((claim, items, false))
             });
-            if join_set.len() >= self.concurrency {
-                let _ = join_set.join_next().await;
+            if join_set.len() >= params.concurrency.max(1) {
+                if let Some(result) = join_set.join_next().await {
+                    finish(result)?;
+                }
             }
         }
-
-        let mut out = Vec::with_capacity(claims.len());
         while let Some(result) = join_set.join_next().await {
-            out.push(result??);
+            finish(result)?;
         }
+
+        usage.cost_usd = f64::from(usage.searches - usage.cached_searches) * self.search_price;
         out.sort_by_key(|(claim, _)| claims.iter().position(|c| c == claim).unwrap_or(usize::MAX));
-        Ok(out)
+        Ok((out, usage))
     }
 }
 
@@ -130,6 +202,37 @@ mod tests {
         assert!(call_log.contains(&"claim b".to_string()));
     }
 
+    #[tokio::test]
+    async fn fetch_evidence_with_usage_prices_each_search() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let fake = FakeSearcher { results: vec![], calls };
+        let provider = WebEvidenceProvider::new(Arc::new(fake), 2, 8, None).with_search_price(0.001);
+
+        let claims = vec!["claim a".to_string(), "claim b".to_string()];
+        let (rows, usage) = provider.fetch_evidence_with_usage(&claims, None).await.unwrap();
+
+        assert_eq!(rows.len(), 2);
+        let usage = usage.unwrap();
+        assert_eq!((usage.searches, usage.cached_searches), (2, 0));
+        assert!((usage.cost_usd - 0.002).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn keeps_every_claim_when_claims_outnumber_concurrency() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let fake = FakeSearcher { results: vec![], calls: calls.clone() };
+        let provider = WebEvidenceProvider::new(Arc::new(fake), 2, 2, None).with_search_price(0.001);
+
+        let claims = (0..7).map(|i| format!("claim {i}")).collect::<Vec<_>>();
+        let (rows, usage) = provider.fetch_evidence_with_usage(&claims, None).await.unwrap();
+
+        assert_eq!(rows.iter().map(|(c, _)| c.clone()).collect::<Vec<_>>(), claims);
+        let usage = usage.unwrap();
+        assert_eq!(usage.searches, 7);
+        assert_eq!(calls.lock().unwrap().len(), 7);
+        assert!((usage.cost_usd - 0.007).abs() < 1e-12);
+    }
+
     #[tokio::test]
     async fn fetch_evidence_for_claims_handles_empty_input() {
         let calls = Arc::new(Mutex::new(Vec::new()));
@@ -14,12 +14,21 @@ class RewardConfig:
     k_median: int = 8
     binary: bool = True
     reward_metric: str = "f1"
+    # e.g. {"mode": "z_score", "clip": 5.0}; the response then carries "advantages"
+    advantage: Optional[Dict[str, Any]] = None
+    # sent as "Authorization: Bearer <api_key>" when the server uses --api-keys-file
+    api_key: Optional[str] = None
 
 
 class VeriScoreRewardClient:
     def __init__(self, config: RewardConfig):
         self.config = config
 
+    def _headers(self) -> Dict[str, str]:
+        if self.config.api_key is None:
+            return {}
+        return {"Authorization": f"Bearer {self.config.api_key}"}
+
     def score_batch(
         self,
         prompts: Sequence[str],
@@ -55,7 +64,11 @@ class VeriScoreRewardClient:
                 for p, c, s in zip(prompts, completions, prompt_sources)
             ],
         }
-        response = requests.post(self.config.endpoint, json=payload, timeout=self.config.timeout_s)
+        if self.config.advantage is not None:
+            payload["advantage"] = self.config.advantage
+        response = requests.post(
+            self.config.endpoint, json=payload, headers=self._headers(), timeout=self.config.timeout_s
+        )
         response.raise_for_status()
         return response.json()
 
@@ -94,8 +107,10 @@ class VeriScoreRewardClient:
                 for p, c, s in zip(prompts, completions, prompt_sources)
             ],
         }
+        if self.config.advantage is not None:
+            payload["advantage"] = self.config.advantage
         timeout = aiohttp.ClientTimeout(total=self.config.timeout_s)
-        async with aiohttp.ClientSession(timeout=timeout) as session:
+        async with aiohttp.ClientSession(timeout=timeout, headers=self._headers()) as session:
             async with session.post(self.config.endpoint, json=payload) as response:
                 response.raise_for_status()
                 return await response.json()